@@ -5,9 +5,12 @@
 
 extern crate renforce as re;
 extern crate gym;
+extern crate rand;
 
 use std::io::stdin;
 
+use rand::thread_rng;
+
 use re::environment::{Finite, Range};
 
 use re::trainer::NaturalEvo;
@@ -71,6 +74,7 @@ impl Acrobot {
 
 fn main() {
 	let mut env = Acrobot::new();
+	let mut rng = thread_rng();
 
 	let mut q_func = QLinear::default(&env.action_space());
 	for d in 0..env.state_space().len() {
@@ -87,7 +91,7 @@ fn main() {
 										   .deviation(0.01);
 
 	println!("Training...");
-	trainer.train(&mut agent, &mut env);
+	trainer.train(&mut agent, &mut env, &mut rng);
 	println!("Done training (press enter)");
 
 	env.toggle_render();
@@ -98,7 +102,7 @@ fn main() {
 	let mut obs = env.reset();
 	let mut reward = 0.0;
 	while !obs.done {
-		let action = agent.get_action(&obs.state);
+		let action = agent.get_action(&obs.state, &mut rng);
 		obs = env.step(&action);
 		reward += obs.reward;
 	}
@@ -2,9 +2,12 @@
 
 extern crate renforce as re;
 extern crate gym;
+extern crate rand;
 
 use std::io::stdin;
 
+use rand::thread_rng;
+
 use re::environment::{Environment, Observation};
 use re::environment::{Finite, Range};
 
@@ -71,6 +74,7 @@ impl CartPole {
 
 fn main() {
 	let mut env = CartPole::new();
+	let mut rng = thread_rng();
 
 	let mut q_func = QLinear::default(&env.action_space());
 	for d in 0..4 {
@@ -91,7 +95,7 @@ fn main() {
 	let mut trainer = CrossEntropy::default().eval_period(tp);
 
 	println!("Training...");
-	trainer.train(&mut agent, &mut env);
+	trainer.train(&mut agent, &mut env, &mut rng);
 	println!("Done training (press enter)");
 
 	let agent = agent.to_greedy();
@@ -102,7 +106,7 @@ fn main() {
 	let mut obs = env.reset();
 	let mut reward = 0.0;
 	while !obs.done {
-		let action = agent.get_action(&obs.state);
+		let action = agent.get_action(&obs.state, &mut rng);
 		obs = env.step(&action);
 		reward += obs.reward;
 		println!("action: {:?}", action);
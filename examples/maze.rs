@@ -1,9 +1,12 @@
 // In this example, we will train an agent to find the shorted path through a maze
 
 extern crate renforce as re;
+extern crate rand;
 
 use std::io::stdin;
 
+use rand::{SeedableRng, StdRng};
+
 use re::environment::{Environment, Observation};
 use re::environment::Finite;
 
@@ -118,6 +121,8 @@ impl Maze {
 
 fn main() {
 	let mut env = Maze::new();
+	// Seeding the rng makes training (and therefore the learned path) reproducible
+	let mut rng = StdRng::from_seed(&[1, 2, 3]);
 
 	// The agent will use a table as its Q-function
 	let q_func = QTable::new();
@@ -130,14 +135,14 @@ fn main() {
 	let mut trainer = QLearner::new(env.action_space(), 0.9, 0.9, TimePeriod::TIMESTEPS(10000));
 
 	// Magic happens
-	trainer.train(&mut agent, &mut env);
+	trainer.train(&mut agent, &mut env, &mut rng);
 
 	// Simulate one episode of the environment to see what the agent learned
 	let mut obs = env.reset();
 	while !obs.done {
 		env.render();
 
-		let action = agent.get_action(&obs.state);
+		let action = agent.get_action(&obs.state, &mut rng);
 		obs = env.step(&action);
 
 		let _ = stdin().read_line(&mut String::new());
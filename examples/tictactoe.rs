@@ -106,6 +106,7 @@ impl Board {
 
 fn main() {
 	let mut env = Board::new();
+	let mut rng = thread_rng();
 
 	let q_func = QTable::new();
 	// Creates an epsilon greedy Q-agent
@@ -121,7 +122,7 @@ fn main() {
 	let mut trainer = SARSALearner::default();
 
 	// Magic happens
-	trainer.train(&mut agent, &mut env);
+	trainer.train(&mut agent, &mut env, &mut rng);
 	// Agent will no longer explore, only exploit
 	let agent = agent.to_greedy();
 
@@ -131,7 +132,7 @@ fn main() {
 	while !obs.done {
 		env.render();
 
-		let action = agent.get_action(&obs.state);
+		let action = agent.get_action(&obs.state, &mut rng);
 		obs = env.step(&action);
 		reward += obs.reward;
 		println!("action: {:?}", action);
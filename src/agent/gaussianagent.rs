@@ -1,6 +1,7 @@
+use std::f64;
 use std::marker::PhantomData;
 
-use rand::thread_rng;
+use rand::Rng;
 use rand::distributions::IndependentSample;
 use rand::distributions::normal::Normal;
 
@@ -9,9 +10,10 @@ use num::cast::NumCast;
 
 use environment::Space;
 
-use agent::Agent;
+use agent::{Agent, StochasticAgent};
 
-use util::{LogDiffFunc, DifferentiableVecFunc, ParameterizedFunc};
+use util::{LogDiffFunc, DifferentiableVecFunc, ParameterizedFunc, Stateful};
+use util::checkpoint::CheckpointError;
 
 /// An agent that samples actions from a Normal distribution
 /// * The mean of the distribution is dependent upon the agent's state
@@ -63,25 +65,71 @@ impl<F: Float, T: Into<F> + Clone, S: Space, A: Space, D> LogDiffFunc<S, A, F> f
 			sum
 		}).collect()
 	}
+	fn log_prob(&self, state: &S::Element, action: &A::Element) -> F {
+		let action: Vec<_> = action.clone().into();
+		let mean = self.mean_func.apply(state);
+
+		let half: F = NumCast::from(0.5).unwrap();
+		let log_norm = self.deviation.ln() + NumCast::from((2.0*f64::consts::PI).ln()/2.0).unwrap();
+		(0..action.len()).fold(F::zero(), |sum, i| {
+			let z = (action[i].clone().into() - mean[i])/self.deviation;
+			sum - half*z*z - log_norm
+		})
+	}
 }
 
 impl<F: Float, T: Into<F> + Clone, S: Space, A: Space, D> Agent<S, A> for GaussianAgent<F, T, S, A, D>
 	where D: DifferentiableVecFunc<S, F>,
 		  A::Element: Into<Vec<T>> + From<Vec<F>> {
-	fn get_action(&self, state: &S::Element) -> A::Element {
-		let mut rng = thread_rng();
+	fn get_action<R: Rng>(&self, state: &S::Element, rng: &mut R) -> A::Element {
 		let mean = self.mean_func.apply(state);
 
 		let action: Vec<_> = (0..mean.len()).map(|i| {
 			let normal = Normal::new(mean[i].to_f64().unwrap(), 
 									 self.deviation.to_f64().unwrap());
-			NumCast::from(normal.ind_sample(&mut rng)).unwrap()
+			NumCast::from(normal.ind_sample(rng)).unwrap()
 		}).collect();
 
 		action.into()
 	}
 }
 
+impl<F: Float, T: Into<F> + Clone, S: Space, A: Space, D> StochasticAgent<S, A> for GaussianAgent<F, T, S, A, D>
+	where D: DifferentiableVecFunc<S, F>,
+		  A::Element: Into<Vec<T>> + From<Vec<F>> {
+	fn get_action_prob(&self, state: &S::Element, action: &A::Element) -> f64 {
+		self.log_prob(state, action).exp().to_f64().unwrap()
+	}
+}
+
+/// The learned state of a GaussianAgent
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct GaussianAgentState<F, D> {
+	/// The standard deviation of the Gaussian used by the agent
+	pub deviation: F,
+	/// State of the function used to calculate the mean of the Gaussian
+	pub mean_func: D,
+}
+
+impl<F: Float, T: Into<F> + Clone, S: Space, A: Space, D> Stateful for GaussianAgent<F, T, S, A, D> 
+	where D: DifferentiableVecFunc<S, F> + Stateful,
+		  A::Element: Into<Vec<T>> {
+	type State = GaussianAgentState<F, D::State>;
+
+	fn get_state(&self) -> GaussianAgentState<F, D::State> {
+		GaussianAgentState {
+			deviation: self.deviation,
+			mean_func: self.mean_func.get_state()
+		}
+	}
+	fn set_state(&mut self, state: GaussianAgentState<F, D::State>) -> Result<(), CheckpointError> {
+		self.mean_func.set_state(state.mean_func)?;
+		self.deviation = state.deviation;
+		Ok(())
+	}
+}
+
 impl<F: Float, T: Into<F> + Clone, S: Space, A: Space, D> GaussianAgent<F, T, S, A, D> 
 	where D: DifferentiableVecFunc<S, F>,
 		  A::Element: Into<Vec<T>> {
@@ -7,22 +7,27 @@ mod policyagent;
 mod gaussianagent;
 
 pub use self::randagent::RandomAgent;
-pub use self::policyagent::PolicyAgent;
-pub use self::gaussianagent::GaussianAgent;
+pub use self::policyagent::{PolicyAgent, PolicyAgentState};
+pub use self::gaussianagent::{GaussianAgent, GaussianAgentState};
 
-use environment::Space;
+use rand::Rng;
+
+use environment::{Space, FiniteSpace};
 
 /// Represents an agent acting in an environment
 pub trait Agent<S: Space, A: Space> {
 	/// Returns the actions the agent should perform in the given state
-	fn get_action(&self, state: &S::Element) -> A::Element;
+	/// Any randomness in the agent's behavior is drawn from rng
+	fn get_action<R: Rng>(&self, state: &S::Element, rng: &mut R) -> A::Element;
 }
 
-/*
-/// An agent that produces probabilites instead of deterministic actions
-// Should this derive Agent?
-pub trait StochasticAgent<F: Float, S: Space, A: Space> {
-	/// Returns the probability of this agent performing an action in a given state
-	fn get_action_prob(&self, state: &S::Element, action: &A::Element) -> F;
-}
-*/
\ No newline at end of file
+/// An agent whose actions are drawn from a known distribution
+pub trait StochasticAgent<S: Space, A: Space> : Agent<S, A> {
+	/// Returns the probability (or probability density) of this agent performing an action in a given state
+	fn get_action_prob(&self, state: &S::Element, action: &A::Element) -> f64;
+	/// Returns the probability of this agent performing each action in action_space, in the order given by enumerate
+	fn get_action_probs(&self, state: &S::Element, action_space: &A) -> Vec<f64>
+		where A: FiniteSpace {
+		action_space.enumerate().iter().map(|a| self.get_action_prob(state, a)).collect()
+	}
+}
\ No newline at end of file
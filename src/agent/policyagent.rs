@@ -1,12 +1,16 @@
 use std::marker::PhantomData;
 
+use rand::Rng;
+
 use num::Float;
+use num::cast::NumCast;
 
 use environment::{Space, FiniteSpace};
 
-use agent::Agent;
+use agent::{Agent, StochasticAgent};
 
-use util::{LogDiffFunc, DifferentiableFunc, ParameterizedFunc};
+use util::{LogDiffFunc, DifferentiableFunc, ParameterizedFunc, Stateful};
+use util::checkpoint::CheckpointError;
 use util::Chooser;
 use util::chooser::Softmax;
 
@@ -28,28 +32,19 @@ pub struct PolicyAgent<F: Float, S: Space, A: FiniteSpace, D: DifferentiableFunc
 	phant: PhantomData<S>,
 }
 
-/*
-impl<F: Float, S: Space, A: FiniteSpace, D> StochasticAgent<F, S, A> for PolicyAgent<F, S, A, D>
+impl<F: Float, S: Space, A: FiniteSpace, D> StochasticAgent<S, A> for PolicyAgent<F, S, A, D>
 	where D: DifferentiableFunc<S, A, F> {
-	fn get_action_prob(&self, state: &S::Element, action: &A::Element) -> F {
-		let mut weights = Vec::with_capacity(self.action_space.size());
-		for a in self.action_space.enumerate() {
-			weights.push(self.log_func.calculate(state, &a));
-		}
-
-		let mut total = F::zero();
-		for w in &mut weights {
-			*w = (*w/self.temp).exp();
-			total = total + *w;
+	fn get_action_prob(&self, state: &S::Element, action: &A::Element) -> f64 {
+		match self.actions.iter().position(|a| a == action) {
+			Some(index) => self.action_probs(state)[index],
+			None => 0.0
 		}
-
-		let index = self.action_space.index(action);
-		debug_assert!(index != -1);
-
-		weights[index as usize]/total
+	}
+	fn get_action_probs(&self, state: &S::Element, action_space: &A) -> Vec<f64> {
+		debug_assert_eq!(action_space.size(), self.actions.len());
+		self.action_probs(state)
 	}
 }
-*/
 
 impl<F: Float, S: Space, A: FiniteSpace, D> ParameterizedFunc<F> for PolicyAgent<F, S, A, D> 
 	where D: DifferentiableFunc<S, A, F> {
@@ -79,21 +74,49 @@ impl<F: Float, S: Space, A: FiniteSpace, D> DifferentiableFunc<S, A, F> for Poli
 impl<F: Float, S: Space, A: FiniteSpace, D> LogDiffFunc<S, A, F> for PolicyAgent<F, S, A, D>
 	where D: DifferentiableFunc<S, A, F> {
 	fn log_grad(&self, state: &S::Element, action: &A::Element) -> Vec<F> {
-		// Not 100% sure either of these are correct
-		self.log_func.get_grad(state, action)
-		//self.calc_log_grad(state, action)
+		self.calc_log_grad(state, action)
+	}
+	fn log_prob(&self, state: &S::Element, action: &A::Element) -> F {
+		let logits: Vec<_> = self.actions.iter().map(|a| self.log_func.calculate(state, a)/self.temp).collect();
+		let max = logits.iter().fold(F::neg_infinity(), |m, &l| m.max(l));
+		let log_total = logits.iter().fold(F::zero(), |sum, &l| sum + (l - max).exp()).ln() + max;
+
+		let index = self.actions.iter().position(|a| a == action).expect("action is not in the action space");
+		logits[index] - log_total
 	}
 }
 
 impl<F: Float, S: Space, A: FiniteSpace, D> Agent<S, A> for PolicyAgent<F, S, A, D>
 	where D: DifferentiableFunc<S, A, F> {
-	fn get_action(&self, state: &S::Element) -> A::Element {
-		let mut weights = Vec::with_capacity(self.actions.len());
-		for a in &self.actions {
-			weights.push(self.log_func.calculate(state, a).to_f64().unwrap());
-		}
+	fn get_action<R: Rng>(&self, state: &S::Element, rng: &mut R) -> A::Element {
+		Softmax::new(self.temp.to_f64().unwrap()).choose(&self.actions, self.weights(state), rng)
+	}
+}
+
+/// The learned state of a PolicyAgent
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct PolicyAgentState<F, D> {
+	/// Temperature of associated softmax
+	pub temp: F,
+	/// State of the function used to calculate softmax weights
+	pub log_func: D,
+}
 
-		Softmax::new(self.temp.to_f64().unwrap()).choose(&self.actions, weights)
+impl<F: Float, S: Space, A: FiniteSpace, D> Stateful for PolicyAgent<F, S, A, D>
+	where D: DifferentiableFunc<S, A, F> + Stateful {
+	type State = PolicyAgentState<F, D::State>;
+
+	fn get_state(&self) -> PolicyAgentState<F, D::State> {
+		PolicyAgentState {
+			temp: self.temp,
+			log_func: self.log_func.get_state()
+		}
+	}
+	fn set_state(&mut self, state: PolicyAgentState<F, D::State>) -> Result<(), CheckpointError> {
+		self.log_func.set_state(state.log_func)?;
+		self.temp = state.temp;
+		Ok(())
 	}
 }
 
@@ -130,41 +153,29 @@ impl<F: Float, S: Space, A: FiniteSpace, D: DifferentiableFunc<S, A, F>> PolicyA
 	pub fn get_temp(&self) -> F {
 		self.temp
 	}
-	/// Calculates the derivative of the log of this function
-	// Can probably be calculated more efficiently
-	// This function is correct assuming I correctly worked out the gradient
-	// Would not put all my trust in it
+
+	// The weights passed into Softmax when choosing an action in state
+	fn weights(&self, state: &S::Element) -> Vec<f64> {
+		self.actions.iter().map(|a| self.log_func.calculate(state, a).to_f64().unwrap()).collect()
+	}
+	// The probability of choosing each action in state
+	fn action_probs(&self, state: &S::Element) -> Vec<f64> {
+		Chooser::<A::Element>::probabilities(&Softmax::new(self.temp.to_f64().unwrap()), self.weights(state))
+	}
+	/// Calculates the gradient of the log probability of choosing action in state
+	///
+	/// With logits f(a)/temp, this is (grad f(action) - sum_a p(a) grad f(a))/temp
 	pub fn calc_log_grad(&self, state: &S::Element, action: &A::Element) -> Vec<F> {
-		let mut total = F::zero();
-
-		let weights = self.actions.iter()
-								  .map(|a| {
-								  	let w = (self.log_func.calculate(state, a)/self.temp).exp();
-								  	total = total + w;
-								  	w
-								  })
-								  .collect::<Vec<_>>();
-
-		let mut index = 0;
-		let mut grad = vec![F::zero(); self.log_func.num_params()];
-		for i in 0..self.actions.len() {
-			if self.actions[i] != *action {
-				let g = self.log_func.get_grad(state, &self.actions[i]);
-				for j in 0..g.len() {
-					grad[j] = grad[j] + g[j] * weights[i];
-				}
-			} else {
-				index = i;
+		let probs = self.action_probs(state);
+
+		let mut grad = self.log_func.get_grad(state, action);
+		for (a, &p) in self.actions.iter().zip(&probs) {
+			let p: F = NumCast::from(p).unwrap();
+			let g = self.log_func.get_grad(state, a);
+			for i in 0..grad.len() {
+				grad[i] = grad[i] - p*g[i];
 			}
 		}
-
-		let coeff = weights[index] - total;
-		let g = self.log_func.get_grad(state, action);
-		for i in 0..g.len() {
-			grad[i] = -(grad[i] + coeff * g[i])/(total*self.temp);
-		}
-
-		grad
+		grad.into_iter().map(|g| g/self.temp).collect()
 	}
-}
-
+}
\ No newline at end of file
@@ -5,13 +5,14 @@ use std::marker::PhantomData;
 use num::Num;
 use num::Float;
 
-use rand::{Rng, thread_rng};
+use rand::Rng;
 
 use environment::{Space, FiniteSpace};
 
-use agent::Agent;
+use agent::{Agent, StochasticAgent};
 
-use util::{ParameterizedFunc, DifferentiableFunc, QFunction, FeatureExtractor};
+use util::{ParameterizedFunc, DifferentiableFunc, QFunction, TracedQFunction, FittableQFunction, TraceType, FeatureExtractor, Stateful};
+use util::checkpoint::CheckpointError;
 use util::Chooser;
 
 // TODO: Derive macro?
@@ -27,6 +28,33 @@ macro_rules! implement_qfunction {
     }
 }
 
+macro_rules! implement_tracedqfunction {
+    () => {
+    	type Trace = Q::Trace;
+
+    	fn new_trace(&self) -> Q::Trace {
+			self.q_func.new_trace()
+		}
+		fn decay_trace(&self, trace: &mut Q::Trace, factor: f64) {
+			self.q_func.decay_trace(trace, factor)
+		}
+		fn visit(&self, trace: &mut Q::Trace, state: &S::Element, action: &A::Element, trace_type: TraceType, alpha: f64) {
+			self.q_func.visit(trace, state, action, trace_type, alpha)
+		}
+		fn update_traced(&mut self, trace: &Q::Trace, step: f64) {
+			self.q_func.update_traced(trace, step)
+		}
+    }
+}
+
+macro_rules! implement_fittableqfunction {
+    () => {
+    	fn fit<R: Rng>(&mut self, data: &[(S::Element, A::Element, f64)], rng: &mut R) {
+			self.q_func.fit(data, rng)
+		}
+    }
+}
+
 macro_rules! implement_parameterizedfunc {
     () => {
     	fn num_params(&self) -> usize {
@@ -49,6 +77,9 @@ macro_rules! implement_featureextractor {
 		fn extract(&self, state: &S::Element, action: &A::Element) -> Vec<F> {
 			self.q_func.extract(state, action)
 		}
+		fn extract_sparse(&self, state: &S::Element, action: &A::Element) -> Vec<(usize, F)> {
+			self.q_func.extract_sparse(state, action)
+		}
 	}
 }
 
@@ -63,10 +94,22 @@ macro_rules! implement_differentiablefunc {
 	}
 }
 
+// Index of the first action with the highest value in state, which is the one greedy agents choose
+fn best_index<S: Space, A: Space, Q: QFunction<S, A>>(q_func: &Q, state: &S::Element, actions: &[A::Element]) -> usize {
+	let mut best = (0, q_func.eval(state, &actions[0]));
+	for i in 1..actions.len() {
+		let val = q_func.eval(state, &actions[i]);
+		if val > best.1 {
+			best = (i, val);
+		}
+	}
+	best.0
+}
+
 /// Greedy Q-Agent
 ///
 /// Represents an agent that only performs the best action according to its QFunction
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct GreedyQAgent<S: Space, A: FiniteSpace, Q: QFunction<S, A>> {
 	/// The underlying QFunction used by the agent
 	q_func:	Q,
@@ -76,7 +119,7 @@ pub struct GreedyQAgent<S: Space, A: FiniteSpace, Q: QFunction<S, A>> {
 }
 
 impl<S: Space, A: FiniteSpace, Q: QFunction<S, A>> Agent<S, A> for GreedyQAgent<S, A, Q> {
-	fn get_action(&self, state: &S::Element) -> A::Element {
+	fn get_action<R: Rng>(&self, state: &S::Element, _: &mut R) -> A::Element {
 		let actions = self.action_space.enumerate();
 		let (mut best_action, mut best_val) = (actions[0].clone(), self.q_func.eval(state, &actions[0]));
 		
@@ -92,10 +135,31 @@ impl<S: Space, A: FiniteSpace, Q: QFunction<S, A>> Agent<S, A> for GreedyQAgent<
 	}
 }
 
+impl<S: Space, A: FiniteSpace, Q: QFunction<S, A>> StochasticAgent<S, A> for GreedyQAgent<S, A, Q> {
+	fn get_action_prob(&self, state: &S::Element, action: &A::Element) -> f64 {
+		let actions = self.action_space.enumerate();
+		if actions[best_index(&self.q_func, state, &actions)] == *action {1.0} else {0.0}
+	}
+	fn get_action_probs(&self, state: &S::Element, action_space: &A) -> Vec<f64> {
+		let actions = action_space.enumerate();
+		let mut probs = vec![0.0; actions.len()];
+		probs[best_index(&self.q_func, state, &actions)] = 1.0;
+		probs
+	}
+}
+
 impl<S: Space, A: FiniteSpace, Q: QFunction<S, A>> QFunction<S, A> for GreedyQAgent<S, A, Q> {
 	implement_qfunction!();
 }
 
+impl<S: Space, A: FiniteSpace, Q: TracedQFunction<S, A>> TracedQFunction<S, A> for GreedyQAgent<S, A, Q> {
+	implement_tracedqfunction!();
+}
+
+impl<S: Space, A: FiniteSpace, Q: FittableQFunction<S, A>> FittableQFunction<S, A> for GreedyQAgent<S, A, Q> {
+	implement_fittableqfunction!();
+}
+
 impl<N: Num, S: Space, A: FiniteSpace, Q> ParameterizedFunc<N> for GreedyQAgent<S, A, Q>
 	where Q: QFunction<S, A> + ParameterizedFunc<N> {
 	implement_parameterizedfunc!();
@@ -111,6 +175,18 @@ impl<F: Float, A: FiniteSpace, S: Space, Q> DifferentiableFunc<S, A, F> for Gree
 	implement_differentiablefunc!();
 }
 
+impl<S: Space, A: FiniteSpace, Q> Stateful for GreedyQAgent<S, A, Q>
+	where Q: QFunction<S, A> + Stateful {
+	type State = Q::State;
+
+	fn get_state(&self) -> Q::State {
+		self.q_func.get_state()
+	}
+	fn set_state(&mut self, state: Q::State) -> Result<(), CheckpointError> {
+		self.q_func.set_state(state)
+	}
+}
+
 impl<S: Space, A: FiniteSpace, Q: QFunction<S, A>> GreedyQAgent<S, A, Q> {
 	/// Returns a new GreedyQAgent with the given function and action space
 	pub fn new(q_func: Q, action_space: A) -> GreedyQAgent<S, A, Q> {
@@ -136,7 +212,7 @@ impl<S: Space, A: FiniteSpace, Q: QFunction<S, A>> GreedyQAgent<S, A, Q> {
 ///
 /// Represents an agent that acts randomly with probabilty epsilon and
 /// acts greedily with probabilty (1 - epsilon)
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct EGreedyQAgent<S: Space, A: FiniteSpace, Q: QFunction<S, A>, T: Chooser<A::Element>> {
 	/// Underlying QFunction
 	q_func: Q,
@@ -152,8 +228,7 @@ pub struct EGreedyQAgent<S: Space, A: FiniteSpace, Q: QFunction<S, A>, T: Choose
 impl<S: Space, A: FiniteSpace, Q, T> Agent<S, A> for EGreedyQAgent<S, A, Q, T>
 	where 	T: Chooser<A::Element>,
 			Q: QFunction<S, A> {
-	fn get_action(&self, state: &S::Element) -> A::Element {
-		let mut rng = thread_rng();
+	fn get_action<R: Rng>(&self, state: &S::Element, rng: &mut R) -> A::Element {
 		let mut best_action;
 
 		let actions = self.action_space.enumerate();
@@ -161,7 +236,7 @@ impl<S: Space, A: FiniteSpace, Q, T> Agent<S, A> for EGreedyQAgent<S, A, Q, T>
 			let weights = actions.iter()
 								 .map(|a| self.q_func.eval(state, a))
 								 .collect();
-			best_action = self.chooser.choose(&actions, weights);
+			best_action = self.chooser.choose(&actions, weights, rng);
 		} else {
 			let mut best_val = self.q_func.eval(state, &actions[0]);
 			
@@ -178,12 +253,41 @@ impl<S: Space, A: FiniteSpace, Q, T> Agent<S, A> for EGreedyQAgent<S, A, Q, T>
 	}
 }
 
+impl<S: Space, A: FiniteSpace, Q, T> StochasticAgent<S, A> for EGreedyQAgent<S, A, Q, T>
+	where 	T: Chooser<A::Element>,
+			Q: QFunction<S, A> {
+	fn get_action_prob(&self, state: &S::Element, action: &A::Element) -> f64 {
+		let index = self.action_space.index(action);
+		if index == -1 {0.0} else {self.get_action_probs(state, &self.action_space)[index as usize]}
+	}
+	fn get_action_probs(&self, state: &S::Element, action_space: &A) -> Vec<f64> {
+		let actions = action_space.enumerate();
+		let weights = actions.iter().map(|a| self.q_func.eval(state, a)).collect();
+
+		let mut probs: Vec<_> = self.chooser.probabilities(weights).into_iter().map(|p| self.epsilon*p).collect();
+		probs[best_index(&self.q_func, state, &actions)] += 1.0 - self.epsilon;
+		probs
+	}
+}
+
 impl<S: Space, A: FiniteSpace, Q, T> QFunction<S, A> for EGreedyQAgent<S, A, Q, T> 
 	where 	T: Chooser<A::Element>,
 			Q: QFunction<S, A> {
 	implement_qfunction!();
 }
 
+impl<S: Space, A: FiniteSpace, Q, T> TracedQFunction<S, A> for EGreedyQAgent<S, A, Q, T>
+	where 	T: Chooser<A::Element>,
+			Q: TracedQFunction<S, A> {
+	implement_tracedqfunction!();
+}
+
+impl<S: Space, A: FiniteSpace, Q, T> FittableQFunction<S, A> for EGreedyQAgent<S, A, Q, T>
+	where 	T: Chooser<A::Element>,
+			Q: FittableQFunction<S, A> {
+	implement_fittableqfunction!();
+}
+
 impl<N: Num, S: Space, A: FiniteSpace, Q, T> ParameterizedFunc<N> for EGreedyQAgent<S, A, Q, T>
 	where 	T: Chooser<A::Element>,
 			Q: QFunction<S, A> + ParameterizedFunc<N> {
@@ -202,6 +306,37 @@ impl<F: Float, S: Space, A: FiniteSpace, Q, T> DifferentiableFunc<S, A, F> for E
 	implement_differentiablefunc!();
 }
 
+/// The learned state of an EGreedyQAgent
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct EGreedyState<Q> {
+	/// Probabilty of acting randomly
+	pub epsilon: f64,
+	/// State of the underlying QFunction
+	pub q_func: Q,
+}
+
+impl<S: Space, A: FiniteSpace, Q, T> Stateful for EGreedyQAgent<S, A, Q, T>
+	where T: Chooser<A::Element>,
+		  Q: QFunction<S, A> + Stateful {
+	type State = EGreedyState<Q::State>;
+
+	fn get_state(&self) -> EGreedyState<Q::State> {
+		EGreedyState {
+			epsilon: self.epsilon,
+			q_func: self.q_func.get_state()
+		}
+	}
+	fn set_state(&mut self, state: EGreedyState<Q::State>) -> Result<(), CheckpointError> {
+		if !(0.0 <= state.epsilon && state.epsilon <= 1.0) {
+			return Err(CheckpointError::Mismatch(format!("epsilon must be between 0 and 1, found {}", state.epsilon)));
+		}
+		self.q_func.set_state(state.q_func)?;
+		self.epsilon = state.epsilon;
+		Ok(())
+	}
+}
+
 impl<S: Space, A: FiniteSpace, Q, T> EGreedyQAgent<S, A, Q, T> 
 	where	T: Chooser<A::Element>,
 			Q: QFunction<S, A> {
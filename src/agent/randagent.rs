@@ -1,6 +1,8 @@
-use environment::Space;
+use rand::Rng;
 
-use agent::Agent;
+use environment::{Space, FiniteSpace};
+
+use agent::{Agent, StochasticAgent};
 
 /// Random Agent
 ///
@@ -12,8 +14,15 @@ pub struct RandomAgent<A: Space> {
 }
 
 impl<S: Space, A: Space> Agent<S, A> for RandomAgent<A> {
-	fn get_action(&self, _: &S::Element) -> A::Element {
-		self.action_space.sample()
+	fn get_action<R: Rng>(&self, _: &S::Element, rng: &mut R) -> A::Element {
+		self.action_space.sample(rng)
+	}
+}
+
+// Assumes sampling a finite space picks each of its members with equal probability
+impl<S: Space, A: FiniteSpace> StochasticAgent<S, A> for RandomAgent<A> {
+	fn get_action_prob(&self, _: &S::Element, action: &A::Element) -> f64 {
+		if self.action_space.index(action) == -1 {0.0} else {1.0/self.action_space.size() as f64}
 	}
 }
 
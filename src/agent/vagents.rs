@@ -1,5 +1,7 @@
 //! V-Agents module
 
+use rand::Rng;
+
 use environment::{Space, FiniteSpace};
 
 use agent::Agent;
@@ -17,7 +19,7 @@ pub struct BinaryVAgent<S: Space, A: FiniteSpace> {
 }
 
 impl<S: Space, A: FiniteSpace> Agent<S, A> for BinaryVAgent<S, A> {
-	fn get_action(&self, state: &S::Element) -> A::Element {
+	fn get_action<R: Rng>(&self, state: &S::Element, _: &mut R) -> A::Element {
 		let val = self.v_func.eval(state);
 		let actions = self.action_space.enumerate();
 		if val < 0.0 {actions[0].clone()} else {actions[1].clone()}
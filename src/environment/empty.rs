@@ -1,9 +1,11 @@
+use rand::Rng;
+
 use environment::{Space, FiniteSpace};
 
 impl Space for () {
 	type Element = ();
 
-	fn sample(&self) -> () {
+	fn sample<R: Rng>(&self, _: &mut R) -> () {
 		()
 	}
 }
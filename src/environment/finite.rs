@@ -1,4 +1,4 @@
-use rand::{Rng, thread_rng};
+use rand::Rng;
 
 use environment::{Space, FiniteSpace};
 
@@ -14,8 +14,7 @@ pub struct Finite {
 impl Space for Finite {
 	type Element = u32;
 
-	fn sample(&self) -> u32 {
-		let mut rng = thread_rng();
+	fn sample<R: Rng>(&self, rng: &mut R) -> u32 {
 		rng.gen_range(0, self.size)
 	}
 }
@@ -8,6 +8,8 @@ mod vector;
 
 use std::fmt::Debug;
 
+use rand::Rng;
+
 pub use self::finite::Finite;
 pub use self::range::Range;
 
@@ -21,8 +23,8 @@ pub trait Space : Debug {
 	/// The type of members of the Space
 	type Element : Debug + PartialEq + Clone;
 
-	/// Returns a random element of this space
-	fn sample(&self) -> Self::Element;
+	/// Returns a random element of this space drawn using the given rng
+	fn sample<R: Rng>(&self, rng: &mut R) -> Self::Element;
 }
 
 /// Finite Space Trait
@@ -1,4 +1,4 @@
-use rand::{Rng, thread_rng};
+use rand::Rng;
 
 use environment::Space;
 
@@ -16,8 +16,7 @@ pub struct Range {
 impl Space for Range {
 	type Element = f64;
 
-	fn sample(&self) -> f64 {
-		let mut rng = thread_rng();
+	fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
 		rng.gen_range(self.low, self.high)
 	}
 }
@@ -1,12 +1,14 @@
 use std::iter;
 
+use rand::Rng;
+
 use environment::{Space, FiniteSpace};
 
 impl<T: Space, U: Space> Space for (T, U) {
 	type Element = (T::Element, U::Element);
 
-	fn sample(&self) -> Self::Element {
-		(self.0.sample(), self.1.sample())
+	fn sample<R: Rng>(&self, rng: &mut R) -> Self::Element {
+		(self.0.sample(rng), self.1.sample(rng))
 	}
 }
 
@@ -1,13 +1,15 @@
 use std::iter;
 
+use rand::Rng;
+
 use environment::{Space, FiniteSpace};
 
 impl<T: Space> Space for Vec<T> {
 	type Element = Vec<T::Element>;
 
-	fn sample(&self) -> Self::Element {
+	fn sample<R: Rng>(&self, rng: &mut R) -> Self::Element {
 		self.iter()
-			.map(|s| s.sample())
+			.map(|s| s.sample(rng))
 			.collect()
 	}
 }
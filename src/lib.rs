@@ -8,6 +8,13 @@
 extern crate rand;
 extern crate num;
 extern crate rulinalg;
+#[cfg(feature = "serialize")]
+extern crate serde;
+#[cfg(feature = "serialize")]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(feature = "serialize")]
+extern crate serde_json;
 
 pub mod environment;
 pub mod prelude;
@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use rand::Rng;
+
+use environment::{FiniteSpace, Transition};
+use model::{Model, DeterministicModel};
+use util::Stateful;
+use util::checkpoint::CheckpointError;
+
+/// A DeterministicModel that remembers the last next state and reward observed after each (state, action) pair
+///
+/// Suited to environments that really are deterministic, where it needs a single observation per
+/// pair and stores no more than one entry for each. It can be used directly as a Model.
+#[derive(Debug, Clone)]
+pub struct DeterministicTable<S: FiniteSpace, A: FiniteSpace>
+	where S::Element: Hash + Eq, A::Element: Hash + Eq {
+	map: HashMap<(S::Element, A::Element), (S::Element, f64)>
+}
+
+impl<S: FiniteSpace, A: FiniteSpace> DeterministicModel<S, A> for DeterministicTable<S, A>
+	where S::Element: Hash + Eq, A::Element: Hash + Eq {
+	fn transition2(&self, curr: &S::Element, action: &A::Element) -> Option<S::Element> {
+		self.map.get(&(curr.clone(), action.clone())).map(|&(ref next, _)| next.clone())
+	}
+	fn reward2(&self, curr: &S::Element, action: &A::Element) -> f64 {
+		self.map.get(&(curr.clone(), action.clone())).map_or(0.0, |&(_, r)| r)
+	}
+	fn update(&mut self, transition: Transition<S, A>) {
+		let (state, action, reward, next) = transition;
+		self.map.insert((state, action), (next, reward));
+	}
+}
+
+impl<S: FiniteSpace, A: FiniteSpace> Model<S, A> for DeterministicTable<S, A>
+	where S::Element: Hash + Eq, A::Element: Hash + Eq {
+	implement_model_for_deterministicmodel!();
+}
+
+impl<S: FiniteSpace, A: FiniteSpace> Stateful for DeterministicTable<S, A>
+	where S::Element: Hash + Eq, A::Element: Hash + Eq {
+	/// Every stored (state, action, next state, reward) tuple
+	type State = Vec<(S::Element, A::Element, S::Element, f64)>;
+
+	fn get_state(&self) -> Self::State {
+		self.map.iter().map(|(&(ref s, ref a), &(ref s1, r))| (s.clone(), a.clone(), s1.clone(), r)).collect()
+	}
+	fn set_state(&mut self, state: Self::State) -> Result<(), CheckpointError> {
+		self.map = state.into_iter().map(|(s, a, s1, r)| ((s, a), (s1, r))).collect();
+		Ok(())
+	}
+}
+
+impl<S: FiniteSpace, A: FiniteSpace> DeterministicTable<S, A>
+	where S::Element: Hash + Eq, A::Element: Hash + Eq {
+	/// Creates a new DeterministicTable that has not observed anything
+	pub fn new() -> DeterministicTable<S, A> {
+		DeterministicTable {
+			map: HashMap::new()
+		}
+	}
+}
\ No newline at end of file
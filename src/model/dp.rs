@@ -0,0 +1,235 @@
+use std::hash::Hash;
+use std::f64;
+
+use environment::FiniteSpace;
+use model::Model;
+use agent::qagents::GreedyQAgent;
+use util::{QFunction, VFunction};
+use util::table::QTable;
+
+/// The dynamic programming algorithm used by a DPSolver
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DPMethod {
+	/// Repeatedly applies the Bellman optimality backup to every state
+	ValueIteration,
+	/// Alternates between fully evaluating the greedy policy and improving it
+	PolicyIteration,
+	/// Alternates between improving the greedy policy and evaluating it with the given number of sweeps
+	ModifiedPolicyIteration(usize),
+}
+
+/// Describes the progress made by a DPSolver
+#[derive(Debug, Clone, PartialEq)]
+pub struct DPReport {
+	/// The number of iterations performed
+	pub iterations: usize,
+	/// Whether the values converged before running out of iterations
+	pub converged: bool,
+	/// The largest change in the value of any state during each iteration
+	pub deltas: Vec<f64>,
+	/// The number of states whose greedy action changed during each iteration
+	pub policy_changes: Vec<usize>,
+}
+
+/// Exactly solves a Model whose states and actions come from finite spaces
+///
+/// The model is only queried when a solve starts: the successors of every (state, action) pair
+/// and their rewards are read once and then swept over until convergence.
+/// Pairs whose next states all have probability 0 (such as those in terminal states) are worth 0.
+#[derive(Debug)]
+pub struct DPSolver<S: FiniteSpace, A: FiniteSpace> {
+	/// The states of the model
+	state_space: S,
+	/// The actions of the model
+	action_space: A,
+	/// The discount factor
+	gamma: f64,
+	/// The algorithm used
+	method: DPMethod,
+	/// Values are converged once no state's value changes by more than this in an iteration
+	tolerance: f64,
+	/// The maximum number of iterations (and of sweeps when evaluating a policy)
+	max_iters: usize,
+}
+
+// The next states (by index) reachable after each (state, action) pair, with their probabilities and rewards
+type Successors = Vec<Vec<Vec<(usize, f64, f64)>>>;
+
+impl<S: FiniteSpace, A: FiniteSpace> DPSolver<S, A> {
+	/// Returns a new DPSolver with the given info
+	pub fn new(state_space: S, action_space: A, gamma: f64, method: DPMethod) -> DPSolver<S, A> {
+		assert!(0.0 <= gamma && gamma <= 1.0, "gamma must be between 0 and 1");
+		if let DPMethod::ModifiedPolicyIteration(sweeps) = method {
+			assert!(sweeps > 0, "modified policy iteration needs at least one sweep");
+		}
+
+		DPSolver {
+			state_space: state_space,
+			action_space: action_space,
+			gamma: gamma,
+			method: method,
+			tolerance: 1e-8,
+			max_iters: 10000
+		}
+	}
+	/// Creates a new DPSolver using value iteration with default gamma, tolerance and max_iters
+	pub fn default(state_space: S, action_space: A) -> DPSolver<S, A> {
+		DPSolver::new(state_space, action_space, 0.95, DPMethod::ValueIteration)
+	}
+	/// Sets gamma field of self
+	pub fn gamma(mut self, gamma: f64) -> DPSolver<S, A> {
+		assert!(0.0 <= gamma && gamma <= 1.0, "gamma must be between 0 and 1");
+
+		self.gamma = gamma;
+		self
+	}
+	/// Sets method field of self
+	pub fn method(mut self, method: DPMethod) -> DPSolver<S, A> {
+		if let DPMethod::ModifiedPolicyIteration(sweeps) = method {
+			assert!(sweeps > 0, "modified policy iteration needs at least one sweep");
+		}
+
+		self.method = method;
+		self
+	}
+	/// Sets tolerance field of self
+	pub fn tolerance(mut self, tolerance: f64) -> DPSolver<S, A> {
+		self.tolerance = tolerance;
+		self
+	}
+	/// Sets max_iters field of self
+	pub fn max_iters(mut self, max_iters: usize) -> DPSolver<S, A> {
+		self.max_iters = max_iters;
+		self
+	}
+
+	/// Computes the optimal value of every state, starting from the values in v_func and writing the results to it
+	pub fn solve_v<M: Model<S, A>, V: VFunction<S>>(&self, model: &M, v_func: &mut V) -> DPReport {
+		let states = self.state_space.enumerate();
+		let mut values: Vec<_> = states.iter().map(|s| v_func.eval(s)).collect();
+		let report = self.solve(model, &mut values);
+
+		for (s, v) in states.iter().zip(values) {
+			v_func.update(s, v, 1.0);
+		}
+		report
+	}
+	/// Computes the optimal value of every (state, action) pair and writes them to q_func
+	pub fn solve_q<M: Model<S, A>, Q: QFunction<S, A>>(&self, model: &M, q_func: &mut Q) -> DPReport {
+		let states = self.state_space.enumerate();
+		let actions = self.action_space.enumerate();
+		let succs = self.successors(model, &states, &actions);
+
+		let mut values = vec![0.0; states.len()];
+		let report = self.solve_succs(&succs, &mut values);
+
+		for (i, s) in states.iter().enumerate() {
+			for (j, a) in actions.iter().enumerate() {
+				q_func.update(s, a, self.backup(&succs[i][j], &values), 1.0);
+			}
+		}
+		report
+	}
+	/// Returns an agent that acts optimally in the model
+	pub fn solve_agent<M: Model<S, A>>(&self, model: &M) -> (GreedyQAgent<S, A, QTable<S, A>>, DPReport)
+		where A: Clone, S::Element: Hash + Eq, A::Element: Hash + Eq {
+		let mut q_func = QTable::new();
+		let report = self.solve_q(model, &mut q_func);
+		(GreedyQAgent::new(q_func, self.action_space.clone()), report)
+	}
+
+	fn solve<M: Model<S, A>>(&self, model: &M, values: &mut Vec<f64>) -> DPReport {
+		let states = self.state_space.enumerate();
+		let actions = self.action_space.enumerate();
+		let succs = self.successors(model, &states, &actions);
+		self.solve_succs(&succs, values)
+	}
+	fn successors<M: Model<S, A>>(&self, model: &M, states: &[S::Element], actions: &[A::Element]) -> Successors {
+		states.iter().map(|s| {
+			actions.iter().map(|a| {
+				model.successors(s, a).into_iter().map(|(s1, p)| {
+					let k = self.state_space.index(&s1);
+					assert!(k >= 0, "model has a successor ({:?}) outside of the state space", s1);
+					(k as usize, p, model.reward(s, a, &s1))
+				}).collect()
+			}).collect()
+		}).collect()
+	}
+	fn solve_succs(&self, succs: &Successors, values: &mut Vec<f64>) -> DPReport {
+		let mut report = DPReport {
+			iterations: 0,
+			converged: false,
+			deltas: Vec::new(),
+			policy_changes: Vec::new()
+		};
+		let mut policy: Vec<Option<usize>> = vec![None; values.len()];
+
+		while report.iterations < self.max_iters && !report.converged {
+			let changes = self.improve(succs, values, &mut policy);
+			let policy: Vec<usize> = policy.iter().map(|a| a.unwrap_or(0)).collect();
+			let old_values = values.clone();
+
+			match self.method {
+				DPMethod::ValueIteration => {
+					self.sweep(succs, &policy, values);
+				},
+				DPMethod::ModifiedPolicyIteration(sweeps) => {
+					for _ in 0..sweeps {
+						self.sweep(succs, &policy, values);
+					}
+				},
+				DPMethod::PolicyIteration => if changes > 0 || report.iterations == 0 {
+					for _ in 0..self.max_iters {
+						if self.sweep(succs, &policy, values) <= self.tolerance {
+							break;
+						}
+					}
+				},
+			}
+
+			let delta = old_values.iter().zip(values.iter()).fold(0.0, |max: f64, (v0, v1)| max.max((v1 - v0).abs()));
+			report.converged = match self.method {
+				DPMethod::PolicyIteration => changes == 0 && report.iterations > 0,
+				_ => delta <= self.tolerance
+			};
+			report.iterations += 1;
+			report.deltas.push(delta);
+			report.policy_changes.push(changes);
+		}
+		report
+	}
+	// Makes policy greedy with respect to values, only switching actions that are strictly better
+	// Returns how many states changed action
+	fn improve(&self, succs: &Successors, values: &[f64], policy: &mut Vec<Option<usize>>) -> usize {
+		let mut changes = 0;
+		for (i, action) in policy.iter_mut().enumerate() {
+			let mut best = action.map(|j| (j, self.backup(&succs[i][j], values)));
+			for j in 0..succs[i].len() {
+				let val = self.backup(&succs[i][j], values);
+				if best.map_or(true, |(_, best_val)| val > best_val) {
+					best = Some((j, val));
+				}
+			}
+
+			let best = best.map(|(j, _)| j);
+			if best != *action {
+				*action = best;
+				changes += 1;
+			}
+		}
+		changes
+	}
+	// Sets each value to that of following policy for one step, returning the largest change
+	fn sweep(&self, succs: &Successors, policy: &[usize], values: &mut Vec<f64>) -> f64 {
+		let new_values: Vec<_> = policy.iter().enumerate().map(|(i, &j)| {
+			self.backup(&succs[i][j], values)
+		}).collect();
+
+		let delta = values.iter().zip(&new_values).fold(0.0, |max: f64, (v0, v1)| max.max((v1 - v0).abs()));
+		*values = new_values;
+		delta
+	}
+	fn backup(&self, succs: &[(usize, f64, f64)], values: &[f64]) -> f64 {
+		succs.iter().fold(0.0, |sum, &(k, p, r)| sum + p*(r + self.gamma*values[k]))
+	}
+}
\ No newline at end of file
@@ -0,0 +1,208 @@
+use rand::Rng;
+use rand::distributions::IndependentSample;
+use rand::distributions::normal::Normal;
+
+use environment::{Space, FiniteSpace, Transition};
+use model::GenerativeModel;
+use util::{Feature, Stateful};
+use util::checkpoint::CheckpointError;
+
+/// A GenerativeModel of environments with continuous (Vec<f64>) states
+///
+/// For each action, the change in state and the reward are predicted as linear functions of the
+/// given features (plus a constant). The weights are fit by recursive least squares, so after every
+/// update they exactly solve the ridge regression problem over all observed transitions of that action.
+/// Generated transitions add Gaussian noise matching the mean squared residual of the fit (scaled by noise).
+#[derive(Debug)]
+pub struct LinearDynamics<S: Space<Element=Vec<f64>>, A: FiniteSpace> {
+	/// The features the predictions are linear in
+	features: Vec<Box<Feature<S, f64>>>,
+	/// The actions of the environment
+	action_space: A,
+	/// The regularization strength of the least squares fit
+	ridge: f64,
+	/// How much noise generated transitions have, relative to the observed prediction errors
+	noise: f64,
+	/// For each action, the weights of each output (state change, then reward)
+	weights: Vec<Vec<Vec<f64>>>,
+	/// For each action, the inverse of the (regularized) covariance of the observed features
+	inverses: Vec<Vec<Vec<f64>>>,
+	/// The sum of squared residuals of the fit of each output
+	residuals: Vec<f64>,
+	/// The number of observed transitions
+	count: usize,
+}
+
+/// The learned parameters of a LinearDynamics
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct LinearDynamicsState {
+	/// For each action, the weights of each output (state change, then reward)
+	pub weights: Vec<Vec<Vec<f64>>>,
+	/// For each action, the inverse of the (regularized) covariance of the observed features
+	pub inverses: Vec<Vec<Vec<f64>>>,
+	/// The sum of squared residuals of the fit of each output
+	pub residuals: Vec<f64>,
+	/// The number of observed transitions
+	pub count: usize,
+}
+
+impl<S: Space<Element=Vec<f64>>, A: FiniteSpace> GenerativeModel<S, A> for LinearDynamics<S, A> {
+	fn generate<R: Rng>(&self, curr: &Vec<f64>, action: &A::Element, rng: &mut R) -> (Vec<f64>, f64) {
+		let (mut next, mut reward) = self.predict(curr, action);
+		if self.noise > 0.0 && self.count > 0 {
+			let variances = self.get_variances();
+			for (x, var) in next.iter_mut().zip(&variances) {
+				*x += self.noise*var.sqrt()*Normal::new(0.0, 1.0).ind_sample(rng);
+			}
+			reward += self.noise*variances[curr.len()].sqrt()*Normal::new(0.0, 1.0).ind_sample(rng);
+		}
+		(next, reward)
+	}
+	fn update(&mut self, transition: Transition<S, A>) {
+		let (state, action, reward, next) = transition;
+		assert_eq!(state.len(), next.len(), "states must all have the same length");
+
+		if self.count == 0 {
+			let outputs = state.len() + 1;
+			for weights in &mut self.weights {
+				*weights = vec![vec![0.0; self.features.len() + 1]; outputs];
+			}
+			self.residuals = vec![0.0; outputs];
+		}
+		assert_eq!(state.len() + 1, self.residuals.len(), "states must all have the same length");
+
+		let i = self.action_index(&action);
+		let x = self.extract(&state);
+		let mut targets: Vec<_> = next.iter().zip(&state).map(|(s1, s)| s1 - s).collect();
+		targets.push(reward);
+		let errors: Vec<_> = self.weights[i].iter().zip(targets).map(|(w, y)| y - dot(w, &x)).collect();
+
+		// Sherman-Morrison update of the inverse covariance
+		let px: Vec<_> = self.inverses[i].iter().map(|row| dot(row, &x)).collect();
+		let denom = 1.0 + dot(&x, &px);
+
+		// How much the residuals of the refit grow
+		self.count += 1;
+		for (res, e) in self.residuals.iter_mut().zip(&errors) {
+			*res += e*e/denom;
+		}
+
+		let gain: Vec<_> = px.iter().map(|p| p/denom).collect();
+		for (w, e) in self.weights[i].iter_mut().zip(errors) {
+			for (wj, g) in w.iter_mut().zip(&gain) {
+				*wj += e*g;
+			}
+		}
+		for (row, g) in self.inverses[i].iter_mut().zip(&gain) {
+			for (pj, p) in row.iter_mut().zip(&px) {
+				*pj -= g*p;
+			}
+		}
+	}
+}
+
+impl<S: Space<Element=Vec<f64>>, A: FiniteSpace> Stateful for LinearDynamics<S, A> {
+	type State = LinearDynamicsState;
+
+	fn get_state(&self) -> LinearDynamicsState {
+		LinearDynamicsState {
+			weights: self.weights.clone(),
+			inverses: self.inverses.clone(),
+			residuals: self.residuals.clone(),
+			count: self.count
+		}
+	}
+	fn set_state(&mut self, state: LinearDynamicsState) -> Result<(), CheckpointError> {
+		let size = self.features.len() + 1;
+		if state.weights.len() != self.weights.len() || state.inverses.len() != self.inverses.len() {
+			return Err(CheckpointError::Mismatch(format!("expected parameters for {} actions", self.weights.len())));
+		}
+		if state.inverses.iter().any(|p| p.len() != size || p.iter().any(|row| row.len() != size)) ||
+		   state.weights.iter().any(|w| w.iter().any(|row| row.len() != size)) {
+			return Err(CheckpointError::Mismatch(format!("expected {} features (including the constant)", size)));
+		}
+
+		self.weights = state.weights;
+		self.inverses = state.inverses;
+		self.residuals = state.residuals;
+		self.count = state.count;
+		Ok(())
+	}
+}
+
+impl<S: Space<Element=Vec<f64>>, A: FiniteSpace> LinearDynamics<S, A> {
+	/// Creates a new LinearDynamics using the given features, which has not observed anything
+	pub fn new(features: Vec<Box<Feature<S, f64>>>, action_space: A) -> LinearDynamics<S, A> {
+		let num_actions = action_space.size();
+		let mut model = LinearDynamics {
+			features: features,
+			action_space: action_space,
+			ridge: 1e-3,
+			noise: 1.0,
+			weights: vec![Vec::new(); num_actions],
+			inverses: Vec::new(),
+			residuals: Vec::new(),
+			count: 0
+		};
+		model.reset_inverses();
+		model
+	}
+	/// Sets ridge field of self
+	/// Should be called before self observes anything, since the current fit is forgotten
+	pub fn ridge(mut self, ridge: f64) -> LinearDynamics<S, A> {
+		assert!(ridge > 0.0, "ridge must be positive");
+
+		self.ridge = ridge;
+		self.reset_inverses();
+		self.weights = vec![Vec::new(); self.weights.len()];
+		self.residuals = Vec::new();
+		self.count = 0;
+		self
+	}
+	/// Sets noise field of self
+	pub fn noise(mut self, noise: f64) -> LinearDynamics<S, A> {
+		assert!(noise >= 0.0, "noise must be nonnegative");
+
+		self.noise = noise;
+		self
+	}
+	/// Returns the expected next state and reward after performing action in curr
+	/// Before anything is observed, the state is predicted not to change and to give no reward
+	pub fn predict(&self, curr: &Vec<f64>, action: &A::Element) -> (Vec<f64>, f64) {
+		let weights = &self.weights[self.action_index(action)];
+		if weights.is_empty() {
+			return (curr.clone(), 0.0);
+		}
+
+		let x = self.extract(curr);
+		let next = curr.iter().zip(weights).map(|(s, w)| s + dot(w, &x)).collect();
+		(next, dot(&weights[curr.len()], &x))
+	}
+	/// Returns the mean squared residual of the fit of each state component, followed by that of the reward
+	pub fn get_variances(&self) -> Vec<f64> {
+		self.residuals.iter().map(|res| res/(self.count.max(1) as f64)).collect()
+	}
+
+	fn reset_inverses(&mut self) {
+		let size = self.features.len() + 1;
+		let identity: Vec<Vec<f64>> = (0..size).map(|i| {
+			(0..size).map(|j| if i == j {1.0/self.ridge} else {0.0}).collect()
+		}).collect();
+		self.inverses = vec![identity; self.weights.len()];
+	}
+	fn action_index(&self, action: &A::Element) -> usize {
+		let i = self.action_space.index(action);
+		assert!(i >= 0, "action {:?} is not in the action space", action);
+		i as usize
+	}
+	fn extract(&self, state: &Vec<f64>) -> Vec<f64> {
+		let mut x = vec![1.0];
+		x.extend(self.features.iter().map(|f| f.extract(state)));
+		x
+	}
+}
+
+fn dot(x: &[f64], y: &[f64]) -> f64 {
+	x.iter().zip(y).fold(0.0, |sum, (a, b)| sum + a*b)
+}
\ No newline at end of file
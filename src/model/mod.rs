@@ -1,32 +1,44 @@
 //! Model Module
 
-mod plain;
-
-pub use self::plain::PlainModel;
-
-use environment::{Space, Transition};
-
-// Macro not tested, likely needs to be modified
+// Implements Model for a type that implements DeterministicModel
 macro_rules! implement_model_for_deterministicmodel {
-    ($name:ident, $state:ident, $action:ident) => {
-    	impl<S: $state, A: $action> Model<S, A> for $name<S, A> {
-	    fn transition(&self, curr: &S::Element, action: &A::Element, next: &S::Element) -> f64 {
-			let actual_next = self.transition2(curr, action);
-			if *next == actual_next {1.0} else {0.0}
+    () => {
+    	fn transition(&self, curr: &S::Element, action: &A::Element, next: &S::Element) -> f64 {
+			if self.transition2(curr, action).as_ref() == Some(next) {1.0} else {0.0}
 		}
-
 		fn reward(&self, curr: &S::Element, action: &A::Element, next: &S::Element) -> f64 {
-			let actual_next = self.transition2(curr, action);
-			if *next == actual_next {self.reward2(curr, action)} else {0.0}
+			if self.transition2(curr, action).as_ref() == Some(next) {self.reward2(curr, action)} else {0.0}
 		}
-
 		fn update(&mut self, transition: Transition<S, A>) {
-			self.update(transition);
+			DeterministicModel::update(self, transition);
+		}
+		fn successors(&self, curr: &S::Element, action: &A::Element) -> Vec<(S::Element, f64)> {
+			self.transition2(curr, action).into_iter().map(|next| (next, 1.0)).collect()
+		}
+		fn sample<R: Rng>(&self, curr: &S::Element, action: &A::Element, _: &mut R) -> Option<(S::Element, f64)> {
+			self.transition2(curr, action).map(|next| (next, self.reward2(curr, action)))
 		}
-	    	}
     }
 }
 
+mod plain;
+mod deterministic;
+mod tabular;
+mod linear;
+mod dp;
+
+pub use self::plain::{PlainModel, PlainModelState};
+pub use self::deterministic::DeterministicTable;
+pub use self::tabular::TabularModel;
+pub use self::linear::{LinearDynamics, LinearDynamicsState};
+pub use self::dp::{DPSolver, DPMethod, DPReport};
+
+use rand::Rng;
+
+use environment::{Space, Transition};
+use util::Chooser;
+use util::chooser::Weighted;
+
 /// Represents a (nondeterministic) model of an environment
 /// The model itself is composed of the transition and reward functions
 pub trait Model<S: Space, A: Space> {
@@ -36,15 +48,76 @@ pub trait Model<S: Space, A: Space> {
 	fn reward(&self, curr: &S::Element, action: &A::Element, next: &S::Element) -> f64;
 	/// Updates the model using information from the given transition
 	fn update(&mut self, transition: Transition<S, A>);
+	/// Returns every next state with nonzero probability of following (curr, action), along with that probability
+	fn successors(&self, curr: &S::Element, action: &A::Element) -> Vec<(S::Element, f64)>;
+	/// Samples a next state and reward following (curr, action), or None if the model knows of no next states
+	fn sample<R: Rng>(&self, curr: &S::Element, action: &A::Element, rng: &mut R) -> Option<(S::Element, f64)> {
+		let (states, probs): (Vec<_>, Vec<_>) = self.successors(curr, action).into_iter().unzip();
+		if states.is_empty() {
+			return None;
+		}
+
+		let next = Weighted.choose(&states, probs, rng);
+		let reward = self.reward(curr, action, &next);
+		Some((next, reward))
+	}
+}
+
+/// Represents a model of an environment that can only be sampled from
+///
+/// Unlike Model, this does not require listing next states or their probabilities, so it suits
+/// continuous state spaces
+pub trait GenerativeModel<S: Space, A: Space> {
+	/// Samples a next state and reward following (curr, action)
+	fn generate<R: Rng>(&self, curr: &S::Element, action: &A::Element, rng: &mut R) -> (S::Element, f64);
+	/// Updates the model using information from the given transition
+	fn update(&mut self, transition: Transition<S, A>);
 }
 
 /// Represents a deterministic model of an environment
 /// When the agent performs a specified action in a specified state, there's only one possible next state
+///
+/// Wrap one in a DeterministicAdaptor to use it as a Model, which gives probability 1 to the predicted next state
 pub trait DeterministicModel<S: Space, A: Space> {
-	/// Returns the new state of the agent after performing action in curr
-	fn transition2(&self, curr: &S::Element, action: &A::Element) -> S::Element;
+	/// Returns the new state of the agent after performing action in curr, or None if it is unknown
+	fn transition2(&self, curr: &S::Element, action: &A::Element) -> Option<S::Element>;
 	/// Returns the reward received when performing action in curr
 	fn reward2(&self, curr: &S::Element, action: &A::Element) -> f64;
 	/// Upates the model using information from the given transition
 	fn update(&mut self, transition: Transition<S, A>);
+}
+
+/// Makes any DeterministicModel usable as a Model
+#[derive(Debug, Clone)]
+pub struct DeterministicAdaptor<M> {
+	model: M,
+}
+
+impl<S: Space, A: Space, M: DeterministicModel<S, A>> DeterministicModel<S, A> for DeterministicAdaptor<M> {
+	fn transition2(&self, curr: &S::Element, action: &A::Element) -> Option<S::Element> {
+		self.model.transition2(curr, action)
+	}
+	fn reward2(&self, curr: &S::Element, action: &A::Element) -> f64 {
+		self.model.reward2(curr, action)
+	}
+	fn update(&mut self, transition: Transition<S, A>) {
+		self.model.update(transition)
+	}
+}
+
+impl<S: Space, A: Space, M: DeterministicModel<S, A>> Model<S, A> for DeterministicAdaptor<M> {
+	implement_model_for_deterministicmodel!();
+}
+
+impl<M> DeterministicAdaptor<M> {
+	/// Wraps model so it can be used as a Model
+	pub fn new(model: M) -> DeterministicAdaptor<M> {
+		DeterministicAdaptor {
+			model: model
+		}
+	}
+	/// Returns the wrapped model
+	pub fn get_model(&self) -> &M {
+		&self.model
+	}
 }
\ No newline at end of file
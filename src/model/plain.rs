@@ -1,27 +1,69 @@
 use std::collections::HashMap;
 use std::hash::Hash;
 
+use rand::Rng;
+
 use environment::{FiniteSpace, Transition};
 use model::Model;
+use util::Stateful;
+use util::checkpoint::CheckpointError;
 
 // TODO: Come up with a better name
 /// StraightForward model that approximates probabilities can counting observations
+///
+/// Observations are stored as counts, so updating the model and looking up probabilities or rewards
+/// take O(1) (amortized) time, while listing next states is linear in how many were seen. Every
+/// observation is also remembered in order, so sampling picks one uniformly in O(1) time.
 #[derive(Debug)]
 pub struct PlainModel<S: FiniteSpace, A: FiniteSpace>
 	where S::Element: Hash + Eq, A::Element: Hash + Eq {
-	trans: HashMap<(S::Element, A::Element), Vec<S::Element>>,
+	trans: HashMap<(S::Element, A::Element), Counts<S::Element>>,
 	rewards: HashMap<(S::Element, A::Element, S::Element), f64>,
 }
 
+// How often each next state was observed after some (state, action) pair
+#[derive(Debug, Clone)]
+struct Counts<T: Hash + Eq> {
+	total: usize,
+	// Kept in the order states were first seen, so listing them is reproducible
+	nexts: Vec<(T, usize)>,
+	// Position of each next state in nexts
+	index: HashMap<T, usize>,
+	// Position in nexts of the next state of every observation, so a uniform pick is a sample
+	history: Vec<usize>,
+}
+
+impl<T: Hash + Eq + Clone> Counts<T> {
+	fn new() -> Counts<T> {
+		Counts {
+			total: 0,
+			nexts: Vec::new(),
+			index: HashMap::new(),
+			history: Vec::new()
+		}
+	}
+	fn add(&mut self, next: T, count: usize) {
+		let nexts = &mut self.nexts;
+		let i = *self.index.entry(next.clone()).or_insert_with(|| {
+			nexts.push((next, 0));
+			nexts.len() - 1
+		});
+		nexts[i].1 += count;
+		self.total += count;
+		self.history.extend((0..count).map(|_| i));
+	}
+	fn count(&self, next: &T) -> usize {
+		self.index.get(next).map_or(0, |&i| self.nexts[i].1)
+	}
+}
+
 impl<S: FiniteSpace, A: FiniteSpace> Model<S, A> for PlainModel<S, A>
 	where S::Element: Hash + Eq, A::Element: Hash + Eq {
 	fn transition(&self, curr: &S::Element, action: &A::Element, next: &S::Element) -> f64 {
-		let key = (curr.clone(), action.clone());
-		let poss = if self.trans.contains_key(&key) {&self.trans[&key]} else {return 0.0};
-
-		let total = poss.len();
-		let count = poss.into_iter().filter(|&s| s == next).count();
-		(count as f64)/(total as f64)
+		match self.trans.get(&(curr.clone(), action.clone())) {
+			Some(counts) => (counts.count(next) as f64)/(counts.total as f64),
+			None => 0.0
+		}
 	}
 	fn reward(&self, curr: &S::Element, action: &A::Element, next: &S::Element) -> f64 {
 		let key = (curr.clone(), action.clone(), next.clone());
@@ -35,11 +77,66 @@ impl<S: FiniteSpace, A: FiniteSpace> Model<S, A> for PlainModel<S, A>
 		let (state, action, reward, next) = transition;
 
 		let key = (state.clone(), action.clone());
-		self.trans.entry(key).or_insert(Vec::new()).push(next.clone());
+		self.trans.entry(key).or_insert_with(Counts::new).add(next.clone(), 1);
 
 		let key = (state.clone(), action.clone(), next.clone());
 		self.rewards.insert(key, reward);
 	}
+	fn successors(&self, curr: &S::Element, action: &A::Element) -> Vec<(S::Element, f64)> {
+		match self.trans.get(&(curr.clone(), action.clone())) {
+			Some(counts) => counts.nexts.iter().map(|&(ref s, c)| {
+				(s.clone(), (c as f64)/(counts.total as f64))
+			}).collect(),
+			None => Vec::new()
+		}
+	}
+	fn sample<R: Rng>(&self, curr: &S::Element, action: &A::Element, rng: &mut R) -> Option<(S::Element, f64)> {
+		let counts = match self.trans.get(&(curr.clone(), action.clone())) {
+			Some(counts) => counts,
+			None => return None
+		};
+
+		let i = counts.history[rng.gen_range(0, counts.history.len())];
+		let next = counts.nexts[i].0.clone();
+
+		let reward = self.reward(curr, action, &next);
+		Some((next, reward))
+	}
+}
+
+/// The observations stored by a PlainModel
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct PlainModelState<S, A> {
+	/// The next states observed after each (state, action) pair, in the order they were observed
+	pub trans: Vec<((S, A), Vec<S>)>,
+	/// The last reward observed for each (state, action, next state) triple
+	pub rewards: Vec<((S, A, S), f64)>,
+}
+
+impl<S: FiniteSpace, A: FiniteSpace> Stateful for PlainModel<S, A>
+	where S::Element: Hash + Eq, A::Element: Hash + Eq {
+	type State = PlainModelState<S::Element, A::Element>;
+
+	fn get_state(&self) -> Self::State {
+		PlainModelState {
+			trans: self.trans.iter().map(|(k, v)| {
+				(k.clone(), v.history.iter().map(|&i| v.nexts[i].0.clone()).collect())
+			}).collect(),
+			rewards: self.rewards.iter().map(|(k, &r)| (k.clone(), r)).collect()
+		}
+	}
+	fn set_state(&mut self, state: Self::State) -> Result<(), CheckpointError> {
+		self.trans = state.trans.into_iter().map(|(k, nexts)| {
+			let mut counts = Counts::new();
+			for s in nexts {
+				counts.add(s, 1);
+			}
+			(k, counts)
+		}).collect();
+		self.rewards = state.rewards.into_iter().collect();
+		Ok(())
+	}
 }
 
 impl<S: FiniteSpace, A: FiniteSpace> PlainModel<S, A>
@@ -0,0 +1,161 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use rand::{SeedableRng, StdRng};
+
+use environment::{Environment, Observation, FiniteSpace, Transition};
+use model::Model;
+use util::Chooser;
+use util::chooser::Weighted;
+
+// How far a sum of probabilities may be from 1 due to rounding
+const PROB_TOLERANCE: f64 = 1e-9;
+
+/// A model whose transition probabilities and rewards are given explicitly
+///
+/// Describes a small MDP that can be handed both to planners (as a Model) and to learners (as an
+/// Environment that samples from its tables). Since everything is known, update ignores observations.
+/// Pairs without any transitions lead nowhere, so they are worth 0 to planners and stepping into
+/// them as an environment panics. Using a pair whose probabilities do not sum to 1 also panics.
+#[derive(Debug)]
+pub struct TabularModel<S: FiniteSpace, A: FiniteSpace>
+	where S::Element: Hash + Eq, A::Element: Hash + Eq {
+	/// The states of the MDP
+	state_space: S,
+	/// The actions of the MDP
+	action_space: A,
+	/// The possible next states of each (state, action) pair, with their probabilities
+	trans: HashMap<(S::Element, A::Element), Vec<(S::Element, f64)>>,
+	/// The reward received for each (state, action, next state) triple
+	rewards: HashMap<(S::Element, A::Element, S::Element), f64>,
+	/// The possible initial states, with their probabilities
+	starts: Vec<(S::Element, f64)>,
+	/// States that end an episode
+	terminals: HashSet<S::Element>,
+	/// The current state when used as an Environment
+	curr: S::Element,
+	/// Used to sample transitions when used as an Environment
+	rng: StdRng,
+}
+
+impl<S: FiniteSpace, A: FiniteSpace> Model<S, A> for TabularModel<S, A>
+	where S::Element: Hash + Eq, A::Element: Hash + Eq {
+	fn transition(&self, curr: &S::Element, action: &A::Element, next: &S::Element) -> f64 {
+		self.trans.get(&(curr.clone(), action.clone()))
+				  .and_then(|succs| succs.iter().find(|&&(ref s, _)| s == next))
+				  .map_or(0.0, |&(_, p)| p)
+	}
+	fn reward(&self, curr: &S::Element, action: &A::Element, next: &S::Element) -> f64 {
+		let key = (curr.clone(), action.clone(), next.clone());
+		self.rewards.get(&key).cloned().unwrap_or(0.0)
+	}
+	fn update(&mut self, _: Transition<S, A>) {
+	}
+	fn successors(&self, curr: &S::Element, action: &A::Element) -> Vec<(S::Element, f64)> {
+		let succs = self.trans.get(&(curr.clone(), action.clone())).cloned().unwrap_or(Vec::new());
+		let total = succs.iter().fold(0.0, |sum, &(_, p)| sum + p);
+		assert!(succs.is_empty() || (total - 1.0).abs() < PROB_TOLERANCE,
+				"probabilities for {:?} in {:?} sum to {}, not 1", action, curr, total);
+		succs
+	}
+}
+
+impl<S: FiniteSpace + Clone, A: FiniteSpace + Clone> Environment for TabularModel<S, A>
+	where S::Element: Hash + Eq, A::Element: Hash + Eq {
+	type State = S;
+	type Action = A;
+
+	fn state_space(&self) -> S {
+		self.state_space.clone()
+	}
+	fn action_space(&self) -> A {
+		self.action_space.clone()
+	}
+	fn step(&mut self, action: &A::Element) -> Observation<S> {
+		let (states, probs): (Vec<_>, Vec<_>) = self.successors(&self.curr, action).into_iter().unzip();
+		assert!(!probs.is_empty(), "no transitions given for {:?} in {:?}", action, self.curr);
+
+		let next = Weighted.choose(&states, probs, &mut self.rng);
+		let reward = self.reward(&self.curr, action, &next);
+		self.curr = next;
+		Observation {
+			state: self.curr.clone(),
+			reward: reward,
+			done: self.terminals.contains(&self.curr)
+		}
+	}
+	fn reset(&mut self) -> Observation<S> {
+		let (states, probs): (Vec<_>, Vec<_>) = self.starts.iter().cloned().unzip();
+		self.curr = Weighted.choose(&states, probs, &mut self.rng);
+		Observation {
+			state: self.curr.clone(),
+			reward: 0.0,
+			done: self.terminals.contains(&self.curr)
+		}
+	}
+	fn render(&self) {
+		println!("{:?}", self.curr);
+	}
+}
+
+impl<S: FiniteSpace, A: FiniteSpace> TabularModel<S, A>
+	where S::Element: Hash + Eq, A::Element: Hash + Eq {
+	/// Returns a new TabularModel without any transitions, starting in the first state of state_space
+	pub fn new(state_space: S, action_space: A) -> TabularModel<S, A> {
+		let first = state_space.enumerate().into_iter().next().expect("state_space must not be empty");
+		TabularModel {
+			state_space: state_space,
+			action_space: action_space,
+			trans: HashMap::new(),
+			rewards: HashMap::new(),
+			starts: vec![(first.clone(), 1.0)],
+			terminals: HashSet::new(),
+			curr: first,
+			rng: StdRng::from_seed(&[0])
+		}
+	}
+	/// Makes performing action in curr lead to next with probability prob and the given reward
+	/// Replaces any previously given probability and reward for the same triple
+	///
+	/// Panics if the probabilities given for (curr, action) would sum to more than 1
+	pub fn with_transition(mut self, curr: S::Element, action: A::Element, next: S::Element,
+						   prob: f64, reward: f64) -> TabularModel<S, A> {
+		assert!(0.0 <= prob && prob <= 1.0, "prob must be between 0 and 1");
+
+		let succs = self.trans.entry((curr.clone(), action.clone())).or_insert(Vec::new());
+		succs.retain(|&(ref s, _)| *s != next);
+		if prob > 0.0 {
+			succs.push((next.clone(), prob));
+		}
+		let total = succs.iter().fold(0.0, |sum, &(_, p)| sum + p);
+		assert!(total < 1.0 + PROB_TOLERANCE, "probabilities for {:?} in {:?} sum to {}, more than 1", action, curr, total);
+		self.rewards.insert((curr, action, next), reward);
+		self
+	}
+	/// Makes the episode end whenever state is reached
+	pub fn with_terminal(mut self, state: S::Element) -> TabularModel<S, A> {
+		self.terminals.insert(state);
+		self
+	}
+	/// Makes every episode start in state
+	pub fn start(self, state: S::Element) -> TabularModel<S, A> {
+		self.start_dist(vec![(state, 1.0)])
+	}
+	/// Sets the initial states of episodes and their probabilities
+	pub fn start_dist(mut self, starts: Vec<(S::Element, f64)>) -> TabularModel<S, A> {
+		assert!(!starts.is_empty(), "there must be at least one initial state");
+
+		self.curr = starts[0].0.clone();
+		self.starts = starts;
+		self
+	}
+	/// Sets the seed used to sample transitions when self is used as an Environment
+	pub fn seed(mut self, seed: &[usize]) -> TabularModel<S, A> {
+		self.rng = StdRng::from_seed(seed);
+		self
+	}
+	/// Returns whether state ends an episode
+	pub fn is_terminal(&self, state: &S::Element) -> bool {
+		self.terminals.contains(state)
+	}
+}
\ No newline at end of file
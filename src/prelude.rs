@@ -6,12 +6,14 @@
 
 pub use environment::{Environment, Observation, Transition, Space, FiniteSpace};
 
-pub use trainer::{EpisodicTrainer, BatchTrainer, OnlineTrainer};
+pub use trainer::{EpisodicTrainer, ParallelTrainer, BatchTrainer, OnlineTrainer};
+pub use trainer::{Observer, Signal};
 
-pub use agent::Agent;
+pub use agent::{Agent, StochasticAgent};
 pub use agent::qagents::{GreedyQAgent, EGreedyQAgent};
 pub use agent::{RandomAgent, PolicyAgent, GaussianAgent};
 
 pub use util::TimePeriod;
-pub use util::{ParameterizedFunc, DifferentiableFunc, DifferentiableVecFunc, LogDiffFunc, GradientDescAlgo};
-pub use util::{QFunction, VFunction};
\ No newline at end of file
+pub use util::{ParameterizedFunc, DifferentiableFunc, DifferentiableVecFunc, LogDiffFunc, GradientDescAlgo, Baseline};
+pub use util::{QFunction, VFunction};
+pub use util::Stateful;
\ No newline at end of file
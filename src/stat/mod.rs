@@ -1,6 +1,6 @@
 //! Statistics Module
 
-use rand::{Rng, thread_rng};
+use rand::Rng;
 
 use num::Float;
 use num::cast::NumCast;
@@ -47,8 +47,7 @@ pub fn normalize<T: Float>(nums: &mut [T]) {
 }
 
 /// Performs in-place Fisher-Yates Shuffle
-pub fn shuffle<T: Clone>(nums: &mut [T]) {
-	let mut rng = thread_rng();
+pub fn shuffle<T: Clone, R: Rng>(nums: &mut [T], rng: &mut R) {
 	for i in 0..(nums.len()-1) {
 		let j = rng.gen_range(i, nums.len());
 
@@ -58,6 +57,8 @@ pub fn shuffle<T: Clone>(nums: &mut [T]) {
 
 #[cfg(test)]
 mod test {
+	use rand::{SeedableRng, StdRng};
+
 	use super::{mean_var, normalize, shuffle};
 
 	const EPSILON: f64 = 0.000001;
@@ -91,8 +92,16 @@ mod test {
 	#[test]
 	fn shuffle_simple() {
 		let mut nums: Vec<_> = (0..10).collect();
-		shuffle(&mut nums);
+		shuffle(&mut nums, &mut StdRng::from_seed(&[1, 2, 3]));
 
 		assert!(nums.iter().enumerate().fold(false, |acc, (i, &j)| acc || i != j));
 	}
+	#[test]
+	fn shuffle_same_seed() {
+		let (mut nums1, mut nums2): (Vec<_>, Vec<_>) = ((0..10).collect(), (0..10).collect());
+		shuffle(&mut nums1, &mut StdRng::from_seed(&[4, 5, 6]));
+		shuffle(&mut nums2, &mut StdRng::from_seed(&[4, 5, 6]));
+
+		assert_eq!(nums1, nums2);
+	}
 }
\ No newline at end of file
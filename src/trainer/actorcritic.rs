@@ -0,0 +1,225 @@
+use rand::Rng;
+
+use num::Float;
+use num::cast::NumCast;
+
+use environment::{Space, Environment};
+
+use trainer::{EpisodicTrainer, Observer, Iterative, Trajectory, train_episodic, collect_trajectory};
+
+use agent::Agent;
+
+use util::{LogDiffFunc, GradientDescAlgo, VFunction, Stateful};
+use util::checkpoint::CheckpointError;
+use util::TimePeriod;
+
+/// An advantage actor-critic (A2C) trainer
+///
+/// The agent (actor) is trained like in PolicyGradient, but the returns are replaced by
+/// [generalized advantage estimates](https://arxiv.org/abs/1506.02438) computed using a learned
+/// value function (critic) as the baseline. The critic is trained towards the lambda-returns.
+#[derive(Debug)]
+pub struct ActorCritic<F: Float, G: GradientDescAlgo<F>, V> {
+	/// The value function used as a baseline
+	critic: V,
+	/// Gradient descent algorithm used by the actor
+	grad_desc: G,
+	/// Discount factor
+	gamma: f64,
+	/// Trades off bias (0) and variance (1) of the advantage estimates
+	lambda: f64,
+	/// Learning rate of the actor
+	lr: F,
+	/// Learning rate of the critic
+	critic_alpha: f64,
+	/// Number of training iterations to perform when calling `train`
+	iters: usize,
+	/// Time period to act out in each training iteration
+	eval_period: TimePeriod,
+	/// Number of iterations of the current call to `train` already performed
+	curr_iter: usize,
+}
+
+/// The internal state of an ActorCritic trainer
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct ActorCriticState<G, V> {
+	/// State of the critic
+	pub critic: V,
+	/// State of the gradient descent algorithm
+	pub grad_desc: G,
+	/// Number of iterations of the current call to `train` already performed
+	pub curr_iter: usize,
+}
+
+impl<F: Float, S: Space, A: Space, G, V, T> EpisodicTrainer<S, A, T> for ActorCritic<F, G, V>
+	where T: Agent<S, A> + LogDiffFunc<S, A, F>,
+		  G: GradientDescAlgo<F>,
+		  V: VFunction<S> {
+	fn train_step<R: Rng>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R) {
+		self.iterate(agent, env, rng, &mut ());
+	}
+	fn train_with<R, O>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R, observer: &mut O)
+		where R: Rng, O: Observer<S, A, T> {
+		train_episodic(self, agent, rng, observer, |trainer, agent, rng, observer| {
+			trainer.iterate(agent, env, rng, observer)
+		});
+	}
+}
+
+impl<F: Float, G: GradientDescAlgo<F>, V> Iterative for ActorCritic<F, G, V> {
+	fn iters(&self) -> usize {
+		self.iters
+	}
+	fn curr_iter(&mut self) -> &mut usize {
+		&mut self.curr_iter
+	}
+}
+
+impl<F: Float, G, V> Stateful for ActorCritic<F, G, V>
+	where G: GradientDescAlgo<F> + Stateful,
+		  V: Stateful {
+	type State = ActorCriticState<G::State, V::State>;
+
+	fn get_state(&self) -> Self::State {
+		ActorCriticState {
+			critic: self.critic.get_state(),
+			grad_desc: self.grad_desc.get_state(),
+			curr_iter: self.curr_iter
+		}
+	}
+	fn set_state(&mut self, state: Self::State) -> Result<(), CheckpointError> {
+		self.critic.set_state(state.critic)?;
+		self.grad_desc.set_state(state.grad_desc)?;
+		self.curr_iter = state.curr_iter;
+		Ok(())
+	}
+}
+
+impl<G: GradientDescAlgo<f64>, V> ActorCritic<f64, G, V> {
+	/// Creates an ActorCritic with default parameter values and the given critic and gradient descent algorithm
+	pub fn default(critic: V, grad_desc: G) -> ActorCritic<f64, G, V> {
+		ActorCritic::new(critic, grad_desc, 0.99, 0.95, 0.001, 0.01, 100, TimePeriod::EPISODES(5))
+	}
+}
+
+impl<F: Float, G: GradientDescAlgo<F>, V> ActorCritic<F, G, V> {
+	/// Constructs a new ActorCritic with given information
+	pub fn new(critic: V, grad_desc: G, gamma: f64, lambda: f64, lr: F, critic_alpha: f64,
+			   iters: usize, eval_period: TimePeriod) -> ActorCritic<F, G, V> {
+		assert!(0.0 < gamma && gamma <= 1.0, "gamma must be between 0 and 1");
+		assert!(0.0 <= lambda && lambda <= 1.0, "lambda must be between 0 and 1");
+		assert!(F::zero() < lr && lr <= F::one(), "learning rate must be between 0 and 1");
+
+		ActorCritic {
+			critic: critic,
+			grad_desc: grad_desc,
+			gamma: gamma,
+			lambda: lambda,
+			lr: lr,
+			critic_alpha: critic_alpha,
+			iters: iters,
+			eval_period: eval_period,
+			curr_iter: 0
+		}
+	}
+	/// Updates gamma field of self
+	pub fn gamma(mut self, gamma: f64) -> ActorCritic<F, G, V> {
+		assert!(0.0 < gamma && gamma <= 1.0, "gamma must be between 0 and 1");
+
+		self.gamma = gamma;
+		self
+	}
+	/// Updates lambda field of self
+	pub fn lambda(mut self, lambda: f64) -> ActorCritic<F, G, V> {
+		assert!(0.0 <= lambda && lambda <= 1.0, "lambda must be between 0 and 1");
+
+		self.lambda = lambda;
+		self
+	}
+	/// Updates lr field of self
+	pub fn lr(mut self, lr: F) -> ActorCritic<F, G, V> {
+		assert!(F::zero() <= lr && lr <= F::one(), "lr must be between 0 and 1");
+
+		self.lr = lr;
+		self
+	}
+	/// Updates critic_alpha field of self
+	pub fn critic_alpha(mut self, critic_alpha: f64) -> ActorCritic<F, G, V> {
+		self.critic_alpha = critic_alpha;
+		self
+	}
+	/// Updates iters field of self
+	pub fn iters(mut self, iters: usize) -> ActorCritic<F, G, V> {
+		self.iters = iters;
+		self
+	}
+	/// Updates eval_period field of self
+	pub fn eval_period(mut self, eval_period: TimePeriod) -> ActorCritic<F, G, V> {
+		self.eval_period = eval_period;
+		self
+	}
+	/// Returns the critic used by self
+	pub fn get_critic(&self) -> &V {
+		&self.critic
+	}
+
+	// Performs one training iteration, returning the total reward received while exploring
+	// and whether observer asked to stop
+	fn iterate<S, A, T, R, O>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>,
+							  rng: &mut R, observer: &mut O) -> (f64, bool)
+		where S: Space,
+			  A: Space,
+			  T: Agent<S, A> + LogDiffFunc<S, A, F>,
+			  V: VFunction<S>,
+			  R: Rng,
+			  O: Observer<S, A, T> {
+		let (traj, score, stop) = collect_trajectory(agent, env, self.eval_period.clone(), rng, observer);
+		if traj.rewards.is_empty() {
+			return (score, stop);
+		}
+
+		let advantages = self.advantages(&traj);
+
+		let mut grad = vec![F::zero(); agent.num_params()];
+		for i in 0..advantages.len() {
+			let g = agent.log_grad(&traj.states[i], &traj.actions[i]);
+			let adv = NumCast::from(advantages[i]).unwrap();
+
+			for j in 0..g.len() {
+				grad[j] = grad[j] + g[j] * adv;
+			}
+		}
+
+		let mut params = agent.get_params();
+		let grad_step = self.grad_desc.calculate(grad, self.lr);
+		for i in 0..params.len() {
+			params[i] = params[i] + grad_step[i];
+		}
+		agent.set_params(params);
+
+		// The critic is moved towards the lambda-returns
+		for i in 0..advantages.len() {
+			let target = advantages[i] + self.critic.eval(&traj.states[i]);
+			self.critic.update(&traj.states[i], target, self.critic_alpha);
+		}
+
+		(score, stop)
+	}
+	// Generalized advantage estimates of every step in traj
+	fn advantages<S: Space, A: Space>(&self, traj: &Trajectory<S, A>) -> Vec<f64>
+		where V: VFunction<S> {
+		let len = traj.rewards.len();
+		let mut advantages = vec![0.0; len];
+		let mut running_adv = 0.0;
+		for t in (0..len).rev() {
+			let next_val = if traj.dones[t] {0.0} else {self.critic.eval(&traj.next_states[t])};
+			let delta = traj.rewards[t] + self.gamma*next_val - self.critic.eval(&traj.states[t]);
+
+			running_adv = if traj.dones[t] {0.0} else {running_adv};
+			running_adv = delta + self.gamma*self.lambda*running_adv;
+			advantages[t] = running_adv;
+		}
+		advantages
+	}
+}
\ No newline at end of file
@@ -0,0 +1,335 @@
+use std::f64;
+
+use rand::Rng;
+use rand::distributions::IndependentSample;
+use rand::distributions::normal::Normal;
+
+use num::Float;
+use num::cast::NumCast;
+
+use rulinalg::matrix::{Matrix, BaseMatrix};
+use rulinalg::vector::Vector;
+
+use environment::{Space, Environment};
+
+use trainer::{EpisodicTrainer, Observer, Iterative, train_episodic, rollout};
+
+use agent::Agent;
+
+use util::{ParameterizedFunc, Stateful};
+use util::checkpoint::CheckpointError;
+use util::TimePeriod;
+
+/// [Covariance Matrix Adaptation Evolution Strategy](https://arxiv.org/abs/1604.00772)
+///
+/// Samples parameters from a Gaussian with a full covariance matrix. Each iteration moves the mean
+/// towards a weighted average of the best half of the samples, adapts the covariance using both the
+/// path the mean has taken (rank-one update) and the spread of the best samples (rank-mu update),
+/// and adapts the overall step size by comparing the length of the path to its expected length
+/// under random selection (cumulative step-size adaptation)
+///
+/// Samples are drawn and whitened using the Cholesky factor of the covariance, as in
+/// [Cholesky CMA-ES](https://doi.org/10.1007/s10994-006-6266-6)
+#[derive(Debug)]
+pub struct CMAES<F: Float> {
+	/// The mean of the gaussian
+	mean_params: Vec<F>,
+	/// The step size (overall standard deviation) of the gaussian
+	deviation: f64,
+	/// The covariance of the gaussian, before scaling by the step size
+	covariance: Vec<Vec<f64>>,
+	/// Lower triangular Cholesky factor of the covariance
+	factor: Matrix<f64>,
+	/// Evolution path used to adapt the covariance
+	path_c: Vec<f64>,
+	/// Evolution path used to adapt the step size
+	path_s: Vec<f64>,
+	/// Number of times the gaussian has been updated
+	generation: usize,
+	/// Number of samples to take (if None, 4 + floor(3 ln n) for n parameters)
+	num_samples: Option<usize>,
+	/// Time period to evaluate each parameter sample on
+	eval_period: TimePeriod,
+	/// Number of training iterations to perform when calling `train`
+	iters: usize,
+	/// Number of iterations of the current call to `train` already performed
+	curr_iter: usize,
+}
+
+/// The internal state of a CMAES trainer
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct CMAESState<F> {
+	/// The mean of the gaussian
+	pub mean_params: Vec<F>,
+	/// The step size of the gaussian
+	pub deviation: f64,
+	/// The covariance of the gaussian, before scaling by the step size
+	pub covariance: Vec<Vec<f64>>,
+	/// Evolution path used to adapt the covariance
+	pub path_c: Vec<f64>,
+	/// Evolution path used to adapt the step size
+	pub path_s: Vec<f64>,
+	/// Number of times the gaussian has been updated
+	pub generation: usize,
+	/// Number of iterations of the current call to `train` already performed
+	pub curr_iter: usize,
+}
+
+impl<F: Float, S: Space, A: Space, T> EpisodicTrainer<S, A, T> for CMAES<F>
+	where T: Agent<S, A> + ParameterizedFunc<F> {
+	fn train_step<R: Rng>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R) {
+		self.iterate(agent, env, rng, &mut ());
+	}
+	fn train_with<R, O>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R, observer: &mut O)
+		where R: Rng, O: Observer<S, A, T> {
+		train_episodic(self, agent, rng, observer, |trainer, agent, rng, observer| {
+			trainer.iterate(agent, env, rng, observer)
+		});
+	}
+}
+
+impl<F: Float> Iterative for CMAES<F> {
+	fn iters(&self) -> usize {
+		self.iters
+	}
+	fn curr_iter(&mut self) -> &mut usize {
+		&mut self.curr_iter
+	}
+}
+
+impl<F: Float> Stateful for CMAES<F> {
+	type State = CMAESState<F>;
+
+	fn get_state(&self) -> CMAESState<F> {
+		CMAESState {
+			mean_params: self.mean_params.clone(),
+			deviation: self.deviation,
+			covariance: self.covariance.clone(),
+			path_c: self.path_c.clone(),
+			path_s: self.path_s.clone(),
+			generation: self.generation,
+			curr_iter: self.curr_iter
+		}
+	}
+	fn set_state(&mut self, state: CMAESState<F>) -> Result<(), CheckpointError> {
+		let n = state.mean_params.len();
+		if state.covariance.len() != n || state.covariance.iter().any(|row| row.len() != n) {
+			return Err(CheckpointError::Mismatch(format!("{} means but covariance is not {}x{}", n, n, n)));
+		} else if state.path_c.len() != n || state.path_s.len() != n {
+			return Err(CheckpointError::Mismatch(format!("{} means but evolution paths of lengths {} and {}",
+														 n, state.path_c.len(), state.path_s.len())));
+		}
+		let factor = match cholesky(&state.covariance) {
+			Some(factor) => factor,
+			None => return Err(CheckpointError::Mismatch("covariance is not positive definite".to_string()))
+		};
+		self.mean_params = state.mean_params;
+		self.deviation = state.deviation;
+		self.covariance = state.covariance;
+		self.factor = factor;
+		self.path_c = state.path_c;
+		self.path_s = state.path_s;
+		self.generation = state.generation;
+		self.curr_iter = state.curr_iter;
+		Ok(())
+	}
+}
+
+impl<F: Float> CMAES<F> {
+	// Performs one training iteration, returning the average score of the samples
+	// and whether observer asked to stop
+	fn iterate<S, A, T, R, O>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>,
+							  rng: &mut R, observer: &mut O) -> (f64, bool)
+		where S: Space,
+			  A: Space,
+			  T: Agent<S, A> + ParameterizedFunc<F>,
+			  R: Rng,
+			  O: Observer<S, A, T> {
+		if self.mean_params.is_empty() {
+			self.mean_params = agent.get_params();
+		}
+		let n = self.mean_params.len();
+		if self.covariance.len() != n {
+			self.covariance = identity(n);
+			self.path_c = vec![0.0; n];
+			self.path_s = vec![0.0; n];
+			self.generation = 0;
+			self.decompose();
+		}
+
+		let num_samples = self.num_samples.unwrap_or(4 + (3.0*(n as f64).ln()).floor() as usize).max(2);
+		let mean: Vec<f64> = self.mean_params.iter().map(|x| x.to_f64().unwrap()).collect();
+
+		// Each sample is mean + deviation*y where y = L*z is drawn from N(0, covariance)
+		let normal = Normal::new(0.0, 1.0);
+		let steps: Vec<Vec<f64>> = (0..num_samples).map(|_| {
+			let z: Vec<f64> = (0..n).map(|_| normal.ind_sample(rng)).collect();
+			(&self.factor * Vector::new(z)).into_vec()
+		}).collect();
+
+		let mut stop = false;
+		let mut scored: Vec<_> = steps.into_iter().map(|y| {
+			let params = mean.iter().zip(&y).map(|(m, y)| NumCast::from(m + self.deviation*y).unwrap()).collect();
+			let (score, s_stop) = self.eval(params, agent, env, rng, observer);
+			stop = stop || s_stop;
+			(score, y)
+		}).collect();
+		let avg_score = scored.iter().fold(0.0, |acc, s| acc + s.0)/num_samples as f64;
+		// Best samples first, with NaN scores treated as the worst
+		scored.sort_by(|x, y| match (x.0.is_nan(), y.0.is_nan()) {
+			(false, false) => y.0.partial_cmp(&x.0).unwrap(),
+			(x_nan, y_nan) => x_nan.cmp(&y_nan)
+		});
+
+		self.update(&mean, &scored[..num_samples/2]);
+
+		agent.set_params(self.mean_params.clone());
+		(avg_score, stop)
+	}
+	// Updates the gaussian given the steps taken by the best samples, from best to worst
+	fn update(&mut self, mean: &[f64], best: &[(f64, Vec<f64>)]) {
+		let n = mean.len() as f64;
+		let mu = best.len();
+
+		let raw_weights: Vec<f64> = (0..mu).map(|i| (mu as f64 + 0.5).ln() - (i as f64 + 1.0).ln()).collect();
+		let total: f64 = raw_weights.iter().sum();
+		let weights: Vec<f64> = raw_weights.iter().map(|w| w/total).collect();
+		let mu_eff = 1.0/weights.iter().map(|w| w*w).sum::<f64>();
+
+		let cc = (4.0 + mu_eff/n)/(n + 4.0 + 2.0*mu_eff/n);
+		let cs = (mu_eff + 2.0)/(n + mu_eff + 5.0);
+		let c1 = 2.0/((n + 1.3).powi(2) + mu_eff);
+		let cmu = (1.0 - c1).min(2.0*(mu_eff - 2.0 + 1.0/mu_eff)/((n + 2.0).powi(2) + mu_eff));
+		let damps = 1.0 + 2.0*(((mu_eff - 1.0)/(n + 1.0)).sqrt() - 1.0).max(0.0) + cs;
+		let chi_n = n.sqrt()*(1.0 - 1.0/(4.0*n) + 1.0/(21.0*n*n));
+
+		// Weighted average of the best steps, which moves the mean
+		let mut step = vec![0.0; mean.len()];
+		for (w, &(_, ref y)) in weights.iter().zip(best) {
+			for i in 0..step.len() {
+				step[i] += w*y[i];
+			}
+		}
+		self.mean_params = mean.iter().zip(&step).map(|(m, y)| NumCast::from(m + self.deviation*y).unwrap()).collect();
+
+		// The step-size path uses the step whitened by L^(-1)
+		let whitened = self.factor.solve_l_triangular(Vector::new(step.clone()))
+								  .expect("Cholesky factor of covariance is singular").into_vec();
+		let coeff = (cs*(2.0 - cs)*mu_eff).sqrt();
+		for i in 0..step.len() {
+			self.path_s[i] = (1.0 - cs)*self.path_s[i] + coeff*whitened[i];
+		}
+
+		self.generation += 1;
+		let path_len = norm(&self.path_s);
+		// Stall the covariance path while the step-size path is unusually long
+		let correction = (1.0 - (1.0 - cs).powi(2*self.generation as i32)).sqrt();
+		let hsig = path_len/correction/chi_n < 1.4 + 2.0/(n + 1.0);
+
+		let coeff = if hsig {(cc*(2.0 - cc)*mu_eff).sqrt()} else {0.0};
+		for i in 0..step.len() {
+			self.path_c[i] = (1.0 - cc)*self.path_c[i] + coeff*step[i];
+		}
+
+		let decay = 1.0 - c1 - cmu + if hsig {0.0} else {c1*cc*(2.0 - cc)};
+		for i in 0..step.len() {
+			for j in 0..step.len() {
+				let rank_mu = weights.iter().zip(best).fold(0.0, |sum, (w, &(_, ref y))| sum + w*y[i]*y[j]);
+				self.covariance[i][j] = decay*self.covariance[i][j] + c1*self.path_c[i]*self.path_c[j] + cmu*rank_mu;
+			}
+		}
+
+		self.deviation *= ((cs/damps)*(path_len/chi_n - 1.0)).exp();
+		self.decompose();
+	}
+	// Recomputes the Cholesky factor of the covariance, keeping the old one if rounding has made
+	// the covariance lose positive definiteness
+	fn decompose(&mut self) {
+		if let Some(factor) = cholesky(&self.covariance) {
+			self.factor = factor;
+		}
+	}
+}
+
+impl<F: Float> Default for CMAES<F> {
+	/// Creates a new CMAES with some default values
+	fn default() -> CMAES<F> {
+		CMAES::new(0.5, TimePeriod::EPISODES(1), 10)
+	}
+}
+
+impl<F: Float> CMAES<F> {
+	/// Constructs a new CMAES with the given initial step size, using the default number of samples
+	pub fn new(deviation: f64, eval_period: TimePeriod, iters: usize) -> CMAES<F> {
+		assert!(deviation > 0.0, "deviation must be greater than 0");
+
+		CMAES {
+			mean_params: Vec::new(),
+			deviation: deviation,
+			covariance: Vec::new(),
+			factor: Matrix::new(0, 0, Vec::new()),
+			path_c: Vec::new(),
+			path_s: Vec::new(),
+			generation: 0,
+			num_samples: None,
+			eval_period: eval_period,
+			iters: iters,
+			curr_iter: 0
+		}
+	}
+	/// Updates deviation field of self (the initial step size)
+	pub fn deviation(mut self, deviation: f64) -> CMAES<F> {
+		assert!(deviation > 0.0, "deviation must be greater than 0");
+
+		self.deviation = deviation;
+		self
+	}
+	/// Updates num_samples field of self
+	pub fn num_samples(mut self, num_samples: usize) -> CMAES<F> {
+		self.num_samples = Some(num_samples);
+		self
+	}
+	/// Updates eval_period field of self
+	pub fn eval_period(mut self, eval_period: TimePeriod) -> CMAES<F> {
+		self.eval_period = eval_period;
+		self
+	}
+	/// Updates iters field of self
+	pub fn iters(mut self, iters: usize) -> CMAES<F> {
+		self.iters = iters;
+		self
+	}
+	/// Returns the current step size
+	pub fn get_deviation(&self) -> f64 {
+		self.deviation
+	}
+
+	fn eval<T, S, A, R, O>(&self, params: Vec<F>, agent: &mut T, env: &mut Environment<State=S, Action=A>,
+						   rng: &mut R, observer: &mut O) -> (f64, bool)
+		where 	R: Rng,
+				S: Space,
+				A: Space,
+				T: Agent<S, A> + ParameterizedFunc<F>,
+				O: Observer<S, A, T> {
+		agent.set_params(params);
+
+		rollout(agent, env, self.eval_period.clone(), rng, observer)
+	}
+}
+
+fn identity(n: usize) -> Vec<Vec<f64>> {
+	(0..n).map(|i| (0..n).map(|j| if i == j {1.0} else {0.0}).collect()).collect()
+}
+
+fn norm(x: &[f64]) -> f64 {
+	x.iter().map(|x| x*x).sum::<f64>().sqrt()
+}
+
+// Lower triangular L with L*L^T = mat, if mat is positive definite
+fn cholesky(mat: &[Vec<f64>]) -> Option<Matrix<f64>> {
+	let n = mat.len();
+	let mat = Matrix::new(n, n, mat.iter().flat_map(|row| row.iter().cloned()).collect::<Vec<_>>());
+	// rulinalg does not notice a negative pivot on the last diagonal entry, so every pivot is checked
+	mat.cholesky().ok().and_then(|l| if l.diag().all(|&d| d > 0.0) {Some(l)} else {None})
+}
\ No newline at end of file
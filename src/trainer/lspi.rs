@@ -1,10 +1,11 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use rand::Rng;
+
 use num::Float;
 use num::cast::NumCast;
 
-use rulinalg::matrix::{Matrix, BaseMatrix};
-use rulinalg::vector::Vector;
-
-use environment::{Space, Transition};
+use environment::{Space, FiniteSpace, Transition};
 
 use trainer::BatchTrainer;
 
@@ -16,72 +17,226 @@ use util::{ParameterizedFunc, FeatureExtractor};
 ///
 /// * Uses LSTD-Q for calculating the Q-function associated with a policy
 /// * Only trains linear Q-functions (not currently enforced by library)
+///
+/// Each iteration evaluates the policy that is greedy with respect to the agent's current weights,
+/// then replaces the weights with the result. Iterating stops once no weight changes by more than
+/// tolerance, or after max_iters iterations. A small (positive) ridge penalty keeps the linear systems
+/// solvable when some features never appear in the transitions. If a system is singular anyway,
+/// iterating stops early and the agent keeps the weights from the last system that was solved.
 #[derive(Debug)]
-pub struct LSPolicyIteration<F: Float> {
+pub struct LSPolicyIteration<F: Float, A: FiniteSpace> {
+	/// All the possible performable actions
+	actions: Vec<A::Element>,
 	/// discount factor
 	gamma: F,
+	/// Added to the diagonal of each system before solving it
+	ridge: F,
+	/// Iteration stops once no weight changes by more than this
+	tolerance: F,
+	/// Maximum number of policy iterations performed when calling train
+	max_iters: usize,
 }
 
-// currently space inefficient for QLinear (intended Q function) since it uses
-// O(n^2d^2) space for an n-action space and d features. Since we have to solve
-// a system with a matrix of this size, that also slows things down. There is probably
-// a way to atleast reduce this to O(nd^2) space since each action uses only d features
-// instead of the requiring the full nd features that techincally exist
-impl<F: Float + 'static, S: Space, A: Space, T> BatchTrainer<S, A, T> for LSPolicyIteration<F>
+// The system being solved has a row and column for every feature, but only entries for features
+// that appear together in some transition are stored. For QLinear with n actions of d features each,
+// that is O(d^2) entries for every (action, next action) pair actually seen instead of a dense
+// O(n^2d^2) matrix, and building the system costs O(d^2) per transition. It is solved by sparse
+// Gaussian elimination, which only allocates the entries filled in along the way
+impl<F: Float + 'static, S: Space, A: FiniteSpace, T> BatchTrainer<S, A, T> for LSPolicyIteration<F, A>
 	where T: Agent<S, A> + ParameterizedFunc<F> + FeatureExtractor<S, A, F> {
-	fn train(&mut self, agent: &mut T, transitions: Vec<Transition<S, A>>) {
-		let num_features = agent.num_features();
-
-		let mut mat: Matrix<F> = Matrix::zeros(num_features, num_features);
-		let mut vec: Matrix<F> = Matrix::zeros(num_features, 1);
+	fn train<R: Rng>(&mut self, agent: &mut T, transitions: Vec<Transition<S, A>>, _: &mut R) {
+		if transitions.is_empty() {
+			return;
+		}
 
+		let num_features = agent.num_features();
 		// Divide by number of transitions in the end for numeric stability
 		let num: F = NumCast::from(transitions.len()).unwrap();
-		for transition in transitions {
-			let (state, action, reward, next) = transition;
-			let next_action = agent.get_action(&next);
-
-			let feats = Matrix::new(num_features, 1, agent.extract(&state, &action));
-			let next_feats = Matrix::new(num_features, 1, agent.extract(&next, &next_action));
-			let feats_t = feats.clone().transpose();
-			
-			mat += &feats * &(&feats_t - &next_feats.transpose() * self.gamma);
-
-			let reward: F = NumCast::from(reward).unwrap();
-			vec += &feats * reward;
-		}
 
-		let vec = Vector::new(vec.into_vec());
-		// Optimal weights w should satisfy mat * w = vec
-		let weights = (&mat / num).solve(&vec / num).unwrap();
+		let feats: Vec<_> = transitions.iter().map(|t| agent.extract_sparse(&t.0, &t.1)).collect();
+		let next_feats: Vec<Vec<_>> = transitions.iter().map(|t| {
+			self.actions.iter().map(|a| agent.extract_sparse(&t.3, a)).collect()
+		}).collect();
+
+		// The reward part of the system does not depend on the policy
+		let mut vec = vec![F::zero(); num_features];
+		for (feat, t) in feats.iter().zip(&transitions) {
+			let reward: F = NumCast::from(t.2).unwrap();
+			for &(i, x) in feat {
+				vec[i] = vec[i] + x*reward/num;
+			}
+		}
 
-		agent.set_params(weights.into_vec());
+		let mut weights = agent.get_params();
+		for _ in 0..self.max_iters {
+			let mut rows: Vec<BTreeMap<usize, F>> = (0..num_features).map(|i| {
+				let mut row = BTreeMap::new();
+				row.insert(i, self.ridge);
+				row
+			}).collect();
+			for (feat, next) in feats.iter().zip(&next_feats) {
+				let next = &next[best_index(&weights, next)];
+				for &(i, x) in feat {
+					for &(j, y) in feat {
+						let entry = rows[i].entry(j).or_insert(F::zero());
+						*entry = *entry + x*y/num;
+					}
+					for &(j, y) in next {
+						let entry = rows[i].entry(j).or_insert(F::zero());
+						*entry = *entry - x*self.gamma*y/num;
+					}
+				}
+			}
+
+			// Optimal weights w should satisfy rows * w = vec
+			let new_weights = match solve_sparse(rows, vec.clone()) {
+				Some(new_weights) => new_weights,
+				None => break
+			};
+			let change = weights.iter().zip(&new_weights).fold(F::zero(), |max, (&w0, &w1)| max.max((w1 - w0).abs()));
+
+			weights = new_weights;
+			agent.set_params(weights.clone());
+			if change <= self.tolerance {
+				break;
+			}
+		}
 	}
 }
 
-impl<F: Float> Default for LSPolicyIteration<F> {
-	/// Creates a new LSPolicyIteration with gamma = 0.99
-	fn default() -> LSPolicyIteration<F> {
-		LSPolicyIteration {
-			gamma: NumCast::from(0.99).unwrap()
-		}
+impl<A: FiniteSpace> LSPolicyIteration<f64, A> {
+	/// Creates a new LSPolicyIteration with gamma = 0.99 and default ridge, tolerance and max_iters
+	pub fn default(action_space: A) -> LSPolicyIteration<f64, A> {
+		LSPolicyIteration::new(action_space, 0.99)
 	}
 }
 
-impl<F: Float> LSPolicyIteration<F> {
-	/// Constructs a new LSPolicyIteration with randomly initialized mean and deviation
-	pub fn new(gamma: F) -> LSPolicyIteration<F> {
-		assert!(F::zero() <= gamma && gamma <= F::one(), "elite must be between 0 and 1");
+impl<F: Float, A: FiniteSpace> LSPolicyIteration<F, A> {
+	/// Constructs a new LSPolicyIteration with the given discount factor
+	pub fn new(action_space: A, gamma: F) -> LSPolicyIteration<F, A> {
+		assert!(F::zero() <= gamma && gamma <= F::one(), "gamma must be between 0 and 1");
 
 		LSPolicyIteration {
-			gamma: gamma
+			actions: action_space.enumerate(),
+			gamma: gamma,
+			ridge: NumCast::from(1e-6).unwrap(),
+			tolerance: NumCast::from(1e-6).unwrap(),
+			max_iters: 20
 		}
 	}
 	/// Updates gamma field of self
-	pub fn gamma(mut self, gamma: F) -> LSPolicyIteration<F> {
-		assert!(F::zero() <= gamma && gamma <= F::one(), "elite must be between 0 and 1");
+	pub fn gamma(mut self, gamma: F) -> LSPolicyIteration<F, A> {
+		assert!(F::zero() <= gamma && gamma <= F::one(), "gamma must be between 0 and 1");
 
 		self.gamma = gamma;
 		self
 	}
+	/// Updates ridge field of self
+	pub fn ridge(mut self, ridge: F) -> LSPolicyIteration<F, A> {
+		assert!(ridge > F::zero(), "ridge must be positive");
+
+		self.ridge = ridge;
+		self
+	}
+	/// Updates tolerance field of self
+	pub fn tolerance(mut self, tolerance: F) -> LSPolicyIteration<F, A> {
+		self.tolerance = tolerance;
+		self
+	}
+	/// Updates max_iters field of self
+	pub fn max_iters(mut self, max_iters: usize) -> LSPolicyIteration<F, A> {
+		self.max_iters = max_iters;
+		self
+	}
+}
+
+// Index of the first action whose features have the highest value under weights
+fn best_index<F: Float>(weights: &[F], feats: &[Vec<(usize, F)>]) -> usize {
+	let vals = feats.iter().map(|feat| feat.iter().fold(F::zero(), |sum, &(i, x)| sum + weights[i]*x));
+	let mut best = (0, F::neg_infinity());
+	for (i, val) in vals.enumerate() {
+		if val > best.1 {
+			best = (i, val);
+		}
+	}
+	best.0
+}
+// Solves mat * x = vec by Gaussian elimination with partial pivoting, where rows holds the nonzero
+// entries of each row of mat. Returns None if mat is singular
+fn solve_sparse<F: Float>(mut rows: Vec<BTreeMap<usize, F>>, mut vec: Vec<F>) -> Option<Vec<F>> {
+	let n = vec.len();
+	// The rows with an entry in each column
+	let mut cols: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); n];
+	for (r, row) in rows.iter().enumerate() {
+		for &c in row.keys() {
+			cols[c].insert(r);
+		}
+	}
+
+	// Row used to eliminate each column
+	let mut pivots = Vec::with_capacity(n);
+	let mut used = vec![false; n];
+	for k in 0..n {
+		let mut pivot: Option<usize> = None;
+		for &r in cols[k].iter().filter(|&&r| !used[r]) {
+			match pivot {
+				Some(p) if rows[p][&k].abs() >= rows[r][&k].abs() => {},
+				_ => pivot = Some(r)
+			}
+		}
+		let p = match pivot {
+			Some(p) if rows[p][&k] != F::zero() => p,
+			_ => return None
+		};
+		used[p] = true;
+		pivots.push(p);
+
+		let pivot_row = rows[p].clone();
+		let below: Vec<usize> = cols[k].iter().cloned().filter(|&r| !used[r]).collect();
+		for r in below {
+			let factor = rows[r].remove(&k).unwrap()/pivot_row[&k];
+			for (&c, &x) in pivot_row.iter().filter(|&(&c, _)| c != k) {
+				let entry = rows[r].entry(c).or_insert(F::zero());
+				*entry = *entry - factor*x;
+				cols[c].insert(r);
+			}
+			vec[r] = vec[r] - factor*vec[p];
+		}
+	}
+
+	let mut x = vec![F::zero(); n];
+	for k in (0..n).rev() {
+		let p = pivots[k];
+		let sum = rows[p].iter().filter(|&(&c, _)| c > k).fold(vec[p], |sum, (&c, &a)| sum - a*x[c]);
+		x[k] = sum/rows[p][&k];
+	}
+	Some(x)
+}
+
+#[cfg(test)]
+mod test {
+	use std::collections::BTreeMap;
+
+	use super::solve_sparse;
+
+	fn rows(dense: &[&[f64]]) -> Vec<BTreeMap<usize, f64>> {
+		dense.iter().map(|row| {
+			row.iter().cloned().enumerate().filter(|&(_, x)| x != 0.0).collect()
+		}).collect()
+	}
+
+	#[test]
+	fn pivots_past_missing_diagonal() {
+		let mat = rows(&[&[0.0, 2.0, 0.0], &[1.0, 0.0, 1.0], &[1.0, 1.0, 0.0]]);
+		let x = solve_sparse(mat, vec![4.0, 4.0, 3.0]).unwrap();
+		for (xi, expected) in x.iter().zip(&[1.0, 2.0, 3.0]) {
+			assert!((xi - expected).abs() < 1e-12);
+		}
+	}
+
+	#[test]
+	fn rejects_singular_system() {
+		let mat = rows(&[&[1.0, 2.0], &[2.0, 4.0]]);
+		assert_eq!(solve_sparse(mat, vec![1.0, 2.0]), None);
+	}
 }
\ No newline at end of file
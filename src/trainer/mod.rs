@@ -5,35 +5,449 @@ mod cem;
 mod lspi;
 mod policygrad;
 mod nes;
+mod actorcritic;
+mod ppo;
+mod cmaes;
 
-pub use self::qlearner::{QLearner, SARSALearner, DynaQ, FittedQIteration};
+pub use self::qlearner::{QLearner, SARSALearner, DynaQ, FittedQIteration, DQN, TargetUpdate};
+pub use self::qlearner::{ExpectedSARSA, NStepQLearner, NStepSARSA, SARSALambda, WatkinsQLambda, DoubleQLearner};
+pub use self::qlearner::{PrioritizedSweeping, GenerativeDynaQ, MonteCarlo, OffPolicyMonteCarlo, VisitType};
 pub use self::cem::CrossEntropy;
 pub use self::lspi::LSPolicyIteration;
 pub use self::policygrad::PolicyGradient;
 pub use self::nes::NaturalEvo;
+pub use self::actorcritic::ActorCritic;
+pub use self::ppo::PPO;
+pub use self::cmaes::CMAES;
+
+use std::sync::Arc;
+use std::sync::mpsc;
+use std::thread;
+
+use rand::{Rng, SeedableRng, StdRng};
+
+use num::Float;
 
 use environment::{Space, Environment, Transition};
 
 use agent::Agent;
 
+use util::{ParameterizedFunc, TimePeriod};
+
+// Every trainer draws its randomness (including the agent's) from the rng it is handed,
+// so seeding that rng makes a training run reproducible
+
+/// Tells a trainer whether it should keep training
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+	/// Keep training
+	Continue,
+	/// Stop training early
+	Stop,
+}
+
+/// Watches a trainer as it trains an agent
+///
+/// Every method does nothing by default, so only the events of interest need to be implemented.
+/// Returning `Signal::Stop` ends training early. Online trainers stop right after the current step.
+/// Episodic trainers first finish the current iteration, and remember how many iterations they
+/// completed so calling `train` again resumes where they stopped.
+pub trait Observer<S: Space, A: Space, T> {
+	/// Called after every step the agent takes in the environment
+	fn on_step(&mut self, _agent: &mut T, _transition: &Transition<S, A>) -> Signal {
+		Signal::Continue
+	}
+	/// Called whenever an episode ends with the (undiscounted) total reward received during it
+	fn on_episode_end(&mut self, _agent: &mut T, _ep_return: f64) -> Signal {
+		Signal::Continue
+	}
+	/// Called after every training iteration with the score achieved during it
+	///
+	/// Online trainers treat every episode as one iteration scored by its return, and end with
+	/// one last (partial) iteration when training stops partway through an episode
+	fn on_iter_end(&mut self, _agent: &mut T, _iter: usize, _score: f64) -> Signal {
+		Signal::Continue
+	}
+}
+
+/// The observer used by `train`, which watches nothing
+impl<S: Space, A: Space, T> Observer<S, A, T> for () {}
+
 /// Represents a way to train an agent online (by interacting with the environment)
 pub trait OnlineTrainer<S: Space, A: Space, T: Agent<S, A>> {
 	/// Performs one training iteration using the given transition
-	fn train_step(&mut self, agent: &mut T, transition: Transition<S, A>);
+	fn train_step<R: Rng>(&mut self, agent: &mut T, transition: Transition<S, A>, rng: &mut R);
+	/// Called by `train` after the last transition of an episode, or with done false when training
+	/// stops partway through one. Does nothing by default
+	fn finish_episode<R: Rng>(&mut self, _agent: &mut T, _done: bool, _rng: &mut R) {}
+	/// Trains the agent in the environment, reporting progress to observer
+	fn train_with<R, O>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R, observer: &mut O)
+		where R: Rng, O: Observer<S, A, T>;
 	/// Automatically trains the agent to perform well in the environment
-	fn train(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>);
+	fn train<R: Rng>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R) {
+		self.train_with(agent, env, rng, &mut ())
+	}
 }
 
 /// Trains agents 1 "episode" at a time
 pub trait EpisodicTrainer<S: Space, A: Space, T: Agent<S, A>> {
 	/// Trains agent using 1 "episodes" worth of exploration
-	fn train_step(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>);
+	fn train_step<R: Rng>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R);
+	/// Trains the agent in the environment, reporting progress to observer
+	fn train_with<R, O>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R, observer: &mut O)
+		where R: Rng, O: Observer<S, A, T>;
 	/// Trains agent to perform well in the environment, potentially acting out multiple episodes
-	fn train(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>);
+	fn train<R: Rng>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R) {
+		self.train_with(agent, env, rng, &mut ())
+	}
+}
+
+/// An episodic trainer that can act out its episodes in several environments at once
+///
+/// Each of the num_threads threads is started once per call with its own clone of the agent, and acts
+/// out every sample it is given in a fresh environment created by make_env. Every sample is acted out
+/// with an rng seeded from rng, so as long as make_env always creates environments that behave the
+/// same, a seeded rng makes training reproducible whatever num_threads is, even for environments with
+/// their own randomness or state. Observers see every step and episode, in the same order `train_with`
+/// would report them, once the threads have finished an iteration.
+pub trait ParallelTrainer<S: Space, A: Space, T: Agent<S, A>> : EpisodicTrainer<S, A, T> {
+	/// Trains the agent in environments created by make_env on num_threads threads, reporting progress to observer
+	fn train_parallel_with<E, M, R, O>(&mut self, agent: &mut T, make_env: M, num_threads: usize, rng: &mut R, observer: &mut O)
+		where E: Environment<State=S, Action=A>,
+			  M: Fn() -> E + Send + Sync + 'static,
+			  R: Rng,
+			  O: Observer<S, A, T>;
+	/// Trains agent like `train`, but acts in environments created by make_env on num_threads threads
+	fn train_parallel<E, M, R>(&mut self, agent: &mut T, make_env: M, num_threads: usize, rng: &mut R)
+		where E: Environment<State=S, Action=A>,
+			  M: Fn() -> E + Send + Sync + 'static,
+			  R: Rng {
+		self.train_parallel_with(agent, make_env, num_threads, rng, &mut ())
+	}
 }
 
 /// Represents a way to train an agent from a set of transitions
 pub trait BatchTrainer<S: Space, A: Space, T: Agent<S, A>> {
 	/// Trains agent based on the observed transitions
-	fn train(&mut self, agent: &mut T, transitions: Vec<Transition<S, A>>);
+	fn train<R: Rng>(&mut self, agent: &mut T, transitions: Vec<Transition<S, A>>, rng: &mut R);
+}
+
+// Episodic trainers that perform a set number of iterations when calling `train`
+trait Iterative {
+	// Number of training iterations to perform when calling `train`
+	fn iters(&self) -> usize;
+	// Number of iterations of the current call to `train` already performed
+	fn curr_iter(&mut self) -> &mut usize;
+}
+
+// The training loop shared by episodic trainers. Calls iterate until the current call to `train` has done
+// all its iterations, or observer asks to stop (in which case the next call resumes where this one stopped)
+fn train_episodic<L, S, A, T, R, O, I>(trainer: &mut L, agent: &mut T, rng: &mut R, observer: &mut O, mut iterate: I)
+	where L: Iterative,
+		  S: Space,
+		  A: Space,
+		  T: Agent<S, A>,
+		  R: Rng,
+		  O: Observer<S, A, T>,
+		  I: FnMut(&mut L, &mut T, &mut R, &mut O) -> (f64, bool) {
+	while *trainer.curr_iter() < trainer.iters() {
+		let (score, stop) = iterate(trainer, agent, rng, observer);
+		let iter = *trainer.curr_iter();
+		*trainer.curr_iter() += 1;
+
+		if observer.on_iter_end(agent, iter, score) == Signal::Stop || stop {
+			return;
+		}
+	}
+	*trainer.curr_iter() = 0;
+}
+
+// The training loop shared by online trainers
+fn train_online<S, A, T, L, R, O>(trainer: &mut L, agent: &mut T, env: &mut Environment<State=S, Action=A>, 
+								  period: TimePeriod, rng: &mut R, observer: &mut O)
+	where S: Space,
+		  A: Space,
+		  T: Agent<S, A>,
+		  L: OnlineTrainer<S, A, T>,
+		  R: Rng,
+		  O: Observer<S, A, T> {
+	let mut obs = env.reset();
+	let mut ep_return = 0.0;
+	let mut iter = 0;
+	let mut mid_episode = false;
+	let mut time_remaining = period;
+	while !time_remaining.is_none() {
+		let action = agent.get_action(&obs.state, rng);
+		let new_obs = env.step(&action);
+		let transition = (obs.state, action, new_obs.reward, new_obs.state.clone());
+		trainer.train_step(agent, transition.clone(), rng);
+		if new_obs.done {
+			trainer.finish_episode(agent, true, rng);
+		}
+		mid_episode = !new_obs.done;
+
+		let mut stop = observer.on_step(agent, &transition) == Signal::Stop;
+		ep_return += new_obs.reward;
+
+		time_remaining = time_remaining.dec(new_obs.done);
+		obs = if new_obs.done {
+			stop = observer.on_episode_end(agent, ep_return) == Signal::Stop || stop;
+			stop = observer.on_iter_end(agent, iter, ep_return) == Signal::Stop || stop;
+			iter += 1;
+			ep_return = 0.0;
+			env.reset()
+		} else {new_obs};
+
+		if stop {
+			break;
+		}
+	}
+	if mid_episode {
+		trainer.finish_episode(agent, false, rng);
+		observer.on_iter_end(agent, iter, ep_return);
+	}
+}
+
+// Acts out period in the environment, returning the total reward received and whether observer asked to stop
+fn rollout<S, A, T, R, O>(agent: &mut T, env: &mut Environment<State=S, Action=A>, period: TimePeriod, 
+						  rng: &mut R, observer: &mut O) -> (f64, bool)
+	where S: Space,
+		  A: Space,
+		  T: Agent<S, A>,
+		  R: Rng,
+		  O: Observer<S, A, T> {
+	let mut obs = env.reset();
+	let (mut ep_return, mut total) = (0.0, 0.0);
+	let mut stop = false;
+	let mut time_remaining = period;
+	while !time_remaining.is_none() {
+		let action = agent.get_action(&obs.state, rng);
+		let new_obs = env.step(&action);
+
+		let transition = (obs.state, action, new_obs.reward, new_obs.state.clone());
+		stop = observer.on_step(agent, &transition) == Signal::Stop || stop;
+		ep_return += new_obs.reward;
+		total += new_obs.reward;
+
+		time_remaining = time_remaining.dec(new_obs.done);
+		obs = if new_obs.done {
+			stop = observer.on_episode_end(agent, ep_return) == Signal::Stop || stop;
+			ep_return = 0.0;
+			env.reset()
+		} else {new_obs};
+	}
+	(total, stop)
+}
+
+// The steps acted out by collect_trajectory, in order
+struct Trajectory<S: Space, A: Space> {
+	states: Vec<S::Element>,
+	actions: Vec<A::Element>,
+	rewards: Vec<f64>,
+	next_states: Vec<S::Element>,
+	// Whether each step ended its episode
+	dones: Vec<bool>,
+}
+
+impl<S: Space, A: Space> Trajectory<S, A> {
+	// How many steps into its episode each step was
+	fn times(&self) -> Vec<usize> {
+		let mut time = 0;
+		self.dones.iter().map(|&done| {
+			let t = time;
+			time = if done {0} else {time + 1};
+			t
+		}).collect()
+	}
+	// The discounted return received from each step until the end of its episode
+	fn discount(&self, gamma: f64) -> Vec<f64> {
+		let mut returns = vec![0.0; self.rewards.len()];
+		let mut running_sum = 0.0;
+		for t in (0..self.rewards.len()).rev() {
+			if self.dones[t] {
+				running_sum = 0.0;
+			}
+			running_sum = running_sum * gamma + self.rewards[t];
+			returns[t] = running_sum;
+		}
+		returns
+	}
+}
+
+// Adds every step of a rollout to traj, while still reporting them to observer
+struct TrajectoryRecorder<'a, S: Space, A: Space, O: 'a> {
+	traj: Trajectory<S, A>,
+	observer: &'a mut O,
+}
+
+impl<'a, S: Space, A: Space, T, O: Observer<S, A, T>> Observer<S, A, T> for TrajectoryRecorder<'a, S, A, O> {
+	fn on_step(&mut self, agent: &mut T, transition: &Transition<S, A>) -> Signal {
+		let (state, action, reward, next) = transition.clone();
+		self.traj.states.push(state);
+		self.traj.actions.push(action);
+		self.traj.rewards.push(reward);
+		self.traj.next_states.push(next);
+		self.traj.dones.push(false);
+		self.observer.on_step(agent, transition)
+	}
+	fn on_episode_end(&mut self, agent: &mut T, ep_return: f64) -> Signal {
+		if let Some(done) = self.traj.dones.last_mut() {
+			*done = true;
+		}
+		self.observer.on_episode_end(agent, ep_return)
+	}
+}
+
+// Acts out period like rollout, also returning every step taken
+fn collect_trajectory<S, A, T, R, O>(agent: &mut T, env: &mut Environment<State=S, Action=A>, period: TimePeriod, 
+									 rng: &mut R, observer: &mut O) -> (Trajectory<S, A>, f64, bool)
+	where S: Space,
+		  A: Space,
+		  T: Agent<S, A>,
+		  R: Rng,
+		  O: Observer<S, A, T> {
+	let mut recorder = TrajectoryRecorder {
+		traj: Trajectory {
+			states: Vec::new(),
+			actions: Vec::new(),
+			rewards: Vec::new(),
+			next_states: Vec::new(),
+			dones: Vec::new()
+		},
+		observer: observer
+	};
+	let (total, stop) = rollout(agent, env, period, rng, &mut recorder);
+	(recorder.traj, total, stop)
+}
+
+// Acts out period once with the agent using each set of params, returning the total rewards received
+// and whether observer asked to stop
+fn evaluate<S, A, T, F, R, O>(samples: &[Vec<F>], agent: &mut T, env: &mut Environment<State=S, Action=A>, 
+							  period: TimePeriod, rng: &mut R, observer: &mut O) -> (Vec<f64>, bool)
+	where S: Space,
+		  A: Space,
+		  T: Agent<S, A> + ParameterizedFunc<F>,
+		  F: Float,
+		  R: Rng,
+		  O: Observer<S, A, T> {
+	let mut stop = false;
+	let scores = samples.iter().map(|params| {
+		agent.set_params(params.clone());
+		let (score, s_stop) = rollout(agent, env, period.clone(), rng, observer);
+		stop = stop || s_stop;
+		score
+	}).collect();
+	(scores, stop)
+}
+
+// What a Recorder saw happen during a rollout
+enum Event<S: Space, A: Space> {
+	Step(Transition<S, A>),
+	EpisodeEnd(f64),
+}
+
+// Remembers the events of a rollout so they can be reported to an observer on another thread
+struct Recorder<S: Space, A: Space> {
+	events: Vec<Event<S, A>>,
+}
+
+impl<S: Space, A: Space, T> Observer<S, A, T> for Recorder<S, A> {
+	fn on_step(&mut self, _agent: &mut T, transition: &Transition<S, A>) -> Signal {
+		self.events.push(Event::Step(transition.clone()));
+		Signal::Continue
+	}
+	fn on_episode_end(&mut self, _agent: &mut T, ep_return: f64) -> Signal {
+		self.events.push(Event::EpisodeEnd(ep_return));
+		Signal::Continue
+	}
+}
+
+// Threads that each act with their own clone of the agent. They are created once and then evaluate the
+// samples they are sent until the pool is dropped, each in a new environment so no sample can affect
+// another
+struct EvalPool<S: Space, A: Space, F> {
+	workers: Vec<Worker<S, A, F>>,
+}
+
+// A thread of an EvalPool, along with the channels used to send it samples and receive their results
+struct Worker<S: Space, A: Space, F> {
+	jobs: mpsc::Sender<(Vec<F>, usize)>,
+	results: mpsc::Receiver<(f64, Vec<Event<S, A>>)>,
+	handle: thread::JoinHandle<()>,
+}
+
+impl<S, A, F> EvalPool<S, A, F>
+	where S: Space + 'static,
+		  A: Space + 'static,
+		  S::Element: Send,
+		  A::Element: Send,
+		  F: Float + Send + 'static {
+	// Starts num_threads threads that act out period with clones of agent in environments created by make_env
+	fn new<T, E, M>(agent: &T, make_env: M, period: TimePeriod, num_threads: usize) -> EvalPool<S, A, F>
+		where T: Agent<S, A> + ParameterizedFunc<F> + Clone + Send + 'static,
+			  E: Environment<State=S, Action=A>,
+			  M: Fn() -> E + Send + Sync + 'static {
+		assert!(num_threads > 0, "num_threads must be greater than 0");
+
+		let make_env = Arc::new(make_env);
+		let workers = (0..num_threads).map(|_| {
+			let (jobs, job_receiver) = mpsc::channel::<(Vec<F>, usize)>();
+			let (result_sender, results) = mpsc::channel();
+			let mut agent = agent.clone();
+			let make_env = make_env.clone();
+			let period = period.clone();
+			let handle = thread::spawn(move || {
+				for (params, seed) in job_receiver {
+					let mut env = make_env();
+					agent.set_params(params);
+					let mut rng = StdRng::from_seed(&[seed]);
+					let mut recorder = Recorder {events: Vec::new()};
+					let (score, _) = rollout(&mut agent, &mut env, period.clone(), &mut rng, &mut recorder);
+					if result_sender.send((score, recorder.events)).is_err() {
+						break;
+					}
+				}
+			});
+			Worker {jobs: jobs, results: results, handle: handle}
+		}).collect();
+		EvalPool {workers: workers}
+	}
+	// Does the same as evaluate, but splits the samples between the threads. Every sample gets its own rng
+	// seeded from rng, so the scores do not depend on how they are split
+	fn evaluate<T, R, O>(&self, samples: &[Vec<F>], agent: &mut T, rng: &mut R, observer: &mut O) -> (Vec<f64>, bool)
+		where T: Agent<S, A> + ParameterizedFunc<F>,
+			  R: Rng,
+			  O: Observer<S, A, T> {
+		let num_threads = self.workers.len();
+		for (i, params) in samples.iter().enumerate() {
+			let seed = rng.gen::<usize>();
+			self.workers[i % num_threads].jobs.send((params.clone(), seed)).expect("an evaluation thread panicked");
+		}
+
+		// Each thread returns its results in the order it was sent the samples
+		let mut stop = false;
+		let scores = samples.iter().enumerate().map(|(i, params)| {
+			let (score, events) = self.workers[i % num_threads].results.recv().expect("an evaluation thread panicked");
+			agent.set_params(params.clone());
+			for event in events {
+				let signal = match event {
+					Event::Step(transition) => observer.on_step(agent, &transition),
+					Event::EpisodeEnd(ep_return) => observer.on_episode_end(agent, ep_return),
+				};
+				stop = signal == Signal::Stop || stop;
+			}
+			score
+		}).collect();
+		(scores, stop)
+	}
+}
+
+impl<S: Space, A: Space, F> Drop for EvalPool<S, A, F> {
+	fn drop(&mut self) {
+		for worker in self.workers.drain(..) {
+			// Closing the job channel ends the thread
+			drop(worker.jobs);
+			let _ = worker.handle.join();
+		}
+	}
 }
\ No newline at end of file
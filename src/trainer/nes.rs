@@ -1,4 +1,4 @@
-use rand::thread_rng;
+use rand::Rng;
 use rand::distributions::IndependentSample;
 use rand::distributions::normal::Normal;
 
@@ -7,11 +7,12 @@ use num::cast::NumCast;
 
 use environment::{Space, Environment};
 
-use trainer::EpisodicTrainer;
+use trainer::{EpisodicTrainer, ParallelTrainer, Observer, Iterative, train_episodic, evaluate, EvalPool};
 
 use agent::Agent;
 
-use util::ParameterizedFunc;
+use util::{ParameterizedFunc, Stateful};
+use util::checkpoint::CheckpointError;
 use util::TimePeriod;
 
 use stat::normalize;
@@ -32,13 +33,95 @@ pub struct NaturalEvo<F: Float> {
 	eval_period: TimePeriod,
 	/// Number of training iterations to perform when calling `train`
 	iters: usize,
+	/// Number of iterations of the current call to `train` already performed
+	curr_iter: usize,
+}
+
+/// The internal state of a NaturalEvo trainer
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct NaturalEvoState<F> {
+	/// The mean of the gaussian
+	pub mean_params: Vec<F>,
+	/// Number of iterations of the current call to `train` already performed
+	pub curr_iter: usize,
 }
 
 impl<F: Float, S: Space, A: Space, T> EpisodicTrainer<S, A, T> for NaturalEvo<F>
 	where T: Agent<S, A> + ParameterizedFunc<F> {
-	fn train_step(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>) {
-		let mut rng = thread_rng();
+	fn train_step<R: Rng>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R) {
+		let period = self.eval_period.clone();
+		self.iterate(agent, rng, &mut (), &mut |samples, agent, rng, observer| {
+			evaluate(samples, agent, env, period.clone(), rng, observer)
+		});
+	}
+	fn train_with<R, O>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R, observer: &mut O)
+		where R: Rng, O: Observer<S, A, T> {
+		train_episodic(self, agent, rng, observer, |trainer, agent, rng, observer| {
+			let period = trainer.eval_period.clone();
+			trainer.iterate(agent, rng, observer, &mut |samples, agent, rng, observer| {
+				evaluate(samples, agent, env, period.clone(), rng, observer)
+			})
+		});
+	}
+}
+
+impl<F: Float> Iterative for NaturalEvo<F> {
+	fn iters(&self) -> usize {
+		self.iters
+	}
+	fn curr_iter(&mut self) -> &mut usize {
+		&mut self.curr_iter
+	}
+}
+
+impl<F, S, A, T> ParallelTrainer<S, A, T> for NaturalEvo<F>
+	where F: Float + Send + 'static,
+		  S: Space + 'static,
+		  A: Space + 'static,
+		  S::Element: Send,
+		  A::Element: Send,
+		  T: Agent<S, A> + ParameterizedFunc<F> + Clone + Send + 'static {
+	fn train_parallel_with<E, M, R, O>(&mut self, agent: &mut T, make_env: M, num_threads: usize, rng: &mut R, observer: &mut O)
+		where E: Environment<State=S, Action=A>,
+			  M: Fn() -> E + Send + Sync + 'static,
+			  R: Rng,
+			  O: Observer<S, A, T> {
+		let pool = EvalPool::new(agent, make_env, self.eval_period.clone(), num_threads);
+		train_episodic(self, agent, rng, observer, |trainer, agent, rng, observer| {
+			trainer.iterate(agent, rng, observer, &mut |samples, agent, rng, observer| {
+				pool.evaluate(samples, agent, rng, observer)
+			})
+		});
+	}
+}
 
+impl<F: Float> Stateful for NaturalEvo<F> {
+	type State = NaturalEvoState<F>;
+
+	fn get_state(&self) -> NaturalEvoState<F> {
+		NaturalEvoState {
+			mean_params: self.mean_params.clone(),
+			curr_iter: self.curr_iter
+		}
+	}
+	fn set_state(&mut self, state: NaturalEvoState<F>) -> Result<(), CheckpointError> {
+		self.mean_params = state.mean_params;
+		self.curr_iter = state.curr_iter;
+		Ok(())
+	}
+}
+
+impl<F: Float> NaturalEvo<F> {
+	// Performs one training iteration, scoring the samples with eval. Returns the average score
+	// of the samples and whether observer asked to stop
+	fn iterate<S, A, T, R, O, V>(&mut self, agent: &mut T, rng: &mut R, observer: &mut O, eval: &mut V) -> (f64, bool)
+		where S: Space,
+			  A: Space,
+			  T: Agent<S, A> + ParameterizedFunc<F>,
+			  R: Rng,
+			  O: Observer<S, A, T>,
+			  V: FnMut(&[Vec<F>], &mut T, &mut R, &mut O) -> (Vec<f64>, bool) {
 		if self.mean_params.is_empty() {
 			self.mean_params = agent.get_params();
 		}
@@ -47,13 +130,13 @@ impl<F: Float, S: Space, A: Space, T> EpisodicTrainer<S, A, T> for NaturalEvo<F>
 			(0..self.mean_params.len()).map(|i| {
 				let normal = Normal::new(self.mean_params[i].to_f64().unwrap(), 
 										 self.deviation.to_f64().unwrap());
-				NumCast::from(normal.ind_sample(&mut rng)).unwrap()
+				NumCast::from(normal.ind_sample(rng)).unwrap()
 			}).collect()
 		}).collect();
 
-		let mut scores: Vec<_> = samples.iter()
-										.map(|s| self.eval(s.clone(), agent, env))
-										.collect();
+		let (scores, stop) = eval(&samples, agent, rng, observer);
+		let mut scores: Vec<F> = scores.into_iter().map(|score| NumCast::from(score).unwrap()).collect();
+		let avg_score = scores.iter().fold(0.0, |acc, s| acc + s.to_f64().unwrap())/self.num_samples as f64;
 		normalize(&mut scores);
 
 		for d in 0..self.mean_params.len() {
@@ -67,11 +150,7 @@ impl<F: Float, S: Space, A: Space, T> EpisodicTrainer<S, A, T> for NaturalEvo<F>
 		}
 
 		agent.set_params(self.mean_params.clone());
-	}
-	fn train(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>) {
-		for _ in 0..self.iters {
-			self.train_step(agent, env);
-		}
+		(avg_score, stop)
 	}
 }
 
@@ -85,7 +164,8 @@ impl Default for NaturalEvo<f64> {
 			deviation: 0.1,
 			num_samples: 100,
 			eval_period: TimePeriod::EPISODES(1),
-			iters: 10
+			iters: 10,
+			curr_iter: 0
 		}
 	}
 }
@@ -102,7 +182,8 @@ impl<F: Float> NaturalEvo<F> {
 			deviation: deviation,
 			num_samples: num_samples,
 			eval_period: eval_period,
-			iters: iters
+			iters: iters,
+			curr_iter: 0
 		}
 	}
 	/// Updates alpha field of self
@@ -130,25 +211,4 @@ impl<F: Float> NaturalEvo<F> {
 		self.iters = iters;
 		self
 	}
-	
-	fn eval<T, S, A>(&self, params: Vec<F>, agent: &mut T, env: &mut Environment<State=S, Action=A>) -> F 
-		where 	S: Space,
-				A: Space,
-				T: Agent<S, A> + ParameterizedFunc<F> {
-		agent.set_params(params);
-
-		let mut obs = env.reset();
-		let mut reward = 0.0;
-		let mut time_remaining = self.eval_period.clone();
-		while !time_remaining.is_none() {
-			let action = agent.get_action(&obs.state);
-			let new_obs = env.step(&action);
-
-			reward += new_obs.reward;
-			time_remaining = time_remaining.dec(new_obs.done);
-			obs = if new_obs.done {env.reset()} else {new_obs};
-		}
-
-		NumCast::from(reward).unwrap()
-	}
 }
\ No newline at end of file
@@ -1,24 +1,29 @@
+use rand::Rng;
+
 use num::Float;
 use num::cast::NumCast;
 
 use environment::{Space, Environment};
 
-use trainer::EpisodicTrainer;
+use trainer::{EpisodicTrainer, Observer, Iterative, train_episodic, collect_trajectory};
 
 use agent::Agent;
 
-use util::{LogDiffFunc, GradientDescAlgo};
+use util::{LogDiffFunc, GradientDescAlgo, Baseline, Stateful};
+use util::checkpoint::CheckpointError;
+use util::baseline::Normalize;
 use util::TimePeriod;
 
-use stat::normalize;
-
 /// A variation of the [Vanilla Policy Gradient](https://youtu.be/PtAIh9KSnjo?t=2590) algorithm
 ///
-/// Instead of using a baseline, rewards are normalized to mean 0 and variance 1
+/// Discounted returns are passed through a baseline before being used to weight the gradient.
+/// By default they are normalized to mean 0 and variance 1
 #[derive(Debug)]
-pub struct PolicyGradient<F: Float, G: GradientDescAlgo<F>> {
+pub struct PolicyGradient<F: Float, G: GradientDescAlgo<F>, B = Normalize> {
 	/// Gradient descent algorithm
 	grad_desc: G,
+	/// Baseline applied to the discounted returns
+	baseline: B,
 	/// Discount factor
 	gamma: f64,
 	/// Learning rate
@@ -27,20 +32,84 @@ pub struct PolicyGradient<F: Float, G: GradientDescAlgo<F>> {
 	iters: usize,
 	/// Time period to evaluate each parameter sample on
 	eval_period: TimePeriod,
+	/// Number of iterations of the current call to `train` already performed
+	curr_iter: usize,
 }
 
-// Have I deviated too much from the original algorithm here?
-// Should there be a Baseline trait instead of always normalizing rewards?
+/// The internal state of a PolicyGradient trainer
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct PolicyGradientState<G, B> {
+	/// State of the gradient descent algorithm
+	pub grad_desc: G,
+	/// State of the baseline
+	pub baseline: B,
+	/// Number of iterations of the current call to `train` already performed
+	pub curr_iter: usize,
+}
 
 // Sometimes I wonder if I'm using traits how they were meant to be used, because this just looks ugly
 // Honestly, even disregarding the trait boilerplate, this whole implementation is pretty messy
-impl<F: Float, S: Space, A: Space, G, T> EpisodicTrainer<S, A, T> for PolicyGradient<F, G>
+impl<F: Float, S: Space, A: Space, G, B, T> EpisodicTrainer<S, A, T> for PolicyGradient<F, G, B>
 	where T: Agent<S, A> + LogDiffFunc<S, A, F>,
-		  G: GradientDescAlgo<F> {
-	fn train_step(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>) {
-		let (xs, ys, mut rs) = self.collect_trajectory(agent, env);
-		if rs.len() > 0 {
-			normalize(&mut rs);
+		  G: GradientDescAlgo<F>,
+		  B: Baseline<S> {
+	fn train_step<R: Rng>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R) {
+		self.iterate(agent, env, rng, &mut ());
+	}
+	fn train_with<R, O>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R, observer: &mut O)
+		where R: Rng, O: Observer<S, A, T> {
+		train_episodic(self, agent, rng, observer, |trainer, agent, rng, observer| {
+			trainer.iterate(agent, env, rng, observer)
+		});
+	}
+}
+
+impl<F: Float, G: GradientDescAlgo<F>, B> Iterative for PolicyGradient<F, G, B> {
+	fn iters(&self) -> usize {
+		self.iters
+	}
+	fn curr_iter(&mut self) -> &mut usize {
+		&mut self.curr_iter
+	}
+}
+
+impl<F: Float, G, B> Stateful for PolicyGradient<F, G, B>
+	where G: GradientDescAlgo<F> + Stateful,
+		  B: Stateful {
+	type State = PolicyGradientState<G::State, B::State>;
+
+	fn get_state(&self) -> Self::State {
+		PolicyGradientState {
+			grad_desc: self.grad_desc.get_state(),
+			baseline: self.baseline.get_state(),
+			curr_iter: self.curr_iter
+		}
+	}
+	fn set_state(&mut self, state: Self::State) -> Result<(), CheckpointError> {
+		self.grad_desc.set_state(state.grad_desc)?;
+		self.baseline.set_state(state.baseline)?;
+		self.curr_iter = state.curr_iter;
+		Ok(())
+	}
+}
+
+impl<F: Float, G: GradientDescAlgo<F>, B> PolicyGradient<F, G, B> {
+	// Performs one training iteration, returning the total reward received while exploring
+	// and whether observer asked to stop
+	fn iterate<S, A, T, R, O>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, 
+							  rng: &mut R, observer: &mut O) -> (f64, bool)
+		where S: Space,
+			  A: Space,
+			  T: Agent<S, A> + LogDiffFunc<S, A, F>,
+			  B: Baseline<S>,
+			  R: Rng,
+			  O: Observer<S, A, T> {
+		let (traj, score, stop) = collect_trajectory(agent, env, self.eval_period.clone(), rng, observer);
+		if traj.rewards.len() > 0 {
+			let (ts, rs) = (traj.times(), traj.discount(self.gamma));
+			let (xs, ys) = (traj.states, traj.actions);
+			let rs = self.baseline.apply(&xs, &ts, rs);
 
 			let mut grad = vec![F::zero(); agent.num_params()];
 			for i in 0..rs.len() {
@@ -60,11 +129,7 @@ impl<F: Float, S: Space, A: Space, G, T> EpisodicTrainer<S, A, T> for PolicyGrad
 
 			agent.set_params(params);
 		}
-	}
-	fn train(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>) {
-		for _ in 0..self.iters {
-			self.train_step(agent, env);	
-		}
+		(score, stop)
 	}
 }
 
@@ -74,10 +139,12 @@ impl<G: GradientDescAlgo<f64>> PolicyGradient<f64, G> {
 	pub fn default(grad_desc: G) -> PolicyGradient<f64, G> {
 		PolicyGradient {
 			grad_desc: grad_desc,
+			baseline: Normalize,
 			gamma: 0.99,
 			lr: 0.001,
 			iters: 100,
-			eval_period: TimePeriod::EPISODES(5)
+			eval_period: TimePeriod::EPISODES(5),
+			curr_iter: 0
 		}
 	}
 }
@@ -90,78 +157,55 @@ impl<F: Float, G: GradientDescAlgo<F>> PolicyGradient<F, G> {
 
 		PolicyGradient {
 			grad_desc: grad_desc,
+			baseline: Normalize,
 			gamma: gamma,
 			lr: lr,
 			iters: iters,
 			eval_period: eval_period,
+			curr_iter: 0
+		}
+	}
+}
+
+impl<F: Float, G: GradientDescAlgo<F>, B> PolicyGradient<F, G, B> {
+	/// Updates baseline field of self
+	pub fn baseline<B2>(self, baseline: B2) -> PolicyGradient<F, G, B2> {
+		PolicyGradient {
+			grad_desc: self.grad_desc,
+			baseline: baseline,
+			gamma: self.gamma,
+			lr: self.lr,
+			iters: self.iters,
+			eval_period: self.eval_period,
+			curr_iter: self.curr_iter
 		}
 	}
 	/// Updates gamma field of self
-	pub fn gamma(mut self, gamma: f64) -> PolicyGradient<F, G> {
+	pub fn gamma(mut self, gamma: f64) -> PolicyGradient<F, G, B> {
 		assert!(0.0 <= gamma && gamma <= 1.0, "gamma must be between 0 and 1");
 
 		self.gamma = gamma;
 		self
 	}
 	/// Updates lr field of self
-	pub fn lr(mut self, lr: F) -> PolicyGradient<F, G> {
+	pub fn lr(mut self, lr: F) -> PolicyGradient<F, G, B> {
 		assert!(F::zero() <= lr && lr <= F::one(), "lr must be between 0 and 1");
 
 		self.lr = lr;
 		self
 	}
 	/// Updates iters field of self
-	pub fn iters(mut self, iters: usize) -> PolicyGradient<F, G> {
+	pub fn iters(mut self, iters: usize) -> PolicyGradient<F, G, B> {
 		self.iters = iters;
 		self
 	}
 	/// Updates eval_period field of self
-	pub fn eval_period(mut self, eval_period: TimePeriod) -> PolicyGradient<F, G> {
+	pub fn eval_period(mut self, eval_period: TimePeriod) -> PolicyGradient<F, G, B> {
 		self.eval_period = eval_period;
 		self
 	}
-
-	fn discount(&self, mut rewards: Vec<f64>) -> Vec<f64> {
-		let mut running_sum = 0.0;
-		for t in (0..rewards.len()).rev() {
-			running_sum = running_sum * self.gamma + rewards[t];
-			rewards[t] = running_sum;
-		}
-		return rewards;
-	}
-	fn collect_trajectory<S, A, T>(&self, agent: &mut T, env: &mut Environment<State=S, Action=A>) -> (Vec<S::Element>, Vec<A::Element>, Vec<f64>)
-		where S: Space,
-			  A: Space,
-			  T: Agent<S, A> + LogDiffFunc<S, A, F> {
-		let (mut states, mut actions, mut rewards) = if let TimePeriod::TIMESTEPS(len) = self.eval_period.clone() {
-			(Vec::with_capacity(len), Vec::with_capacity(len), Vec::with_capacity(len))
-		} else {
-			(Vec::new(), Vec::new(), Vec::new())
-		};
-
-		let mut ep_rewards = Vec::new();
-
-		let mut obs = env.reset();
-		let mut time_remaining = self.eval_period.clone();
-		while !time_remaining.is_none() {
-			let action = agent.get_action(&obs.state);
-			let new_obs = env.step(&action);
-
-			states.push(obs.state);
-			actions.push(action);
-			ep_rewards.push(new_obs.reward);
-
-			time_remaining = time_remaining.dec(new_obs.done);
-			obs = if new_obs.done {
-				ep_rewards = self.discount(ep_rewards);
-				rewards.extend_from_slice(&ep_rewards);
-
-				ep_rewards.clear();
-				env.reset()
-			} else {new_obs};
-		}
-		rewards.extend_from_slice(&self.discount(ep_rewards));
-
-        (states, actions, rewards)
+	/// Returns the baseline used by self
+	pub fn get_baseline(&self) -> &B {
+		&self.baseline
 	}
 }
@@ -0,0 +1,283 @@
+use rand::Rng;
+
+use num::Float;
+use num::cast::NumCast;
+
+use environment::{Space, Environment};
+
+use trainer::{EpisodicTrainer, Observer, Iterative, train_episodic, collect_trajectory};
+
+use agent::Agent;
+
+use util::{LogDiffFunc, GradientDescAlgo, Baseline, Stateful};
+use util::checkpoint::CheckpointError;
+use util::baseline::Normalize;
+use util::TimePeriod;
+
+/// A [Proximal Policy Optimization](https://arxiv.org/abs/1707.06347) trainer
+///
+/// Like PolicyGradient, discounted returns are passed through a baseline to get advantages.
+/// Each batch of experience is then reused for several epochs of minibatch updates of the
+/// clipped surrogate objective, which keeps the new policy close to the one that collected it.
+#[derive(Debug)]
+pub struct PPO<F: Float, G: GradientDescAlgo<F>, B = Normalize> {
+	/// Gradient descent algorithm
+	grad_desc: G,
+	/// Baseline applied to the discounted returns
+	baseline: B,
+	/// Discount factor
+	gamma: f64,
+	/// Learning rate
+	lr: F,
+	/// How far the probability ratio of the new and old policies can move from 1
+	clip: f64,
+	/// Number of passes made over each batch of experience
+	epochs: usize,
+	/// Number of steps in each minibatch
+	batch_size: usize,
+	/// Weight of the entropy bonus encouraging exploration
+	entropy_coeff: f64,
+	/// Stop an iteration's epochs early once the estimated KL-divergence from the old policy exceeds this
+	max_kl: Option<f64>,
+	/// Number of training iterations to perform when calling `train`
+	iters: usize,
+	/// Time period to act out in each training iteration
+	eval_period: TimePeriod,
+	/// Number of iterations of the current call to `train` already performed
+	curr_iter: usize,
+}
+
+/// The internal state of a PPO trainer
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct PPOState<G, B> {
+	/// State of the gradient descent algorithm
+	pub grad_desc: G,
+	/// State of the baseline
+	pub baseline: B,
+	/// Number of iterations of the current call to `train` already performed
+	pub curr_iter: usize,
+}
+
+impl<F: Float, S: Space, A: Space, G, B, T> EpisodicTrainer<S, A, T> for PPO<F, G, B>
+	where T: Agent<S, A> + LogDiffFunc<S, A, F>,
+		  G: GradientDescAlgo<F>,
+		  B: Baseline<S> {
+	fn train_step<R: Rng>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R) {
+		self.iterate(agent, env, rng, &mut ());
+	}
+	fn train_with<R, O>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R, observer: &mut O)
+		where R: Rng, O: Observer<S, A, T> {
+		train_episodic(self, agent, rng, observer, |trainer, agent, rng, observer| {
+			trainer.iterate(agent, env, rng, observer)
+		});
+	}
+}
+
+impl<F: Float, G: GradientDescAlgo<F>, B> Iterative for PPO<F, G, B> {
+	fn iters(&self) -> usize {
+		self.iters
+	}
+	fn curr_iter(&mut self) -> &mut usize {
+		&mut self.curr_iter
+	}
+}
+
+impl<F: Float, G, B> Stateful for PPO<F, G, B>
+	where G: GradientDescAlgo<F> + Stateful,
+		  B: Stateful {
+	type State = PPOState<G::State, B::State>;
+
+	fn get_state(&self) -> Self::State {
+		PPOState {
+			grad_desc: self.grad_desc.get_state(),
+			baseline: self.baseline.get_state(),
+			curr_iter: self.curr_iter
+		}
+	}
+	fn set_state(&mut self, state: Self::State) -> Result<(), CheckpointError> {
+		self.grad_desc.set_state(state.grad_desc)?;
+		self.baseline.set_state(state.baseline)?;
+		self.curr_iter = state.curr_iter;
+		Ok(())
+	}
+}
+
+impl<G: GradientDescAlgo<f64>> PPO<f64, G> {
+	/// Creates a PPO trainer with default parameter values and the given gradient descent algorithm
+	pub fn default(grad_desc: G) -> PPO<f64, G> {
+		PPO::new(grad_desc, 0.99, 0.01, 0.2, 4, 64, 100, TimePeriod::EPISODES(5))
+	}
+}
+
+impl<F: Float, G: GradientDescAlgo<F>> PPO<F, G> {
+	/// Constructs a new PPO trainer with given information, no entropy bonus and no KL limit
+	pub fn new(grad_desc: G, gamma: f64, lr: F, clip: f64, epochs: usize, batch_size: usize,
+			   iters: usize, eval_period: TimePeriod) -> PPO<F, G> {
+		assert!(0.0 < gamma && gamma <= 1.0, "gamma must be between 0 and 1");
+		assert!(F::zero() < lr && lr <= F::one(), "learning rate must be between 0 and 1");
+		assert!(clip >= 0.0, "clip must be nonnegative");
+		assert!(batch_size > 0, "batch_size must be positive");
+
+		PPO {
+			grad_desc: grad_desc,
+			baseline: Normalize,
+			gamma: gamma,
+			lr: lr,
+			clip: clip,
+			epochs: epochs,
+			batch_size: batch_size,
+			entropy_coeff: 0.0,
+			max_kl: None,
+			iters: iters,
+			eval_period: eval_period,
+			curr_iter: 0
+		}
+	}
+}
+
+impl<F: Float, G: GradientDescAlgo<F>, B> PPO<F, G, B> {
+	/// Updates baseline field of self
+	pub fn baseline<B2>(self, baseline: B2) -> PPO<F, G, B2> {
+		PPO {
+			grad_desc: self.grad_desc,
+			baseline: baseline,
+			gamma: self.gamma,
+			lr: self.lr,
+			clip: self.clip,
+			epochs: self.epochs,
+			batch_size: self.batch_size,
+			entropy_coeff: self.entropy_coeff,
+			max_kl: self.max_kl,
+			iters: self.iters,
+			eval_period: self.eval_period,
+			curr_iter: self.curr_iter
+		}
+	}
+	/// Updates gamma field of self
+	pub fn gamma(mut self, gamma: f64) -> PPO<F, G, B> {
+		assert!(0.0 < gamma && gamma <= 1.0, "gamma must be between 0 and 1");
+
+		self.gamma = gamma;
+		self
+	}
+	/// Updates lr field of self
+	pub fn lr(mut self, lr: F) -> PPO<F, G, B> {
+		assert!(F::zero() < lr && lr <= F::one(), "learning rate must be between 0 and 1");
+
+		self.lr = lr;
+		self
+	}
+	/// Updates clip field of self
+	pub fn clip(mut self, clip: f64) -> PPO<F, G, B> {
+		assert!(clip >= 0.0, "clip must be nonnegative");
+
+		self.clip = clip;
+		self
+	}
+	/// Updates epochs field of self
+	pub fn epochs(mut self, epochs: usize) -> PPO<F, G, B> {
+		self.epochs = epochs;
+		self
+	}
+	/// Updates batch_size field of self
+	pub fn batch_size(mut self, batch_size: usize) -> PPO<F, G, B> {
+		assert!(batch_size > 0, "batch_size must be positive");
+
+		self.batch_size = batch_size;
+		self
+	}
+	/// Updates entropy_coeff field of self
+	pub fn entropy_coeff(mut self, entropy_coeff: f64) -> PPO<F, G, B> {
+		self.entropy_coeff = entropy_coeff;
+		self
+	}
+	/// Stops each iteration's epochs early once the estimated KL-divergence from the old policy exceeds max_kl
+	pub fn max_kl(mut self, max_kl: f64) -> PPO<F, G, B> {
+		assert!(max_kl > 0.0, "max_kl must be positive");
+
+		self.max_kl = Some(max_kl);
+		self
+	}
+	/// Updates iters field of self
+	pub fn iters(mut self, iters: usize) -> PPO<F, G, B> {
+		self.iters = iters;
+		self
+	}
+	/// Updates eval_period field of self
+	pub fn eval_period(mut self, eval_period: TimePeriod) -> PPO<F, G, B> {
+		self.eval_period = eval_period;
+		self
+	}
+	/// Returns the baseline used by self
+	pub fn get_baseline(&self) -> &B {
+		&self.baseline
+	}
+
+	// Performs one training iteration, returning the total reward received while exploring
+	// and whether observer asked to stop
+	fn iterate<S, A, T, R, O>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>,
+							  rng: &mut R, observer: &mut O) -> (f64, bool)
+		where S: Space,
+			  A: Space,
+			  T: Agent<S, A> + LogDiffFunc<S, A, F>,
+			  B: Baseline<S>,
+			  R: Rng,
+			  O: Observer<S, A, T> {
+		let (traj, score, stop) = collect_trajectory(agent, env, self.eval_period.clone(), rng, observer);
+		if traj.rewards.is_empty() {
+			return (score, stop);
+		}
+
+		let (ts, rs) = (traj.times(), traj.discount(self.gamma));
+		let (xs, ys) = (traj.states, traj.actions);
+
+		let advantages = self.baseline.apply(&xs, &ts, rs);
+		let old_log_probs: Vec<_> = (0..xs.len()).map(|i| {
+			agent.log_prob(&xs[i], &ys[i]).to_f64().unwrap()
+		}).collect();
+
+		let mut indices: Vec<_> = (0..xs.len()).collect();
+		for _ in 0..self.epochs {
+			rng.shuffle(&mut indices);
+			for batch in indices.chunks(self.batch_size) {
+				let mut grad = vec![F::zero(); agent.num_params()];
+				for &i in batch {
+					let log_prob = agent.log_prob(&xs[i], &ys[i]).to_f64().unwrap();
+					let ratio = (log_prob - old_log_probs[i]).exp();
+
+					// The clipped objective is flat once the ratio moves too far in the advantageous direction
+					let clipped = (advantages[i] > 0.0 && ratio > 1.0 + self.clip) ||
+								  (advantages[i] < 0.0 && ratio < 1.0 - self.clip);
+					let mut weight = if clipped {0.0} else {ratio * advantages[i]};
+					// Sampled estimate of the entropy gradient, -E[log(p) * grad(log(p))]
+					weight -= self.entropy_coeff * ratio * log_prob;
+
+					let weight: F = NumCast::from(weight/batch.len() as f64).unwrap();
+					let g = agent.log_grad(&xs[i], &ys[i]);
+					for j in 0..g.len() {
+						grad[j] = grad[j] + g[j] * weight;
+					}
+				}
+
+				let mut params = agent.get_params();
+				let grad_step = self.grad_desc.calculate(grad, self.lr);
+				for j in 0..params.len() {
+					params[j] = params[j] + grad_step[j];
+				}
+				agent.set_params(params);
+			}
+
+			if let Some(max_kl) = self.max_kl {
+				let kl = (0..xs.len()).fold(0.0, |sum, i| {
+					sum + old_log_probs[i] - agent.log_prob(&xs[i], &ys[i]).to_f64().unwrap()
+				})/xs.len() as f64;
+
+				if kl > max_kl {
+					break;
+				}
+			}
+		}
+		(score, stop)
+	}
+}
\ No newline at end of file
@@ -0,0 +1,137 @@
+use std::f64;
+
+use rand::Rng;
+
+use environment::Environment;
+use environment::Transition;
+use environment::{Space, FiniteSpace};
+use trainer::{OnlineTrainer, Observer, train_online};
+use agent::Agent;
+use util::{TracedQFunction, TimePeriod, Stateful};
+use util::checkpoint::CheckpointError;
+
+/// Represents an OnlineTrainer for Q-functions
+/// Uses the [Double Q-learning algorithm](https://papers.nips.cc/paper/3964-double-q-learning)
+///
+/// Two estimates, A and B, are learned. On each step one of them is picked at random and updated
+/// towards the value the other gives to its best next action, which avoids the overestimation
+/// caused by maximizing over noisy estimates. The agent's QFunction holds the sum A + B (so the
+/// agent acts on both), while the trainer stores B and recovers A as the difference.
+///
+/// That difference only stays exact if moving the sum moves A by the same amount, which holds when
+/// updates do not depend on a function's current parameters. Both functions are therefore
+/// restricted to TracedQFunctions (the tabular and linear ones), which already rely on this.
+/// Both functions should start out at 0.
+#[derive(Debug)]
+pub struct DoubleQLearner<A: FiniteSpace, Q> {
+	/// The action space used by the agent
+	action_space: A,
+	/// The second estimate (B)
+	q_func: Q,
+	/// The discount factor
+	gamma: f64,
+	/// The learning rate
+	alpha: f64,
+	/// The time period to train agent on when calling train
+	train_period: TimePeriod,
+}
+
+impl<T, S: Space, A: FiniteSpace, Q> OnlineTrainer<S, A, T> for DoubleQLearner<A, Q>
+	where T: TracedQFunction<S, A> + Agent<S, A>,
+		  Q: TracedQFunction<S, A> {
+	fn train_step<R: Rng>(&mut self, agent: &mut T, transition: Transition<S, A>, rng: &mut R) {
+		let (state, action, reward, next) = transition;
+
+		let actions = self.action_space.enumerate();
+		let val_a = |agent: &T, q_func: &Q, a: &A::Element| agent.eval(&next, a) - q_func.eval(&next, a);
+		let val_b = |q_func: &Q, a: &A::Element| q_func.eval(&next, a);
+
+		let update_a = rng.gen::<bool>();
+		let best = if update_a {
+			argmax(actions.iter().map(|a| val_a(agent, &self.q_func, a)))
+		} else {
+			argmax(actions.iter().map(|a| val_b(&self.q_func, a)))
+		};
+
+		let target = if update_a {
+			reward + self.gamma*val_b(&self.q_func, &actions[best])
+		} else {
+			reward + self.gamma*val_a(agent, &self.q_func, &actions[best])
+		};
+		// The sum moves exactly as much as the updated estimate does
+		let other = if update_a {
+			self.q_func.eval(&state, &action)
+		} else {
+			agent.eval(&state, &action) - self.q_func.eval(&state, &action)
+		};
+
+		agent.update(&state, &action, target + other, self.alpha);
+		if !update_a {
+			self.q_func.update(&state, &action, target, self.alpha);
+		}
+	}
+	fn train_with<R, O>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R, observer: &mut O)
+		where R: Rng, O: Observer<S, A, T> {
+		let period = self.train_period.clone();
+		train_online(self, agent, env, period, rng, observer);
+	}
+}
+
+impl<A: FiniteSpace, Q: Stateful> Stateful for DoubleQLearner<A, Q> {
+	/// The state of the second estimate
+	type State = Q::State;
+
+	fn get_state(&self) -> Q::State {
+		self.q_func.get_state()
+	}
+	fn set_state(&mut self, state: Q::State) -> Result<(), CheckpointError> {
+		self.q_func.set_state(state)
+	}
+}
+
+impl<A: FiniteSpace, Q> DoubleQLearner<A, Q> {
+	/// Returns a new DoubleQLearner with the given info, using q_func as the second estimate
+	pub fn new(action_space: A, q_func: Q, gamma: f64, alpha: f64, train_period: TimePeriod) -> DoubleQLearner<A, Q> {
+		DoubleQLearner {
+			action_space: action_space,
+			q_func: q_func,
+			gamma: gamma,
+			alpha: alpha,
+			train_period: train_period
+		}
+	}
+	/// Creates a new DoubleQLearner with default gamma, alpha, and train_period
+	pub fn default(action_space: A, q_func: Q) -> DoubleQLearner<A, Q> {
+		DoubleQLearner::new(action_space, q_func, 0.95, 0.1, TimePeriod::EPISODES(100))
+	}
+	/// Sets gamma field of self
+	pub fn gamma(mut self, gamma: f64) -> DoubleQLearner<A, Q> {
+		self.gamma = gamma;
+		self
+	}
+	/// Sets alpha field of self
+	pub fn alpha(mut self, alpha: f64) -> DoubleQLearner<A, Q> {
+		self.alpha = alpha;
+		self
+	}
+	/// Sets train_period field of self
+	pub fn train_period(mut self, train_period: TimePeriod) -> DoubleQLearner<A, Q> {
+		self.train_period = train_period;
+		self
+	}
+	/// Returns the second estimate (B) learned by self
+	pub fn get_q_func(&self) -> &Q {
+		&self.q_func
+	}
+}
+
+// Index of the first largest value
+fn argmax<I: Iterator<Item=f64>>(vals: I) -> usize {
+	let mut best = (0, f64::MIN);
+	for (i, val) in vals.enumerate() {
+		if val > best.1 {
+			best = (i, val);
+		}
+	}
+	best.0
+}
\ No newline at end of file
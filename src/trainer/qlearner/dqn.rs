@@ -0,0 +1,234 @@
+use std::f64;
+
+use rand::Rng;
+
+use num::Float;
+use num::cast::NumCast;
+
+use environment::Environment;
+use environment::Transition;
+use environment::{Space, FiniteSpace};
+use trainer::{OnlineTrainer, Observer, train_online};
+use agent::Agent;
+use util::{QFunction, ParameterizedFunc, TimePeriod, Stateful};
+use util::checkpoint::CheckpointError;
+use util::replay::{ReplayBuffer, ReplayBufferState};
+
+/// How the target network of a DQN follows the agent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TargetUpdate {
+	/// Copy the agent's parameters into the target network every given number of steps
+	Sync(usize),
+	/// After every step, move the target network towards the agent:
+	/// target = tau*params + (1 - tau)*target
+	Polyak(f64),
+}
+
+/// Represents an OnlineTrainer for Q-functions
+/// Uses the [Deep Q-Network](https://www.nature.com/articles/nature14236) algorithm
+///
+/// Like QLearner, but trains on minibatches sampled from a replay buffer and bootstraps
+/// from a separate, slowly changing copy of the agent's parameters (the target network)
+#[derive(Debug)]
+pub struct DQN<F: Float, S: Space, A: FiniteSpace> {
+	/// The action space used by the agent
+	action_space: A,
+	/// The discount factor
+	gamma: f64,
+	/// The learning rate
+	alpha: f64,
+	/// The time period to train agent on when calling train
+	train_period: TimePeriod,
+	/// Stores the transitions minibatches are sampled from
+	replay: ReplayBuffer<S, A>,
+	/// The number of transitions in each minibatch
+	batch_size: usize,
+	/// How the target network is updated
+	target_update: TargetUpdate,
+	/// Whether to use the Double DQN target (actions chosen by the agent, evaluated by the target network)
+	double: bool,
+	/// The parameters of the target network
+	target_params: Vec<F>,
+	/// Number of train steps performed
+	steps: usize,
+}
+
+/// The internal state of a DQN trainer
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct DQNState<F, S, A> {
+	/// The parameters of the target network
+	pub target_params: Vec<F>,
+	/// Number of train steps performed
+	pub steps: usize,
+	/// The transitions minibatches are sampled from
+	pub replay: ReplayBufferState<S, A>,
+}
+
+impl<F: Float, T, S: Space, A: FiniteSpace> OnlineTrainer<S, A, T> for DQN<F, S, A>
+	where T: QFunction<S, A> + Agent<S, A> + ParameterizedFunc<F> {
+	fn train_step<R: Rng>(&mut self, agent: &mut T, transition: Transition<S, A>, rng: &mut R) {
+		if self.target_params.is_empty() {
+			self.target_params = agent.get_params();
+		}
+
+		self.replay.push(transition);
+		if self.replay.len() >= self.batch_size {
+			let samples = self.replay.sample(self.batch_size, rng);
+			let batch: Vec<_> = samples.iter().map(|&(i, _)| self.replay.get(i).clone()).collect();
+			let targets = self.targets(agent, &batch);
+
+			for (((index, weight), (state, action, _, _)), target) in samples.into_iter().zip(batch).zip(targets) {
+				let td_error = target - agent.eval(&state, &action);
+				agent.update(&state, &action, target, weight*self.alpha);
+				self.replay.update_priority(index, td_error);
+			}
+		}
+
+		self.steps += 1;
+		match self.target_update {
+			TargetUpdate::Sync(period) => if self.steps % period == 0 {
+				self.target_params = agent.get_params();
+			},
+			TargetUpdate::Polyak(tau) => {
+				let tau: F = NumCast::from(tau).unwrap();
+				let params = agent.get_params();
+				for i in 0..params.len() {
+					self.target_params[i] = tau*params[i] + (F::one() - tau)*self.target_params[i];
+				}
+			}
+		}
+	}
+	fn train_with<R, O>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R, observer: &mut O)
+		where R: Rng, O: Observer<S, A, T> {
+		let period = self.train_period.clone();
+		train_online(self, agent, env, period, rng, observer);
+	}
+}
+
+impl<F: Float, S: Space, A: FiniteSpace> Stateful for DQN<F, S, A> {
+	type State = DQNState<F, S::Element, A::Element>;
+
+	fn get_state(&self) -> Self::State {
+		DQNState {
+			target_params: self.target_params.clone(),
+			steps: self.steps,
+			replay: self.replay.get_state()
+		}
+	}
+	fn set_state(&mut self, state: Self::State) -> Result<(), CheckpointError> {
+		self.replay.set_state(state.replay)?;
+		self.target_params = state.target_params;
+		self.steps = state.steps;
+		Ok(())
+	}
+}
+
+impl<S: Space, A: FiniteSpace> DQN<f64, S, A> {
+	/// Creates a new DQN with default gamma, alpha, train_period, replay buffer and target updates
+	pub fn default(action_space: A) -> DQN<f64, S, A> {
+		DQN::new(action_space, 0.95, 0.1, TimePeriod::EPISODES(100), ReplayBuffer::new(10000), 32)
+	}
+}
+
+impl<F: Float, S: Space, A: FiniteSpace> DQN<F, S, A> {
+	/// Returns a new DQN with the given info, whose target network is synced every 500 steps
+	pub fn new(action_space: A, gamma: f64, alpha: f64, train_period: TimePeriod,
+			   replay: ReplayBuffer<S, A>, batch_size: usize) -> DQN<F, S, A> {
+		assert!(batch_size > 0, "batch_size must be positive");
+
+		DQN {
+			action_space: action_space,
+			gamma: gamma,
+			alpha: alpha,
+			train_period: train_period,
+			replay: replay,
+			batch_size: batch_size,
+			target_update: TargetUpdate::Sync(500),
+			double: false,
+			target_params: Vec::new(),
+			steps: 0
+		}
+	}
+	/// Sets gamma field of self
+	pub fn gamma(mut self, gamma: f64) -> DQN<F, S, A> {
+		self.gamma = gamma;
+		self
+	}
+	/// Sets alpha field of self
+	pub fn alpha(mut self, alpha: f64) -> DQN<F, S, A> {
+		self.alpha = alpha;
+		self
+	}
+	/// Sets train_period field of self
+	pub fn train_period(mut self, train_period: TimePeriod) -> DQN<F, S, A> {
+		self.train_period = train_period;
+		self
+	}
+	/// Sets the replay buffer and the number of transitions in each minibatch
+	pub fn replay(mut self, replay: ReplayBuffer<S, A>, batch_size: usize) -> DQN<F, S, A> {
+		assert!(batch_size > 0, "batch_size must be positive");
+
+		self.replay = replay;
+		self.batch_size = batch_size;
+		self
+	}
+	/// Sets target_update field of self
+	pub fn target_update(mut self, target_update: TargetUpdate) -> DQN<F, S, A> {
+		match target_update {
+			TargetUpdate::Sync(period) => assert!(period > 0, "sync period must be positive"),
+			TargetUpdate::Polyak(tau) => assert!(0.0 <= tau && tau <= 1.0, "tau must be between 0 and 1"),
+		}
+
+		self.target_update = target_update;
+		self
+	}
+	/// Sets double field of self
+	pub fn double(mut self, double: bool) -> DQN<F, S, A> {
+		self.double = double;
+		self
+	}
+	/// Returns the replay buffer used by self
+	pub fn get_replay(&self) -> &ReplayBuffer<S, A> {
+		&self.replay
+	}
+
+	// Calculates the bootstrapped target of each transition
+	fn targets<T>(&self, agent: &mut T, batch: &[Transition<S, A>]) -> Vec<f64>
+		where T: QFunction<S, A> + ParameterizedFunc<F> {
+		let actions = self.action_space.enumerate();
+
+		// The action whose value is bootstrapped from in each next state
+		let best_action = |agent: &T, next: &S::Element| {
+			let mut best = (0, f64::MIN);
+			for (i, a) in actions.iter().enumerate() {
+				let val = agent.eval(next, a);
+				if val > best.1 {
+					best = (i, val);
+				}
+			}
+			best
+		};
+
+		let chosen: Vec<_> = if self.double {
+			batch.iter().map(|t| best_action(agent, &t.3).0).collect()
+		} else {
+			Vec::new()
+		};
+
+		// Evaluate using the target network, then give the agent its parameters back
+		let params = agent.get_params();
+		agent.set_params(self.target_params.clone());
+		let targets = batch.iter().enumerate().map(|(i, &(_, _, reward, ref next))| {
+			let next_val = if self.double {
+				agent.eval(next, &actions[chosen[i]])
+			} else {
+				best_action(agent, next).1
+			};
+			reward + self.gamma*next_val
+		}).collect();
+		agent.set_params(params);
+
+		targets
+	}
+}
\ No newline at end of file
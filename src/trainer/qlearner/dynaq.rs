@@ -2,16 +2,16 @@ use std::collections::HashSet;
 use std::hash::Hash;
 use std::f64;
 
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
 use environment::Environment;
 use environment::Transition;
 use environment::{Space, FiniteSpace};
-use trainer::OnlineTrainer;
+use trainer::{OnlineTrainer, Observer, train_online};
 use model::Model;
 use agent::Agent;
-use util::{QFunction, TimePeriod, Chooser};
-use util::chooser::Weighted;
+use util::{QFunction, TimePeriod, Stateful};
+use util::checkpoint::CheckpointError;
 
 /// Represents an OnlineTrainer for Q-functions
 /// Uses the Dyna-Q algorithm
@@ -28,20 +28,37 @@ pub struct DynaQ<S: Space, A: FiniteSpace, M: Model<S, A>> where
 	train_period: TimePeriod,
 	/// The number of (state, action) pairs to sample each train step
 	num_samples: usize,
-	/// The states observed by the agent
-	states: HashSet<S::Element>,
-	/// The actions performed by the agent
-	actions: HashSet<A::Element>,
+	/// The states observed by the agent, in the order they were first seen
+	// Kept in order (instead of only in a HashSet) so sampling from them is reproducible
+	states: Vec<S::Element>,
+	/// The actions performed by the agent, in the order they were first performed
+	actions: Vec<A::Element>,
+	/// Members of states
+	seen_states: HashSet<S::Element>,
+	/// Members of actions
+	seen_actions: HashSet<A::Element>,
 	model: M,
 }
 
+/// The internal state of a DynaQ trainer
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct DynaQState<S, A, M> {
+	/// The states observed by the agent, in the order they were first seen
+	pub states: Vec<S>,
+	/// The actions performed by the agent, in the order they were first performed
+	pub actions: Vec<A>,
+	/// State of the learned model
+	pub model: M,
+}
+
 impl<T, S: Space, A: FiniteSpace, M: Model<S, A>> OnlineTrainer<S, A, T> for DynaQ<S, A, M>
 	where T: QFunction<S, A> + Agent<S, A>,
 		  S::Element: Hash + Eq,
 		  A::Element: Hash + Eq {
 	// Possibly improperly implemented
 	// Updating Q maybe should make heavier use of model
-	fn train_step(&mut self, agent: &mut T, transition: Transition<S, A>) {
+	fn train_step<R: Rng>(&mut self, agent: &mut T, transition: Transition<S, A>, rng: &mut R) {
 		let (state, action, reward, next) = transition.clone();
 		
 		let mut max_next_val = f64::MIN;
@@ -52,40 +69,53 @@ impl<T, S: Space, A: FiniteSpace, M: Model<S, A>> OnlineTrainer<S, A, T> for Dyn
 		agent.update(&state, &action, reward + self.gamma*max_next_val, self.alpha);
 		self.model.update(transition);
 
-		self.states.insert(state.clone());
-		self.states.insert(next.clone());
-		self.actions.insert(action.clone());
+		self.observe(state, action, next);
 
-		let mut rng = thread_rng();
 		for _ in 0..self.num_samples {
-			let state_index = rng.gen_range(0, self.states.len());
-			let s0 = &self.states.iter().skip(state_index).next().unwrap();
+			let s0 = &self.states[rng.gen_range(0, self.states.len())];
+			let a = &self.actions[rng.gen_range(0, self.actions.len())];
 
-			let action_index = rng.gen_range(0, self.actions.len());
-			let a = &self.actions.iter().skip(action_index).next().unwrap();
-
-			let (r, s1) = self.sample_model(s0, a);
+			let (s1, r) = match self.model.sample(s0, a, rng) {
+				Some(sample) => sample,
+				None => continue
+			};
 
 			let mut max_next_val = f64::MIN;
 			for a in &self.all_actions {
-				max_next_val = max_next_val.max(agent.eval(s1, a));
+				max_next_val = max_next_val.max(agent.eval(&s1, a));
 			}
 
 			agent.update(s0, a, r + self.gamma*max_next_val, self.alpha);			
 		}
 	}
-	fn train(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>) {
-		let mut obs = env.reset();
-		let mut time_remaining = self.train_period.clone();
-		while !time_remaining.is_none() {
-			let action = agent.get_action(&obs.state);
-			let new_obs = env.step(&action);
-			self.train_step(agent, (obs.state, action, new_obs.reward, new_obs.state.clone()));
+	fn train_with<R, O>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R, observer: &mut O)
+		where R: Rng, O: Observer<S, A, T> {
+		let period = self.train_period.clone();
+		train_online(self, agent, env, period, rng, observer);
+	}
+}
 
-			time_remaining = time_remaining.dec(new_obs.done);
-			obs = if new_obs.done {env.reset()} else {new_obs};
+impl<S: Space, A: FiniteSpace, M> Stateful for DynaQ<S, A, M>
+	where M: Model<S, A> + Stateful,
+		  S::Element: Hash + Eq,
+		  A::Element: Hash + Eq {
+	type State = DynaQState<S::Element, A::Element, M::State>;
+
+	fn get_state(&self) -> Self::State {
+		DynaQState {
+			states: self.states.clone(),
+			actions: self.actions.clone(),
+			model: self.model.get_state()
 		}
 	}
+	fn set_state(&mut self, state: Self::State) -> Result<(), CheckpointError> {
+		self.model.set_state(state.model)?;
+		self.seen_states = state.states.iter().cloned().collect();
+		self.seen_actions = state.actions.iter().cloned().collect();
+		self.states = state.states;
+		self.actions = state.actions;
+		Ok(())
+	}
 }
 
 impl<S: Space, A: FiniteSpace, M: Model<S, A>> DynaQ<S, A, M> 
@@ -99,8 +129,10 @@ impl<S: Space, A: FiniteSpace, M: Model<S, A>> DynaQ<S, A, M>
 			alpha: alpha,
 			train_period: train_period,
 			num_samples: num_samples,
-			states: HashSet::new(),
-			actions: HashSet::new(),
+			states: Vec::new(),
+			actions: Vec::new(),
+			seen_states: HashSet::new(),
+			seen_actions: HashSet::new(),
 			model: model
 		}
 	}
@@ -112,8 +144,10 @@ impl<S: Space, A: FiniteSpace, M: Model<S, A>> DynaQ<S, A, M>
 			alpha: 0.1,
 			train_period: TimePeriod::EPISODES(30),
 			num_samples: 10,
-			states: HashSet::new(),
-			actions: HashSet::new(),
+			states: Vec::new(),
+			actions: Vec::new(),
+			seen_states: HashSet::new(),
+			seen_actions: HashSet::new(),
 			model: model
 		}
 	}
@@ -138,15 +172,15 @@ impl<S: Space, A: FiniteSpace, M: Model<S, A>> DynaQ<S, A, M>
 		self
 	}
 
-	fn sample_model(&self, s: &S::Element, a: &A::Element) -> (f64, &S::Element) {
-		let weights = self.states.iter().map(|s1| {
-			self.model.transition(s, a, s1)
-		}).collect::<Vec<_>>();
-		let states: Vec<_> = self.states.iter().collect();
-
-		let chooser = Weighted;
-		let s1 = chooser.choose(&states, weights);
-		let r = self.model.reward(s, a, s1);
-		(r, s1)
+	fn observe(&mut self, state: S::Element, action: A::Element, next: S::Element) {
+		if self.seen_states.insert(state.clone()) {
+			self.states.push(state);
+		}
+		if self.seen_states.insert(next.clone()) {
+			self.states.push(next);
+		}
+		if self.seen_actions.insert(action.clone()) {
+			self.actions.push(action);
+		}
 	}
 }
\ No newline at end of file
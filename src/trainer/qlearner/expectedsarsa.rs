@@ -0,0 +1,74 @@
+use rand::Rng;
+
+use environment::Environment;
+use environment::Transition;
+use environment::{Space, FiniteSpace};
+use trainer::{OnlineTrainer, Observer, train_online};
+use agent::StochasticAgent;
+use util::{QFunction, TimePeriod};
+
+/// Represents an OnlineTrainer for Q-functions
+/// Uses the [Expected SARSA algorithm](http://www.cs.ox.ac.uk/people/shimon.whiteson/pubs/vanseijenadprl09.pdf)
+///
+/// Like SARSA, but instead of bootstrapping from one sampled next action, the values of all
+/// next actions are averaged using the probabilities the agent would choose them with
+#[derive(Debug)]
+pub struct ExpectedSARSA<A: FiniteSpace> {
+	/// The action space used by the agent
+	action_space: A,
+	/// The discount factor
+	gamma: f64,
+	/// The learning rate
+	alpha: f64,
+	/// The time period to train agent on when calling train
+	train_period: TimePeriod,
+}
+
+impl<T, S: Space, A: FiniteSpace> OnlineTrainer<S, A, T> for ExpectedSARSA<A>
+	where T: QFunction<S, A> + StochasticAgent<S, A> {
+	fn train_step<R: Rng>(&mut self, agent: &mut T, transition: Transition<S, A>, _: &mut R) {
+		let (state, action, reward, next) = transition;
+
+		let probs = agent.get_action_probs(&next, &self.action_space);
+		let expected_val = self.action_space.enumerate().iter().zip(probs).fold(0.0, |sum, (a, p)| {
+			sum + p*agent.eval(&next, a)
+		});
+		agent.update(&state, &action, reward + self.gamma*expected_val, self.alpha);
+	}
+	fn train_with<R, O>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R, observer: &mut O)
+		where R: Rng, O: Observer<S, A, T> {
+		let period = self.train_period.clone();
+		train_online(self, agent, env, period, rng, observer);
+	}
+}
+
+impl<A: FiniteSpace> ExpectedSARSA<A> {
+	/// Returns a new ExpectedSARSA with the given info
+	pub fn new(action_space: A, gamma: f64, alpha: f64, train_period: TimePeriod) -> ExpectedSARSA<A> {
+		ExpectedSARSA {
+			action_space: action_space,
+			gamma: gamma,
+			alpha: alpha,
+			train_period: train_period
+		}
+	}
+	/// Creates a new ExpectedSARSA with default gamma, alpha, and train_period
+	pub fn default(action_space: A) -> ExpectedSARSA<A> {
+		ExpectedSARSA::new(action_space, 0.95, 0.1, TimePeriod::EPISODES(100))
+	}
+	/// Sets gamma field of self
+	pub fn gamma(mut self, gamma: f64) -> ExpectedSARSA<A> {
+		self.gamma = gamma;
+		self
+	}
+	/// Sets alpha field of self
+	pub fn alpha(mut self, alpha: f64) -> ExpectedSARSA<A> {
+		self.alpha = alpha;
+		self
+	}
+	/// Sets train_period field of self
+	pub fn train_period(mut self, train_period: TimePeriod) -> ExpectedSARSA<A> {
+		self.train_period = train_period;
+		self
+	}
+}
\ No newline at end of file
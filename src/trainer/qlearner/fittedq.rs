@@ -1,30 +1,33 @@
 use std::f64;
 
+use rand::Rng;
+
 use environment::Transition;
 use environment::{Space, FiniteSpace};
 use trainer::BatchTrainer;
 use agent::Agent;
-use util::QFunction;
+use util::FittableQFunction;
 
 /// BatchTrainer for Q-functions
-/// Uses Fitted Q Iteration 
+/// Uses [Fitted Q Iteration](http://www.jmlr.org/papers/volume6/ernst05a/ernst05a.pdf)
+///
+/// Each iteration computes the target r + gamma*max_a' Q(s', a') of every transition using the
+/// current Q-function, then replaces the Q-function with a fresh fit to those targets
 #[derive(Debug)]
 pub struct FittedQIteration<A: FiniteSpace> {
 	// Set of all possible actions
 	actions: Vec<A::Element>,
 	// Discount factor
 	gamma: f64,
-	// Learning rate
-	alpha: f64,
 	// Number of times to recalculate Q
 	iters: usize,
 }
 
 impl<S: Space, A: FiniteSpace, T> BatchTrainer<S, A, T> for FittedQIteration<A>
-	where T: QFunction<S, A> + Agent<S, A> {
-	fn train(&mut self, agent: &mut T, transitions: Vec<Transition<S, A>>) {
+	where T: FittableQFunction<S, A> + Agent<S, A> {
+	fn train<R: Rng>(&mut self, agent: &mut T, transitions: Vec<Transition<S, A>>, rng: &mut R) {
 		for _ in 0..self.iters {
-			let mut patterns = Vec::with_capacity(transitions.len());
+			let mut data = Vec::with_capacity(transitions.len());
 			for &(ref s0, ref a, r, ref s1) in &transitions {
 				let mut max_next_val = f64::MIN;
 				for a in &self.actions {
@@ -32,24 +35,20 @@ impl<S: Space, A: FiniteSpace, T> BatchTrainer<S, A, T> for FittedQIteration<A>
 				}
 
 				let target = r + self.gamma*max_next_val;
-				patterns.push((s0, a, target));
+				data.push((s0.clone(), a.clone(), target));
 			}
 
-			// Replace with more general learn function and new LearnableFunction trait?
-			for (s, a, q) in patterns {
-				agent.update(s, a, q, self.alpha);
-			}
+			agent.fit(&data, rng);
 		}
 	}
 }
 
 impl<A: FiniteSpace> FittedQIteration<A> {
 	/// Creates a new FittedQIteration with the given parameters
-	pub fn new(action_space: A, gamma: f64, alpha: f64, iters: usize) -> FittedQIteration<A> {
+	pub fn new(action_space: A, gamma: f64, iters: usize) -> FittedQIteration<A> {
 		FittedQIteration {
 			actions: action_space.enumerate(),
 			gamma: gamma,
-			alpha: alpha,
 			iters: iters
 		}
 	}
@@ -58,7 +57,6 @@ impl<A: FiniteSpace> FittedQIteration<A> {
 		FittedQIteration {
 			actions: action_space.enumerate(),
 			gamma: 0.95,
-			alpha: 0.1,
 			iters: 10,
 		}
 	}
@@ -67,11 +65,6 @@ impl<A: FiniteSpace> FittedQIteration<A> {
 		self.gamma = gamma;
 		self
 	}
-	/// Sets alpha field of self
-	pub fn alpha(mut self, alpha: f64) -> FittedQIteration<A> {
-		self.alpha = alpha;
-		self
-	}
 	/// Sets iters field of self
 	pub fn iters(mut self, iters: usize) -> FittedQIteration<A> {
 		self.iters = iters;
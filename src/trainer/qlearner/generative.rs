@@ -0,0 +1,128 @@
+use std::f64;
+
+use rand::Rng;
+
+use environment::Environment;
+use environment::Transition;
+use environment::{Space, FiniteSpace};
+use trainer::{OnlineTrainer, Observer, train_online};
+use model::GenerativeModel;
+use agent::Agent;
+use util::{QFunction, TimePeriod, Stateful};
+use util::checkpoint::CheckpointError;
+use util::replay::ReplayBuffer;
+
+/// Represents an OnlineTrainer for Q-functions
+/// Uses the Dyna-Q algorithm with a GenerativeModel
+///
+/// Like DynaQ, but planning starts from states remembered in a replay buffer and performs a random
+/// action, with the model generating what happens next. Since states are never enumerated or hashed,
+/// this works with continuous state spaces (for example, using LinearDynamics as the model).
+#[derive(Debug)]
+pub struct GenerativeDynaQ<S: Space, A: FiniteSpace, M: GenerativeModel<S, A>> {
+	/// All the possible performable actions
+	all_actions: Vec<A::Element>,
+	/// The discount factor
+	gamma: f64,
+	/// The learning rate
+	alpha: f64,
+	/// The time period to train agent on when calling train
+	train_period: TimePeriod,
+	/// The number of imagined transitions to learn from each train step
+	num_samples: usize,
+	/// The real transitions whose states planning starts from
+	replay: ReplayBuffer<S, A>,
+	model: M,
+}
+
+impl<T, S: Space, A: FiniteSpace, M: GenerativeModel<S, A>> OnlineTrainer<S, A, T> for GenerativeDynaQ<S, A, M>
+	where T: QFunction<S, A> + Agent<S, A> {
+	fn train_step<R: Rng>(&mut self, agent: &mut T, transition: Transition<S, A>, rng: &mut R) {
+		let (state, action, reward, next) = transition.clone();
+
+		let target = reward + self.gamma*self.max_val(agent, &next);
+		agent.update(&state, &action, target, self.alpha);
+		self.model.update(transition.clone());
+		self.replay.push(transition);
+
+		for (i, weight) in self.replay.sample(self.num_samples, rng) {
+			let s0 = self.replay.get(i).0.clone();
+			let a = self.all_actions[rng.gen_range(0, self.all_actions.len())].clone();
+
+			let (s1, r) = self.model.generate(&s0, &a, rng);
+			let target = r + self.gamma*self.max_val(agent, &s1);
+			agent.update(&s0, &a, target, weight*self.alpha);
+		}
+	}
+	fn train_with<R, O>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R, observer: &mut O)
+		where R: Rng, O: Observer<S, A, T> {
+		let period = self.train_period.clone();
+		train_online(self, agent, env, period, rng, observer);
+	}
+}
+
+impl<S: Space, A: FiniteSpace, M> Stateful for GenerativeDynaQ<S, A, M>
+	where M: GenerativeModel<S, A> + Stateful {
+	/// The state of the learned model (the replay buffer is not saved)
+	type State = M::State;
+
+	fn get_state(&self) -> M::State {
+		self.model.get_state()
+	}
+	fn set_state(&mut self, state: M::State) -> Result<(), CheckpointError> {
+		self.model.set_state(state)
+	}
+}
+
+impl<S: Space, A: FiniteSpace, M: GenerativeModel<S, A>> GenerativeDynaQ<S, A, M> {
+	/// Returns a new GenerativeDynaQ with the given parameters
+	pub fn new(action_space: A, gamma: f64, alpha: f64, train_period: TimePeriod,
+			   num_samples: usize, replay: ReplayBuffer<S, A>, model: M) -> GenerativeDynaQ<S, A, M> {
+		GenerativeDynaQ {
+			all_actions: action_space.enumerate(),
+			gamma: gamma,
+			alpha: alpha,
+			train_period: train_period,
+			num_samples: num_samples,
+			replay: replay,
+			model: model
+		}
+	}
+	/// Creates a new GenerativeDynaQ with default parameters, remembering up to 10000 real transitions
+	pub fn default(action_space: A, model: M) -> GenerativeDynaQ<S, A, M> {
+		GenerativeDynaQ::new(action_space, 0.95, 0.1, TimePeriod::EPISODES(30), 10, ReplayBuffer::new(10000), model)
+	}
+	/// Sets gamma field of self
+	pub fn gamma(mut self, gamma: f64) -> GenerativeDynaQ<S, A, M> {
+		self.gamma = gamma;
+		self
+	}
+	/// Sets alpha field of self
+	pub fn alpha(mut self, alpha: f64) -> GenerativeDynaQ<S, A, M> {
+		self.alpha = alpha;
+		self
+	}
+	/// Sets train_period field of self
+	pub fn train_period(mut self, train_period: TimePeriod) -> GenerativeDynaQ<S, A, M> {
+		self.train_period = train_period;
+		self
+	}
+	/// Sets num_samples field of self
+	pub fn num_samples(mut self, num_samples: usize) -> GenerativeDynaQ<S, A, M> {
+		self.num_samples = num_samples;
+		self
+	}
+	/// Sets replay field of self
+	pub fn replay(mut self, replay: ReplayBuffer<S, A>) -> GenerativeDynaQ<S, A, M> {
+		self.replay = replay;
+		self
+	}
+	/// Returns the model learned by self
+	pub fn get_model(&self) -> &M {
+		&self.model
+	}
+
+	fn max_val<T: QFunction<S, A>>(&self, agent: &T, state: &S::Element) -> f64 {
+		self.all_actions.iter().fold(f64::MIN, |max, a| max.max(agent.eval(state, a)))
+	}
+}
\ No newline at end of file
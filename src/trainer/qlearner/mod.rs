@@ -2,8 +2,24 @@ pub mod qlearner;
 pub mod sarsa;
 pub mod dynaq;
 pub mod fittedq;
+pub mod dqn;
+pub mod expectedsarsa;
+pub mod nstep;
+pub mod traces;
+pub mod doubleq;
+pub mod sweeping;
+pub mod generative;
+pub mod montecarlo;
 
 pub use self::qlearner::*;
 pub use self::sarsa::*;
 pub use self::dynaq::*;
-pub use self::fittedq::*;
\ No newline at end of file
+pub use self::fittedq::*;
+pub use self::dqn::*;
+pub use self::expectedsarsa::*;
+pub use self::nstep::*;
+pub use self::traces::*;
+pub use self::doubleq::*;
+pub use self::sweeping::*;
+pub use self::generative::*;
+pub use self::montecarlo::*;
\ No newline at end of file
@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::f64;
+
+use rand::Rng;
+
+use environment::Environment;
+use environment::Transition;
+use environment::{Space, FiniteSpace};
+use trainer::{OnlineTrainer, Observer, train_online};
+use agent::{Agent, StochasticAgent};
+use util::{QFunction, TimePeriod, Stateful};
+use util::checkpoint::CheckpointError;
+
+/// Which occurrences of a (state, action) pair in an episode are learned from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VisitType {
+	/// Only the first time the pair is visited in each episode
+	FirstVisit,
+	/// Every time the pair is visited
+	EveryVisit,
+}
+
+/// Represents an OnlineTrainer for Q-functions
+/// Uses [on-policy Monte Carlo control](http://incompleteideas.net/book/ebook/node53.html)
+///
+/// Transitions are stored until the episode ends, then the value of every visited (state, action)
+/// pair is moved towards the discounted return that followed it. Nothing is bootstrapped, so an
+/// episode cut short when training stops is thrown away.
+#[derive(Debug)]
+pub struct MonteCarlo<S: Space, A: Space> {
+	/// The discount factor
+	gamma: f64,
+	/// The learning rate
+	alpha: f64,
+	/// Which visits to each pair are learned from
+	visit_type: VisitType,
+	/// The time period to train agent on when calling train
+	train_period: TimePeriod,
+	/// The transitions of the current episode
+	transitions: Vec<Transition<S, A>>,
+}
+
+impl<T, S: Space, A: Space> OnlineTrainer<S, A, T> for MonteCarlo<S, A>
+	where T: QFunction<S, A> + Agent<S, A> {
+	fn train_step<R: Rng>(&mut self, _: &mut T, transition: Transition<S, A>, _: &mut R) {
+		self.transitions.push(transition);
+	}
+	fn finish_episode<R: Rng>(&mut self, agent: &mut T, done: bool, _: &mut R) {
+		if done {
+			let returns = discount::<S, A>(&self.transitions, self.gamma);
+			for (t, ret) in returns.into_iter().enumerate() {
+				if self.visit_type == VisitType::EveryVisit || is_first_visit::<S, A>(&self.transitions, t) {
+					let (ref state, ref action, _, _) = self.transitions[t];
+					agent.update(state, action, ret, self.alpha);
+				}
+			}
+		}
+		self.transitions.clear();
+	}
+	fn train_with<R, O>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R, observer: &mut O)
+		where R: Rng, O: Observer<S, A, T> {
+		let period = self.train_period.clone();
+		train_online(self, agent, env, period, rng, observer);
+	}
+}
+
+impl<S: Space, A: Space> Default for MonteCarlo<S, A> {
+	/// Creates a new MonteCarlo with default values for gamma, alpha, visit_type (first-visit), and train_period
+	fn default() -> MonteCarlo<S, A> {
+		MonteCarlo::new(0.95, 0.1, TimePeriod::EPISODES(100))
+	}
+}
+
+impl<S: Space, A: Space> MonteCarlo<S, A> {
+	/// Returns a new first-visit MonteCarlo with the given info
+	pub fn new(gamma: f64, alpha: f64, train_period: TimePeriod) -> MonteCarlo<S, A> {
+		MonteCarlo {
+			gamma: gamma,
+			alpha: alpha,
+			visit_type: VisitType::FirstVisit,
+			train_period: train_period,
+			transitions: Vec::new()
+		}
+	}
+	/// Sets gamma field of self
+	pub fn gamma(mut self, gamma: f64) -> MonteCarlo<S, A> {
+		self.gamma = gamma;
+		self
+	}
+	/// Sets alpha field of self
+	pub fn alpha(mut self, alpha: f64) -> MonteCarlo<S, A> {
+		self.alpha = alpha;
+		self
+	}
+	/// Sets visit_type field of self
+	pub fn visit_type(mut self, visit_type: VisitType) -> MonteCarlo<S, A> {
+		self.visit_type = visit_type;
+		self
+	}
+	/// Sets train_period field of self
+	pub fn train_period(mut self, train_period: TimePeriod) -> MonteCarlo<S, A> {
+		self.train_period = train_period;
+		self
+	}
+}
+
+/// Represents an OnlineTrainer for Q-functions
+/// Uses [off-policy Monte Carlo control](http://incompleteideas.net/book/first/ebook/node56.html)
+/// with weighted importance sampling
+///
+/// The agent explores using its own action probabilities, but its Q-function learns the values of
+/// the greedy policy. Working backwards from the end of each episode, returns are weighted by how
+/// much more likely the greedy policy was to take the same actions, and each pair's value becomes the
+/// weighted average of its returns. Once the agent took a non-greedy action, earlier steps are
+/// ignored. Like MonteCarlo, an episode cut short when training stops is thrown away.
+#[derive(Debug)]
+pub struct OffPolicyMonteCarlo<S: Space, A: FiniteSpace> where
+	S::Element: Hash + Eq, A::Element: Hash + Eq {
+	/// All the possible performable actions
+	all_actions: Vec<A::Element>,
+	/// The discount factor
+	gamma: f64,
+	/// Which visits to each pair are learned from
+	visit_type: VisitType,
+	/// The time period to train agent on when calling train
+	train_period: TimePeriod,
+	/// The transitions of the current episode
+	transitions: Vec<Transition<S, A>>,
+	/// The probability the agent took each action of the current episode with
+	probs: Vec<f64>,
+	/// The total weight given to the returns of each pair
+	weights: HashMap<(S::Element, A::Element), f64>,
+}
+
+/// The internal state of an OffPolicyMonteCarlo trainer
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct OffPolicyMonteCarloState<S, A> {
+	/// The total weight given to the returns of each pair
+	pub weights: Vec<((S, A), f64)>,
+}
+
+impl<T, S: Space, A: FiniteSpace> OnlineTrainer<S, A, T> for OffPolicyMonteCarlo<S, A>
+	where T: QFunction<S, A> + StochasticAgent<S, A>,
+		  S::Element: Hash + Eq,
+		  A::Element: Hash + Eq {
+	fn train_step<R: Rng>(&mut self, agent: &mut T, transition: Transition<S, A>, _: &mut R) {
+		// The agent is not updated until the episode ends, so this is the probability it acted with
+		self.probs.push(agent.get_action_prob(&transition.0, &transition.1));
+		self.transitions.push(transition);
+	}
+	fn finish_episode<R: Rng>(&mut self, agent: &mut T, done: bool, _: &mut R) {
+		if done {
+			let returns = discount::<S, A>(&self.transitions, self.gamma);
+			let mut ratio = 1.0;
+			for t in (0..self.transitions.len()).rev() {
+				let (ref state, ref action, _, _) = self.transitions[t];
+				if self.visit_type == VisitType::EveryVisit || is_first_visit::<S, A>(&self.transitions, t) {
+					let total = self.weights.entry((state.clone(), action.clone())).or_insert(0.0);
+					*total += ratio;
+					agent.update(state, action, returns[t], ratio/(*total));
+				}
+
+				let best = self.all_actions.iter().fold(f64::MIN, |max, a| max.max(agent.eval(state, a)));
+				if agent.eval(state, action) < best || self.probs[t] <= 0.0 {
+					break;
+				}
+				ratio /= self.probs[t];
+			}
+		}
+		self.transitions.clear();
+		self.probs.clear();
+	}
+	fn train_with<R, O>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R, observer: &mut O)
+		where R: Rng, O: Observer<S, A, T> {
+		let period = self.train_period.clone();
+		train_online(self, agent, env, period, rng, observer);
+	}
+}
+
+impl<S: Space, A: FiniteSpace> Stateful for OffPolicyMonteCarlo<S, A>
+	where S::Element: Hash + Eq,
+		  A::Element: Hash + Eq {
+	type State = OffPolicyMonteCarloState<S::Element, A::Element>;
+
+	fn get_state(&self) -> Self::State {
+		OffPolicyMonteCarloState {
+			weights: self.weights.iter().map(|(k, &w)| (k.clone(), w)).collect()
+		}
+	}
+	fn set_state(&mut self, state: Self::State) -> Result<(), CheckpointError> {
+		self.weights = state.weights.into_iter().collect();
+		Ok(())
+	}
+}
+
+impl<S: Space, A: FiniteSpace> OffPolicyMonteCarlo<S, A>
+	where S::Element: Hash + Eq,
+		  A::Element: Hash + Eq {
+	/// Returns a new every-visit OffPolicyMonteCarlo with the given info
+	pub fn new(action_space: A, gamma: f64, train_period: TimePeriod) -> OffPolicyMonteCarlo<S, A> {
+		OffPolicyMonteCarlo {
+			all_actions: action_space.enumerate(),
+			gamma: gamma,
+			visit_type: VisitType::EveryVisit,
+			train_period: train_period,
+			transitions: Vec::new(),
+			probs: Vec::new(),
+			weights: HashMap::new()
+		}
+	}
+	/// Creates a new OffPolicyMonteCarlo with default gamma, visit_type (every-visit), and train_period
+	pub fn default(action_space: A) -> OffPolicyMonteCarlo<S, A> {
+		OffPolicyMonteCarlo::new(action_space, 0.95, TimePeriod::EPISODES(100))
+	}
+	/// Sets gamma field of self
+	pub fn gamma(mut self, gamma: f64) -> OffPolicyMonteCarlo<S, A> {
+		self.gamma = gamma;
+		self
+	}
+	/// Sets visit_type field of self
+	pub fn visit_type(mut self, visit_type: VisitType) -> OffPolicyMonteCarlo<S, A> {
+		self.visit_type = visit_type;
+		self
+	}
+	/// Sets train_period field of self
+	pub fn train_period(mut self, train_period: TimePeriod) -> OffPolicyMonteCarlo<S, A> {
+		self.train_period = train_period;
+		self
+	}
+}
+
+// The discounted return following each transition
+fn discount<S: Space, A: Space>(transitions: &[Transition<S, A>], gamma: f64) -> Vec<f64> {
+	let mut returns = vec![0.0; transitions.len()];
+	let mut running_sum = 0.0;
+	for t in (0..transitions.len()).rev() {
+		running_sum = transitions[t].2 + gamma*running_sum;
+		returns[t] = running_sum;
+	}
+	returns
+}
+
+// Whether the pair visited at time t was not visited earlier in the episode
+fn is_first_visit<S: Space, A: Space>(transitions: &[Transition<S, A>], t: usize) -> bool {
+	let (ref state, ref action, _, _) = transitions[t];
+	!transitions[..t].iter().any(|&(ref s, ref a, _, _)| s == state && a == action)
+}
\ No newline at end of file
@@ -0,0 +1,215 @@
+use std::f64;
+use std::collections::VecDeque;
+
+use rand::Rng;
+
+use environment::Environment;
+use environment::Transition;
+use environment::{Space, FiniteSpace};
+use trainer::{OnlineTrainer, Observer, train_online};
+use agent::Agent;
+use util::{QFunction, TimePeriod};
+
+/// Represents an OnlineTrainer for Q-functions
+/// Uses n-step Q-learning
+///
+/// Instead of bootstrapping after a single reward, the agent is updated towards the discounted sum
+/// of the next n rewards plus the (discounted) max value of the state reached after them.
+/// Near the end of an episode, fewer rewards are available and nothing is bootstrapped.
+#[derive(Debug)]
+pub struct NStepQLearner<S: Space, A: FiniteSpace> {
+	/// The action space used by the agent
+	action_space: A,
+	/// The discount factor
+	gamma: f64,
+	/// The learning rate
+	alpha: f64,
+	/// The number of rewards summed before bootstrapping
+	steps: usize,
+	/// The time period to train agent on when calling train
+	train_period: TimePeriod,
+	/// The most recent transitions of the current episode that have not been learned from yet
+	transitions: VecDeque<Transition<S, A>>,
+}
+
+impl<T, S: Space, A: FiniteSpace> OnlineTrainer<S, A, T> for NStepQLearner<S, A>
+	where T: QFunction<S, A> + Agent<S, A> {
+	fn train_step<R: Rng>(&mut self, agent: &mut T, transition: Transition<S, A>, _: &mut R) {
+		// A full window is only learned from once another step shows it did not end the episode
+		if self.transitions.len() == self.steps {
+			let next_val = self.max_val(agent, &self.transitions[self.steps - 1].3);
+			update_oldest(agent, &mut self.transitions, self.gamma, self.alpha, next_val);
+		}
+		self.transitions.push_back(transition);
+	}
+	fn finish_episode<R: Rng>(&mut self, agent: &mut T, done: bool, _: &mut R) {
+		let last = self.transitions.back().map(|t| t.3.clone());
+		while !self.transitions.is_empty() {
+			let next_val = match last {
+				Some(ref next) if !done => self.max_val(agent, next),
+				_ => 0.0
+			};
+			update_oldest(agent, &mut self.transitions, self.gamma, self.alpha, next_val);
+		}
+	}
+	fn train_with<R, O>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R, observer: &mut O)
+		where R: Rng, O: Observer<S, A, T> {
+		let period = self.train_period.clone();
+		train_online(self, agent, env, period, rng, observer);
+	}
+}
+
+impl<S: Space, A: FiniteSpace> NStepQLearner<S, A> {
+	/// Returns a new NStepQLearner with the given info
+	pub fn new(action_space: A, gamma: f64, alpha: f64, steps: usize, train_period: TimePeriod) -> NStepQLearner<S, A> {
+		assert!(steps > 0, "steps must be positive");
+
+		NStepQLearner {
+			action_space: action_space,
+			gamma: gamma,
+			alpha: alpha,
+			steps: steps,
+			train_period: train_period,
+			transitions: VecDeque::new()
+		}
+	}
+	/// Creates a new NStepQLearner with default gamma, alpha, steps (3), and train_period
+	pub fn default(action_space: A) -> NStepQLearner<S, A> {
+		NStepQLearner::new(action_space, 0.95, 0.1, 3, TimePeriod::EPISODES(100))
+	}
+	/// Sets gamma field of self
+	pub fn gamma(mut self, gamma: f64) -> NStepQLearner<S, A> {
+		self.gamma = gamma;
+		self
+	}
+	/// Sets alpha field of self
+	pub fn alpha(mut self, alpha: f64) -> NStepQLearner<S, A> {
+		self.alpha = alpha;
+		self
+	}
+	/// Sets steps field of self
+	pub fn steps(mut self, steps: usize) -> NStepQLearner<S, A> {
+		assert!(steps > 0, "steps must be positive");
+
+		self.steps = steps;
+		self
+	}
+	/// Sets train_period field of self
+	pub fn train_period(mut self, train_period: TimePeriod) -> NStepQLearner<S, A> {
+		self.train_period = train_period;
+		self
+	}
+
+	fn max_val<T: QFunction<S, A>>(&self, agent: &T, state: &S::Element) -> f64 {
+		self.action_space.enumerate().iter().fold(f64::MIN, |max, a| max.max(agent.eval(state, a)))
+	}
+}
+
+/// Represents an OnlineTrainer for Q-functions
+/// Uses n-step SARSA
+///
+/// Instead of bootstrapping after a single reward, the agent is updated towards the discounted sum
+/// of the next n rewards plus the (discounted) value of the state reached after them and the action
+/// the agent took there. Near the end of an episode, fewer rewards are available and nothing is
+/// bootstrapped. If training stops mid-episode, the agent chooses the action to bootstrap from.
+#[derive(Debug)]
+pub struct NStepSARSA<S: Space, A: Space> {
+	/// The discount factor
+	gamma: f64,
+	/// The learning rate
+	alpha: f64,
+	/// The number of rewards summed before bootstrapping
+	steps: usize,
+	/// The time period to train agent on when calling train
+	train_period: TimePeriod,
+	/// The most recent transitions of the current episode that have not been learned from yet
+	transitions: VecDeque<Transition<S, A>>,
+}
+
+impl<T, S: Space, A: Space> OnlineTrainer<S, A, T> for NStepSARSA<S, A>
+	where T: QFunction<S, A> + Agent<S, A> {
+	fn train_step<R: Rng>(&mut self, agent: &mut T, transition: Transition<S, A>, _: &mut R) {
+		// A full window is only learned from once another step shows it did not end the episode,
+		// and that step starts from the state the window ends in with the action actually taken
+		if self.transitions.len() == self.steps {
+			let next_val = agent.eval(&transition.0, &transition.1);
+			update_oldest(agent, &mut self.transitions, self.gamma, self.alpha, next_val);
+		}
+		self.transitions.push_back(transition);
+	}
+	fn finish_episode<R: Rng>(&mut self, agent: &mut T, done: bool, rng: &mut R) {
+		let last = self.transitions.back().map(|t| t.3.clone());
+		while !self.transitions.is_empty() {
+			let next_val = match last {
+				Some(ref next) if !done => sampled_val(agent, next, rng),
+				_ => 0.0
+			};
+			update_oldest(agent, &mut self.transitions, self.gamma, self.alpha, next_val);
+		}
+	}
+	fn train_with<R, O>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R, observer: &mut O)
+		where R: Rng, O: Observer<S, A, T> {
+		let period = self.train_period.clone();
+		train_online(self, agent, env, period, rng, observer);
+	}
+}
+
+impl<S: Space, A: Space> Default for NStepSARSA<S, A> {
+	/// Creates a new NStepSARSA with default values for gamma, alpha, steps (3), and train_period
+	fn default() -> NStepSARSA<S, A> {
+		NStepSARSA::new(0.95, 0.1, 3, TimePeriod::EPISODES(100))
+	}
+}
+
+impl<S: Space, A: Space> NStepSARSA<S, A> {
+	/// Returns a new NStepSARSA with the given info
+	pub fn new(gamma: f64, alpha: f64, steps: usize, train_period: TimePeriod) -> NStepSARSA<S, A> {
+		assert!(steps > 0, "steps must be positive");
+
+		NStepSARSA {
+			gamma: gamma,
+			alpha: alpha,
+			steps: steps,
+			train_period: train_period,
+			transitions: VecDeque::new()
+		}
+	}
+	/// Sets gamma field of self
+	pub fn gamma(mut self, gamma: f64) -> NStepSARSA<S, A> {
+		self.gamma = gamma;
+		self
+	}
+	/// Sets alpha field of self
+	pub fn alpha(mut self, alpha: f64) -> NStepSARSA<S, A> {
+		self.alpha = alpha;
+		self
+	}
+	/// Sets steps field of self
+	pub fn steps(mut self, steps: usize) -> NStepSARSA<S, A> {
+		assert!(steps > 0, "steps must be positive");
+
+		self.steps = steps;
+		self
+	}
+	/// Sets train_period field of self
+	pub fn train_period(mut self, train_period: TimePeriod) -> NStepSARSA<S, A> {
+		self.train_period = train_period;
+		self
+	}
+}
+
+// The value of state and an action the agent chooses in it
+fn sampled_val<S, A, T, R>(agent: &T, state: &S::Element, rng: &mut R) -> f64
+	where S: Space, A: Space, T: QFunction<S, A> + Agent<S, A>, R: Rng {
+	let action = agent.get_action(state, rng);
+	agent.eval(state, &action)
+}
+
+// Moves the value of the oldest transition towards the discounted rewards of every buffered
+// transition plus the discounted value of the state they end in, then forgets it
+fn update_oldest<S, A, T>(agent: &mut T, transitions: &mut VecDeque<Transition<S, A>>, gamma: f64, alpha: f64, next_val: f64)
+	where S: Space, A: Space, T: QFunction<S, A> {
+	let target = transitions.iter().rev().fold(next_val, |ret, t| t.2 + gamma*ret);
+	let (state, action, _, _) = transitions.pop_front().unwrap();
+	agent.update(&state, &action, target, alpha);
+}
\ No newline at end of file
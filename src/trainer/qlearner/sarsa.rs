@@ -1,80 +1,119 @@
 use std::f64;
 
+use rand::Rng;
+
 use environment::Environment;
 use environment::Transition;
 use environment::Space;
-use trainer::OnlineTrainer;
+use trainer::{OnlineTrainer, Observer, train_online};
 use agent::Agent;
 use util::{QFunction, TimePeriod};
+use util::replay::ReplayBuffer;
 
 /// Represents an OnlineTrainer for Q-functions
 /// Uses the [SARSA algorithm](https://www.wikiwand.com/en/State-Action-Reward-State-Action)
+///
+/// If given a replay buffer, every observed transition is stored in it and the agent is
+/// trained on minibatches sampled from it instead. The next action of a replayed transition
+/// is chosen by the agent's current policy.
 #[derive(Debug)]
-pub struct SARSALearner {
+pub struct SARSALearner<S: Space, A: Space> {
 	/// The discount factor
 	gamma: f64,
 	/// The learning rate
 	alpha: f64,
 	/// The time period to train agent on when calling train
 	train_period: TimePeriod,
+	/// Stores transitions for training on minibatches
+	replay: Option<ReplayBuffer<S, A>>,
+	/// The number of transitions in each minibatch
+	batch_size: usize,
 }
 
-impl<T, S: Space, A: Space> OnlineTrainer<S, A, T> for SARSALearner
+impl<T, S: Space, A: Space> OnlineTrainer<S, A, T> for SARSALearner<S, A>
 	where T: QFunction<S, A> + Agent<S, A> {
-	fn train_step(&mut self, agent: &mut T, transition: Transition<S, A>) {
-		let (state, action, reward, next) = transition;
-		
-		let next_action = agent.get_action(&next);
-		let next_val = agent.eval(&next, &next_action);
-		agent.update(&state, &action, reward + self.gamma*next_val, self.alpha);
-	}
-	fn train(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>) {
-		let mut obs = env.reset();
-		let mut time_remaining = self.train_period.clone();
-		while !time_remaining.is_none() {
-			let action = agent.get_action(&obs.state);
-			let new_obs = env.step(&action);
-			self.train_step(agent, (obs.state, action, new_obs.reward, new_obs.state.clone()));
-
-			time_remaining = time_remaining.dec(new_obs.done);
-			obs = if new_obs.done {env.reset()} else {new_obs};
+	fn train_step<R: Rng>(&mut self, agent: &mut T, transition: Transition<S, A>, rng: &mut R) {
+		if let Some(mut replay) = self.replay.take() {
+			replay.push(transition);
+			if replay.len() >= self.batch_size {
+				for (index, weight) in replay.sample(self.batch_size, rng) {
+					let td_error = self.update(agent, replay.get(index).clone(), weight, rng);
+					replay.update_priority(index, td_error);
+				}
+			}
+			self.replay = Some(replay);
+		} else {
+			self.update(agent, transition, 1.0, rng);
 		}
 	}
+	fn train_with<R, O>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R, observer: &mut O)
+		where R: Rng, O: Observer<S, A, T> {
+		let period = self.train_period.clone();
+		train_online(self, agent, env, period, rng, observer);
+	}
 }
 
-impl Default for SARSALearner {
+impl<S: Space, A: Space> Default for SARSALearner<S, A> {
 	/// Creates a new SARSALearner with default values for gamma, alpha, and train_period
-	fn default() -> SARSALearner {
+	fn default() -> SARSALearner<S, A> {
 		SARSALearner {
 			gamma: 0.95,
 			alpha: 0.1,
-			train_period: TimePeriod::EPISODES(100)
+			train_period: TimePeriod::EPISODES(100),
+			replay: None,
+			batch_size: 0
 		}
 	}
 }
 
-impl SARSALearner {
+impl<S: Space, A: Space> SARSALearner<S, A> {
 	/// Returns a new SARSALearner with the given info
-	pub fn new(gamma: f64, alpha: f64, train_period: TimePeriod) -> SARSALearner {
+	pub fn new(gamma: f64, alpha: f64, train_period: TimePeriod) -> SARSALearner<S, A> {
 		SARSALearner {
 			gamma: gamma,
 			alpha: alpha,
-			train_period: train_period
+			train_period: train_period,
+			replay: None,
+			batch_size: 0
 		}
 	}
 	/// Sets gamma field of self
-	pub fn gamma(mut self, gamma: f64) -> SARSALearner {
+	pub fn gamma(mut self, gamma: f64) -> SARSALearner<S, A> {
 		self.gamma = gamma;
 		self
 	}
 	/// Sets alpha field of self
-	pub fn alpha(mut self, alpha: f64) -> SARSALearner {
+	pub fn alpha(mut self, alpha: f64) -> SARSALearner<S, A> {
 		self.alpha = alpha;
 		self
 	}
 	/// Sets train_period field of self
-	pub fn train_period(mut self, train_period: TimePeriod) -> SARSALearner {
+	pub fn train_period(mut self, train_period: TimePeriod) -> SARSALearner<S, A> {
 		self.train_period = train_period;
 		self
 	}
+	/// Trains on minibatches of batch_size transitions sampled from replay
+	pub fn replay(mut self, replay: ReplayBuffer<S, A>, batch_size: usize) -> SARSALearner<S, A> {
+		assert!(batch_size > 0, "batch_size must be positive");
+
+		self.replay = Some(replay);
+		self.batch_size = batch_size;
+		self
+	}
+	/// Returns the replay buffer used by self, if any
+	pub fn get_replay(&self) -> Option<&ReplayBuffer<S, A>> {
+		self.replay.as_ref()
+	}
+
+	// Moves the value of (state, action) towards its target (with a step scaled by weight), returning the TD error
+	fn update<T, R>(&self, agent: &mut T, transition: Transition<S, A>, weight: f64, rng: &mut R) -> f64
+		where T: QFunction<S, A> + Agent<S, A>, R: Rng {
+		let (state, action, reward, next) = transition;
+		
+		let next_action = agent.get_action(&next, rng);
+		let target = reward + self.gamma*agent.eval(&next, &next_action);
+		let td_error = target - agent.eval(&state, &action);
+		agent.update(&state, &action, target, weight*self.alpha);
+		td_error
+	}
 }
\ No newline at end of file
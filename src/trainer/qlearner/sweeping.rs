@@ -0,0 +1,313 @@
+use std::collections::{HashMap, HashSet, BinaryHeap};
+use std::cmp::Ordering;
+use std::hash::Hash;
+use std::f64;
+
+use rand::Rng;
+
+use environment::Environment;
+use environment::Transition;
+use environment::{Space, FiniteSpace};
+use trainer::{OnlineTrainer, Observer, train_online};
+use model::Model;
+use agent::Agent;
+use util::{QFunction, TimePeriod, Stateful};
+use util::checkpoint::CheckpointError;
+
+/// Represents an OnlineTrainer for Q-functions
+/// Uses [prioritized sweeping](http://incompleteideas.net/book/ebook/node98.html)
+///
+/// Like DynaQ, but instead of planning with random (state, action) pairs, pairs are queued by the
+/// size of their Bellman error and planned in that order. Whenever the value of a state changes,
+/// the pairs the model says lead to it are re-prioritized, so changes sweep backwards from where
+/// they happen. Real transitions are only learned from by queueing them, and planning uses the
+/// expected value of each pair under the model.
+///
+/// Setting kappa adds the [Dyna-Q+](http://incompleteideas.net/book/ebook/node101.html) bonus of
+/// kappa*sqrt(steps since a pair was last tried) to planned rewards. Pairs never tried are assumed
+/// to lead back to the same state, and any planning updates left over once the queue empties are
+/// spent on random pairs so the bonuses can grow anywhere.
+#[derive(Debug)]
+pub struct PrioritizedSweeping<S: Space, A: FiniteSpace, M: Model<S, A>> where
+	S::Element: Hash + Eq, A::Element: Hash + Eq {
+	/// All the possible performable actions
+	all_actions: Vec<A::Element>,
+	/// The discount factor
+	gamma: f64,
+	/// The learning rate
+	alpha: f64,
+	/// The time period to train agent on when calling train
+	train_period: TimePeriod,
+	/// The maximum number of planning updates made each train step
+	num_samples: usize,
+	/// Pairs are only queued if their priority is above this
+	theta: f64,
+	/// The size of the exploration bonus (0 disables it)
+	kappa: f64,
+	/// The states the agent has acted in, in the order they were first acted in
+	sources: Vec<S::Element>,
+	/// Members of sources
+	seen_sources: HashSet<S::Element>,
+	/// The (state, action) pairs observed leading to each state
+	predecessors: HashMap<S::Element, Vec<(S::Element, A::Element)>>,
+	/// The time step each pair was last tried at
+	last_visits: HashMap<(S::Element, A::Element), usize>,
+	/// The number of real steps taken so far
+	time: usize,
+	/// Queued pairs, which may be stale if a pair has been requeued or planned since
+	queue: BinaryHeap<Pending<S::Element, A::Element>>,
+	/// The current priority of every queued pair
+	priorities: HashMap<(S::Element, A::Element), f64>,
+	/// The number of pairs ever queued, used to break ties in favor of older pairs
+	num_queued: usize,
+	model: M,
+}
+
+/// The internal state of a PrioritizedSweeping trainer
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct PrioritizedSweepingState<S, A, M> {
+	/// The states the agent has acted in, in the order they were first acted in
+	pub sources: Vec<S>,
+	/// The (state, action) pairs observed leading to each state
+	pub predecessors: Vec<(S, Vec<(S, A)>)>,
+	/// The time step each pair was last tried at
+	pub last_visits: Vec<((S, A), usize)>,
+	/// The number of real steps taken so far
+	pub time: usize,
+	/// The queued pairs and their priorities, from highest to lowest priority
+	pub queue: Vec<((S, A), f64)>,
+	/// State of the learned model
+	pub model: M,
+}
+
+impl<T, S: Space, A: FiniteSpace, M: Model<S, A>> OnlineTrainer<S, A, T> for PrioritizedSweeping<S, A, M>
+	where T: QFunction<S, A> + Agent<S, A>,
+		  S::Element: Hash + Eq,
+		  A::Element: Hash + Eq {
+	fn train_step<R: Rng>(&mut self, agent: &mut T, transition: Transition<S, A>, rng: &mut R) {
+		let (state, action, reward, next) = transition.clone();
+
+		self.model.update(transition);
+		self.observe(&state, &action, &next);
+
+		let priority = (reward + self.gamma*self.max_val(agent, &next) - agent.eval(&state, &action)).abs();
+		self.enqueue(state, action, priority);
+
+		for _ in 0..self.num_samples {
+			let (s, a) = match self.dequeue() {
+				Some(pair) => pair,
+				None if self.kappa > 0.0 => {
+					let s = self.sources[rng.gen_range(0, self.sources.len())].clone();
+					let a = self.all_actions[rng.gen_range(0, self.all_actions.len())].clone();
+					(s, a)
+				},
+				None => break
+			};
+
+			let target = self.expected_target(agent, &s, &a);
+			agent.update(&s, &a, target, self.alpha);
+
+			let preds = self.predecessors.get(&s).cloned().unwrap_or(Vec::new());
+			for (s0, a0) in preds {
+				if self.model.transition(&s0, &a0, &s) > 0.0 {
+					let priority = (self.expected_target(agent, &s0, &a0) - agent.eval(&s0, &a0)).abs();
+					self.enqueue(s0, a0, priority);
+				}
+			}
+		}
+	}
+	fn train_with<R, O>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R, observer: &mut O)
+		where R: Rng, O: Observer<S, A, T> {
+		let period = self.train_period.clone();
+		train_online(self, agent, env, period, rng, observer);
+	}
+}
+
+impl<S: Space, A: FiniteSpace, M> Stateful for PrioritizedSweeping<S, A, M>
+	where M: Model<S, A> + Stateful,
+		  S::Element: Hash + Eq,
+		  A::Element: Hash + Eq {
+	type State = PrioritizedSweepingState<S::Element, A::Element, M::State>;
+
+	fn get_state(&self) -> Self::State {
+		let queue = self.queue.clone().into_sorted_vec().into_iter().rev()
+								  .filter(|p| self.priorities.get(&p.pair) == Some(&p.priority))
+								  .map(|p| (p.pair, p.priority))
+								  .collect();
+		PrioritizedSweepingState {
+			sources: self.sources.clone(),
+			predecessors: self.predecessors.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+			last_visits: self.last_visits.iter().map(|(k, &t)| (k.clone(), t)).collect(),
+			time: self.time,
+			queue: queue,
+			model: self.model.get_state()
+		}
+	}
+	fn set_state(&mut self, state: Self::State) -> Result<(), CheckpointError> {
+		self.model.set_state(state.model)?;
+		self.seen_sources = state.sources.iter().cloned().collect();
+		self.sources = state.sources;
+		self.predecessors = state.predecessors.into_iter().collect();
+		self.last_visits = state.last_visits.into_iter().collect();
+		self.time = state.time;
+
+		self.queue.clear();
+		self.priorities.clear();
+		self.num_queued = 0;
+		for ((s, a), priority) in state.queue {
+			self.enqueue(s, a, priority);
+		}
+		Ok(())
+	}
+}
+
+impl<S: Space, A: FiniteSpace, M: Model<S, A>> PrioritizedSweeping<S, A, M>
+	where S::Element: Hash + Eq, A::Element: Hash + Eq {
+	/// Returns a new PrioritizedSweeping with the given parameters and no exploration bonus
+	pub fn new(action_space: A, gamma: f64, alpha: f64, train_period: TimePeriod,
+				num_samples: usize, model: M) -> PrioritizedSweeping<S, A, M> {
+		PrioritizedSweeping {
+			all_actions: action_space.enumerate(),
+			gamma: gamma,
+			alpha: alpha,
+			train_period: train_period,
+			num_samples: num_samples,
+			theta: 1e-4,
+			kappa: 0.0,
+			sources: Vec::new(),
+			seen_sources: HashSet::new(),
+			predecessors: HashMap::new(),
+			last_visits: HashMap::new(),
+			time: 0,
+			queue: BinaryHeap::new(),
+			priorities: HashMap::new(),
+			num_queued: 0,
+			model: model
+		}
+	}
+	/// Creates a new PrioritizedSweeping with default parameters
+	pub fn default(action_space: A, model: M) -> PrioritizedSweeping<S, A, M> {
+		PrioritizedSweeping::new(action_space, 0.95, 0.1, TimePeriod::EPISODES(30), 10, model)
+	}
+	/// Sets gamma field of self
+	pub fn gamma(mut self, gamma: f64) -> PrioritizedSweeping<S, A, M> {
+		self.gamma = gamma;
+		self
+	}
+	/// Sets alpha field of self
+	pub fn alpha(mut self, alpha: f64) -> PrioritizedSweeping<S, A, M> {
+		self.alpha = alpha;
+		self
+	}
+	/// Sets train_period field of self
+	pub fn train_period(mut self, train_period: TimePeriod) -> PrioritizedSweeping<S, A, M> {
+		self.train_period = train_period;
+		self
+	}
+	/// Sets num_samples field of self
+	pub fn num_samples(mut self, num_samples: usize) -> PrioritizedSweeping<S, A, M> {
+		self.num_samples = num_samples;
+		self
+	}
+	/// Sets theta field of self
+	pub fn theta(mut self, theta: f64) -> PrioritizedSweeping<S, A, M> {
+		assert!(theta >= 0.0, "theta must be nonnegative");
+
+		self.theta = theta;
+		self
+	}
+	/// Sets kappa field of self
+	pub fn kappa(mut self, kappa: f64) -> PrioritizedSweeping<S, A, M> {
+		assert!(kappa >= 0.0, "kappa must be nonnegative");
+
+		self.kappa = kappa;
+		self
+	}
+	/// Returns the model learned by self
+	pub fn get_model(&self) -> &M {
+		&self.model
+	}
+
+	fn observe(&mut self, state: &S::Element, action: &A::Element, next: &S::Element) {
+		if self.seen_sources.insert(state.clone()) {
+			self.sources.push(state.clone());
+		}
+
+		let preds = self.predecessors.entry(next.clone()).or_insert(Vec::new());
+		if !preds.iter().any(|&(ref s, ref a)| s == state && a == action) {
+			preds.push((state.clone(), action.clone()));
+		}
+
+		self.time += 1;
+		self.last_visits.insert((state.clone(), action.clone()), self.time);
+	}
+	fn enqueue(&mut self, state: S::Element, action: A::Element, priority: f64) {
+		if priority <= self.theta {
+			return;
+		}
+
+		let pair = (state, action);
+		if self.priorities.get(&pair).map_or(true, |&p| p < priority) {
+			self.priorities.insert(pair.clone(), priority);
+			self.queue.push(Pending {priority: priority, order: self.num_queued, pair: pair});
+			self.num_queued += 1;
+		}
+	}
+	fn dequeue(&mut self) -> Option<(S::Element, A::Element)> {
+		while let Some(pending) = self.queue.pop() {
+			if self.priorities.get(&pending.pair) == Some(&pending.priority) {
+				self.priorities.remove(&pending.pair);
+				return Some(pending.pair);
+			}
+		}
+		None
+	}
+	fn max_val<T: QFunction<S, A>>(&self, agent: &T, state: &S::Element) -> f64 {
+		self.all_actions.iter().fold(f64::MIN, |max, a| max.max(agent.eval(state, a)))
+	}
+	// The expected reward (plus bonus) and discounted next value of performing action in state
+	fn expected_target<T: QFunction<S, A>>(&self, agent: &T, state: &S::Element, action: &A::Element) -> f64 {
+		let (total, target) = self.model.successors(state, action).into_iter().fold((0.0, 0.0), |(total, target), (s1, p)| {
+			(total + p, target + p*(self.model.reward(state, action, &s1) + self.gamma*self.max_val(agent, &s1)))
+		});
+		let target = if total > 0.0 {target/total} else {self.gamma*self.max_val(agent, state)};
+
+		if self.kappa > 0.0 {
+			let last = self.last_visits.get(&(state.clone(), action.clone())).cloned().unwrap_or(0);
+			target + self.kappa*((self.time - last) as f64).sqrt()
+		} else {
+			target
+		}
+	}
+}
+
+// A (state, action) pair waiting to be planned
+#[derive(Debug, Clone)]
+struct Pending<S, A> {
+	priority: f64,
+	order: usize,
+	pair: (S, A),
+}
+
+impl<S, A> PartialEq for Pending<S, A> {
+	fn eq(&self, other: &Self) -> bool {
+		self.cmp(other) == Ordering::Equal
+	}
+}
+
+impl<S, A> Eq for Pending<S, A> {}
+
+impl<S, A> PartialOrd for Pending<S, A> {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl<S, A> Ord for Pending<S, A> {
+	// Higher priorities first, then pairs queued earlier
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.priority.partial_cmp(&other.priority).unwrap_or(Ordering::Equal)
+			.then_with(|| other.order.cmp(&self.order))
+	}
+}
\ No newline at end of file
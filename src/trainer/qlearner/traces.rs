@@ -0,0 +1,214 @@
+use std::f64;
+
+use rand::Rng;
+
+use environment::Environment;
+use environment::Transition;
+use environment::{Space, FiniteSpace};
+use trainer::{OnlineTrainer, Observer, train_online};
+use agent::Agent;
+use util::{TracedQFunction, TraceType, TimePeriod};
+
+/// Represents an OnlineTrainer for Q-functions
+/// Uses the [SARSA(lambda) algorithm](http://incompleteideas.net/book/ebook/node77.html)
+///
+/// Every TD error updates all recently visited (state, action) pairs, weighted by their
+/// eligibility traces, which decay by gamma*lambda each step. Traces are cleared between episodes.
+/// Like SARSALearner, the next action is chosen by the agent's current policy.
+#[derive(Debug)]
+pub struct SARSALambda<E> {
+	/// The discount factor
+	gamma: f64,
+	/// The learning rate
+	alpha: f64,
+	/// How much credit earlier pairs receive, from 0 (one-step SARSA) to 1 (Monte Carlo)
+	lambda: f64,
+	/// How traces grow when a pair is visited
+	trace_type: TraceType,
+	/// The time period to train agent on when calling train
+	train_period: TimePeriod,
+	/// The eligibility traces of the current episode
+	trace: Option<E>,
+}
+
+impl<T, S: Space, A: Space> OnlineTrainer<S, A, T> for SARSALambda<T::Trace>
+	where T: TracedQFunction<S, A> + Agent<S, A> {
+	fn train_step<R: Rng>(&mut self, agent: &mut T, transition: Transition<S, A>, rng: &mut R) {
+		let (state, action, reward, next) = transition;
+
+		let mut trace = self.trace.take().unwrap_or_else(|| agent.new_trace());
+		agent.decay_trace(&mut trace, self.gamma*self.lambda);
+		agent.visit(&mut trace, &state, &action, self.trace_type, self.alpha);
+
+		let next_action = agent.get_action(&next, rng);
+		let td_error = reward + self.gamma*agent.eval(&next, &next_action) - agent.eval(&state, &action);
+		agent.update_traced(&trace, self.alpha*td_error);
+
+		self.trace = Some(trace);
+	}
+	fn finish_episode<R: Rng>(&mut self, _: &mut T, _: bool, _: &mut R) {
+		self.trace = None;
+	}
+	fn train_with<R, O>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R, observer: &mut O)
+		where R: Rng, O: Observer<S, A, T> {
+		let period = self.train_period.clone();
+		train_online(self, agent, env, period, rng, observer);
+	}
+}
+
+impl<E> Default for SARSALambda<E> {
+	/// Creates a new SARSALambda with default values for gamma, alpha, lambda, trace_type (accumulating), and train_period
+	fn default() -> SARSALambda<E> {
+		SARSALambda::new(0.95, 0.1, 0.9, TimePeriod::EPISODES(100))
+	}
+}
+
+impl<E> SARSALambda<E> {
+	/// Returns a new SARSALambda using accumulating traces and the given info
+	pub fn new(gamma: f64, alpha: f64, lambda: f64, train_period: TimePeriod) -> SARSALambda<E> {
+		assert!(0.0 <= lambda && lambda <= 1.0, "lambda must be between 0 and 1");
+
+		SARSALambda {
+			gamma: gamma,
+			alpha: alpha,
+			lambda: lambda,
+			trace_type: TraceType::Accumulating,
+			train_period: train_period,
+			trace: None
+		}
+	}
+	/// Sets gamma field of self
+	pub fn gamma(mut self, gamma: f64) -> SARSALambda<E> {
+		self.gamma = gamma;
+		self
+	}
+	/// Sets alpha field of self
+	pub fn alpha(mut self, alpha: f64) -> SARSALambda<E> {
+		self.alpha = alpha;
+		self
+	}
+	/// Sets lambda field of self
+	pub fn lambda(mut self, lambda: f64) -> SARSALambda<E> {
+		assert!(0.0 <= lambda && lambda <= 1.0, "lambda must be between 0 and 1");
+
+		self.lambda = lambda;
+		self
+	}
+	/// Sets trace_type field of self
+	pub fn trace_type(mut self, trace_type: TraceType) -> SARSALambda<E> {
+		self.trace_type = trace_type;
+		self
+	}
+	/// Sets train_period field of self
+	pub fn train_period(mut self, train_period: TimePeriod) -> SARSALambda<E> {
+		self.train_period = train_period;
+		self
+	}
+}
+
+/// Represents an OnlineTrainer for Q-functions
+/// Uses [Watkins's Q(lambda) algorithm](http://incompleteideas.net/book/ebook/node78.html)
+///
+/// Like SARSALambda, but bootstraps from the best next action like QLearner. Since the TD errors
+/// only describe the greedy policy, traces are cleared whenever the agent takes an exploratory
+/// (non-greedy) action.
+#[derive(Debug)]
+pub struct WatkinsQLambda<A: FiniteSpace, E> {
+	/// The action space used by the agent
+	action_space: A,
+	/// The discount factor
+	gamma: f64,
+	/// The learning rate
+	alpha: f64,
+	/// How much credit earlier pairs receive, from 0 (one-step Q-learning) to 1
+	lambda: f64,
+	/// How traces grow when a pair is visited
+	trace_type: TraceType,
+	/// The time period to train agent on when calling train
+	train_period: TimePeriod,
+	/// The eligibility traces of the current episode
+	trace: Option<E>,
+}
+
+impl<T, S: Space, A: FiniteSpace> OnlineTrainer<S, A, T> for WatkinsQLambda<A, T::Trace>
+	where T: TracedQFunction<S, A> + Agent<S, A> {
+	fn train_step<R: Rng>(&mut self, agent: &mut T, transition: Transition<S, A>, _: &mut R) {
+		let (state, action, reward, next) = transition;
+
+		let mut trace = match self.trace.take() {
+			Some(mut trace) => if agent.eval(&state, &action) < self.max_val(agent, &state) {
+				agent.new_trace()
+			} else {
+				agent.decay_trace(&mut trace, self.gamma*self.lambda);
+				trace
+			},
+			None => agent.new_trace()
+		};
+		agent.visit(&mut trace, &state, &action, self.trace_type, self.alpha);
+
+		let td_error = reward + self.gamma*self.max_val(agent, &next) - agent.eval(&state, &action);
+		agent.update_traced(&trace, self.alpha*td_error);
+
+		self.trace = Some(trace);
+	}
+	fn finish_episode<R: Rng>(&mut self, _: &mut T, _: bool, _: &mut R) {
+		self.trace = None;
+	}
+	fn train_with<R, O>(&mut self, agent: &mut T, env: &mut Environment<State=S, Action=A>, rng: &mut R, observer: &mut O)
+		where R: Rng, O: Observer<S, A, T> {
+		let period = self.train_period.clone();
+		train_online(self, agent, env, period, rng, observer);
+	}
+}
+
+impl<A: FiniteSpace, E> WatkinsQLambda<A, E> {
+	/// Returns a new WatkinsQLambda using accumulating traces and the given info
+	pub fn new(action_space: A, gamma: f64, alpha: f64, lambda: f64, train_period: TimePeriod) -> WatkinsQLambda<A, E> {
+		assert!(0.0 <= lambda && lambda <= 1.0, "lambda must be between 0 and 1");
+
+		WatkinsQLambda {
+			action_space: action_space,
+			gamma: gamma,
+			alpha: alpha,
+			lambda: lambda,
+			trace_type: TraceType::Accumulating,
+			train_period: train_period,
+			trace: None
+		}
+	}
+	/// Creates a new WatkinsQLambda with default gamma, alpha, lambda, trace_type (accumulating), and train_period
+	pub fn default(action_space: A) -> WatkinsQLambda<A, E> {
+		WatkinsQLambda::new(action_space, 0.95, 0.1, 0.9, TimePeriod::EPISODES(100))
+	}
+	/// Sets gamma field of self
+	pub fn gamma(mut self, gamma: f64) -> WatkinsQLambda<A, E> {
+		self.gamma = gamma;
+		self
+	}
+	/// Sets alpha field of self
+	pub fn alpha(mut self, alpha: f64) -> WatkinsQLambda<A, E> {
+		self.alpha = alpha;
+		self
+	}
+	/// Sets lambda field of self
+	pub fn lambda(mut self, lambda: f64) -> WatkinsQLambda<A, E> {
+		assert!(0.0 <= lambda && lambda <= 1.0, "lambda must be between 0 and 1");
+
+		self.lambda = lambda;
+		self
+	}
+	/// Sets trace_type field of self
+	pub fn trace_type(mut self, trace_type: TraceType) -> WatkinsQLambda<A, E> {
+		self.trace_type = trace_type;
+		self
+	}
+	/// Sets train_period field of self
+	pub fn train_period(mut self, train_period: TimePeriod) -> WatkinsQLambda<A, E> {
+		self.train_period = train_period;
+		self
+	}
+
+	fn max_val<S: Space, T: TracedQFunction<S, A>>(&self, agent: &T, state: &S::Element) -> f64 {
+		self.action_space.enumerate().iter().fold(f64::MIN, |max, a| max.max(agent.eval(state, a)))
+	}
+}
\ No newline at end of file
@@ -4,18 +4,51 @@ use std::collections::HashMap;
 use std::hash::Hash;
 use std::fmt::Debug;
 
-use rand::{Rng, thread_rng};
+use rand::Rng;
 
 use num::Float;
 use num::cast::NumCast;
 
+use rulinalg::matrix::{Matrix, BaseMatrix};
+use rulinalg::vector::Vector;
+
 use environment::{Space, FiniteSpace};
 
 use agent::Agent;
 
-use util::{VFunction, QFunction};
+use util::{VFunction, QFunction, TracedQFunction, FittableQFunction, TraceType};
 use util::{Feature, FeatureExtractor};
 use util::{ParameterizedFunc, DifferentiableFunc, DifferentiableVecFunc};
+use util::{Stateful, describe_features, check_features};
+use util::checkpoint::CheckpointError;
+
+// Added to the diagonal of least squares systems so they can always be solved
+const FIT_RIDGE: f64 = 1e-8;
+// The largest ridge tried before a least squares fit is given up on
+const MAX_FIT_RIDGE: f64 = 1.0;
+
+/// The learned state of a VLinear
+///
+/// Features can not be saved, so they are only recorded (as their Debug output) and
+/// checked against the features of the function the state is loaded into
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct VLinearState<F> {
+	/// Descriptions of the features used by the function
+	pub features: Vec<String>,
+	/// The weights of the function (bias first)
+	pub weights: Vec<F>,
+}
+
+/// The learned state of a QLinear
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct QLinearState<F, E> {
+	/// Descriptions of the features shared by every action
+	pub features: Vec<String>,
+	/// The weights used for each action (bias first)
+	pub weights: Vec<(E, Vec<F>)>,
+}
 
 /// Represents a linear function approximator
 /// f(x) = w^T g(x) + b
@@ -44,7 +77,7 @@ impl<F: Float + Debug, S: Space> VFunction<S> for VLinear<F, S> {
 		};
 		let lr = NumCast::from(alpha*cost_grad).unwrap();
 		for (i, feat) in self.features.iter().enumerate() {
-			self.weights[i+1] = self.weights[0] - lr*feat.extract(&state);
+			self.weights[i+1] = self.weights[i+1] - lr*feat.extract(&state);
 		}
 		self.weights[0] = self.weights[0] - lr;
 	}
@@ -62,6 +95,26 @@ impl<F: Float + Debug, S: Space> ParameterizedFunc<F> for VLinear<F, S> {
 	}
 }
 
+impl<F: Float + Debug, S: Space> Stateful for VLinear<F, S> {
+	type State = VLinearState<F>;
+
+	fn get_state(&self) -> VLinearState<F> {
+		VLinearState {
+			features: describe_features(&self.features),
+			weights: self.weights.clone()
+		}
+	}
+	fn set_state(&mut self, state: VLinearState<F>) -> Result<(), CheckpointError> {
+		check_features(&self.features, &state.features)?;
+		if state.weights.len() != self.features.len() + 1 {
+			return Err(CheckpointError::Mismatch(format!("expected {} weights but found {}", 
+														 self.features.len() + 1, state.weights.len())));
+		}
+		self.weights = state.weights;
+		Ok(())
+	}
+}
+
 impl<S: Space, A: Space, F: Float + Debug> FeatureExtractor<S, A, F> for VLinear<F, S> {
 	fn num_features(&self) -> usize {
 		// One feature is constant 1
@@ -109,7 +162,7 @@ impl<F: Float + Debug, S: Space> DifferentiableVecFunc<S, F> for VLinear<F, S> {
 // Not sure how I feel about letting functions be agents...
 impl<F: Float + Debug, S: Space, A: Space> Agent<S, A> for VLinear<F, S>
 	where A::Element: Float {
-	fn get_action(&self, state: &S::Element) -> A::Element {
+	fn get_action<R: Rng>(&self, state: &S::Element, _: &mut R) -> A::Element {
 		NumCast::from(self.eval(state)).unwrap()
 	}
 }
@@ -117,36 +170,29 @@ impl<F: Float + Debug, S: Space, A: Space> Agent<S, A> for VLinear<F, S>
 impl<S: Space> Default for VLinear<f64, S> {
 	/// Creates a new Linear V-Function Approximator
 	fn default() -> VLinear<f64, S> {
-		let mut rng = thread_rng();
-		VLinear {
-			features: vec![],
-			weights: vec![rng.gen_range(-10.0, 10.0)]
-		}
+		VLinear::new()
 	}
 }
 
 impl<F: Float + Debug, S: Space> VLinear<F, S> {
-	/// Creates a new Linear V-Function Approximator
+	/// Creates a new Linear V-Function Approximator whose weights are all 0
 	pub fn new() -> VLinear<F, S> {
-		let mut rng = thread_rng();
 		VLinear {
 			features: vec![],
-			weights: vec![NumCast::from(rng.gen_range(-10.0, 10.0)).unwrap()]
+			weights: vec![F::zero()]
 		}
 	}
-	/// Creates a new Linear V-Function Approximator with the given features
+	/// Creates a new Linear V-Function Approximator with the given features, whose weights are all 0
 	pub fn with_features(feats: Vec<Box<Feature<S, F>>>) -> VLinear<F, S> {
-		let mut rng = thread_rng();
 		let num_feats = feats.len();
 		VLinear {
 			features: feats,
-			weights: (0..num_feats+1).map(|_| NumCast::from(rng.gen_range(-10.0, 10.0)).unwrap()).collect()
+			weights: vec![F::zero(); num_feats+1]
 		}
 	}
-	/// Adds the specified feature to the end of the feature vector, giving it a random weight
+	/// Adds the specified feature to the end of the feature vector, giving it a weight of 0
 	pub fn add_feature(mut self, feature: Box<Feature<S, F>>) -> VLinear<F, S> {
-		let mut rng = thread_rng();
-		self.weights.push(NumCast::from(rng.gen_range(-10.0, 10.0)).unwrap());
+		self.weights.push(F::zero());
 		self.features.push(feature);
 		self
 	}
@@ -206,6 +252,130 @@ impl<F: Float + Debug, S: Space, A: FiniteSpace> ParameterizedFunc<F> for QLinea
 	}
 }
 
+impl<F: Float + Debug + 'static, S: Space, A: FiniteSpace> FittableQFunction<S, A> for QLinear<F, S, A>
+	where A::Element: Hash + Eq {
+	/// Sets the weights of every action in data to the least squares fit of its targets.
+	/// Other actions keep their weights. Panics if a fit can not be solved even with a large ridge
+	fn fit<R: Rng>(&mut self, data: &[(S::Element, A::Element, f64)], _: &mut R) {
+		let num_weights = self.features.len() + 1;
+		for a in self.actions.clone() {
+			let mut inputs = Vec::new();
+			let mut targets = Vec::new();
+			for &(ref s, _, target) in data.iter().filter(|d| d.1 == a) {
+				inputs.push(F::one());
+				inputs.extend(self.features.iter().map(|feat| feat.extract(s)));
+				targets.push(NumCast::from(target).unwrap());
+			}
+			if targets.is_empty() {
+				continue;
+			}
+
+			let num: F = NumCast::from(targets.len()).unwrap();
+			let inputs = Matrix::new(targets.len(), num_weights, inputs);
+			let inputs_t = inputs.transpose();
+
+			// The weights w should satisfy (X^T X + ridge*I) w = X^T y
+			// The ridge is grown until the system can be solved
+			let gram = &(&inputs_t * &inputs) / num;
+			let vec = &(&inputs_t * Vector::new(targets)) / num;
+			let mut ridge = FIT_RIDGE;
+			let weights = loop {
+				let diag: F = NumCast::from(ridge).unwrap();
+				let mat = gram.clone() + Matrix::identity(num_weights) * diag;
+				match mat.solve(vec.clone()) {
+					Ok(weights) => break weights,
+					Err(_) if ridge < MAX_FIT_RIDGE => ridge *= 10.0,
+					Err(e) => panic!("could not fit the weights of {:?}: {}", a, e),
+				}
+			};
+			self.get_func(&a).weights = weights.into_vec();
+		}
+	}
+}
+
+impl<F: Float + Debug, S: Space, A: FiniteSpace> TracedQFunction<S, A> for QLinear<F, S, A>
+	where A::Element: Hash + Eq {
+	/// The eligibility of each parameter
+	type Trace = Vec<F>;
+
+	fn new_trace(&self) -> Vec<F> {
+		vec![F::zero(); self.num_params()]
+	}
+	fn decay_trace(&self, trace: &mut Vec<F>, factor: f64) {
+		let factor: F = NumCast::from(factor).unwrap();
+		for e in trace.iter_mut() {
+			*e = *e * factor;
+		}
+	}
+	fn visit(&self, trace: &mut Vec<F>, state: &S::Element, action: &A::Element, trace_type: TraceType, alpha: f64) {
+		// The features of (state, action) are the gradient of its value
+		let feats = self.extract(state, action);
+		match trace_type {
+			TraceType::Accumulating => for i in 0..feats.len() {
+				trace[i] = trace[i] + feats[i];
+			},
+			TraceType::Replacing => for i in 0..feats.len() {
+				if feats[i] != F::zero() {
+					trace[i] = feats[i];
+				}
+			},
+			TraceType::Dutch => {
+				let alpha: F = NumCast::from(alpha).unwrap();
+				let dot = (0..feats.len()).fold(F::zero(), |sum, i| sum + trace[i]*feats[i]);
+				let coeff = F::one() - alpha*dot;
+				for i in 0..feats.len() {
+					trace[i] = trace[i] + coeff*feats[i];
+				}
+			}
+		}
+	}
+	fn update_traced(&mut self, trace: &Vec<F>, step: f64) {
+		let step: F = NumCast::from(step).unwrap();
+		let mut params = self.get_params();
+		for i in 0..params.len() {
+			params[i] = params[i] + step*trace[i];
+		}
+		self.set_params(params);
+	}
+}
+
+impl<F: Float + Debug, S: Space, A: FiniteSpace> Stateful for QLinear<F, S, A>
+	where A::Element: Hash + Eq {
+	type State = QLinearState<F, A::Element>;
+
+	fn get_state(&self) -> QLinearState<F, A::Element> {
+		let weights = self.actions.iter()
+								  .filter(|a| self.functions.contains_key(a))
+								  .map(|a| (a.clone(), self.functions[a].weights.clone()))
+								  .collect();
+		QLinearState {
+			features: describe_features(&self.features),
+			weights: weights
+		}
+	}
+	fn set_state(&mut self, state: QLinearState<F, A::Element>) -> Result<(), CheckpointError> {
+		check_features(&self.features, &state.features)?;
+		let num_weights = self.features.len() + 1;
+		for &(ref a, ref weights) in &state.weights {
+			if !self.indices.contains_key(a) {
+				return Err(CheckpointError::Mismatch(format!("unknown action {:?}", a)));
+			} else if weights.len() != num_weights {
+				return Err(CheckpointError::Mismatch(format!("expected {} weights for action {:?} but found {}", 
+															 num_weights, a, weights.len())));
+			}
+		}
+
+		self.functions.clear();
+		for (a, weights) in state.weights {
+			self.functions.insert(a, VLinear {
+				features: self.features.clone(),
+				weights: weights
+			});
+		}
+		Ok(())
+	}
+}
+
 impl<S: Space, A: FiniteSpace, F: Float + Debug> FeatureExtractor<S, A, F> for QLinear<F, S, A> 
 	where A::Element: Hash + Eq {
 	fn num_features(&self) -> usize {
@@ -226,6 +396,17 @@ impl<S: Space, A: FiniteSpace, F: Float + Debug> FeatureExtractor<S, A, F> for Q
 		feats.extend_from_slice(&vec![F::zero(); (self.actions.len()-index-1)*(self.features.len() + 1)]);
 		feats
 	}
+	fn extract_sparse(&self, state: &S::Element, action: &A::Element) -> Vec<(usize, F)> {
+		// Only the block of features belonging to action can be nonzero
+		let start = self.indices[action]*(self.features.len() + 1);
+		let mut feats = Vec::with_capacity(self.features.len() + 1);
+
+		feats.push((start, F::one()));
+		for (i, feat) in self.features.iter().enumerate() {
+			feats.push((start + i + 1, NumCast::from(feat.extract(state)).unwrap()));
+		}
+		feats
+	}
 }
 
 impl<S: Space, A: FiniteSpace, F: Float + Debug> DifferentiableFunc<S, A, F> for QLinear<F, S, A> 
@@ -266,7 +447,14 @@ impl<F: Float + Debug, S: Space, A: FiniteSpace> QLinear<F, S, A> where A::Eleme
 		self.features.push(feat);
 	}
 	/// Returns a mutable reference to the function for the corresponding action
+	// New functions start with zero weights so they agree with what eval and get_params
+	// already report for unseen actions (and so training doesn't consume hidden randomness)
 	fn get_func(&mut self, action: &A::Element) -> &mut VLinear<F, S> {
-		self.functions.entry(action.clone()).or_insert(VLinear::with_features(self.features.clone()))
+		let num_weights = self.features.len() + 1;
+		let features = &self.features;
+		self.functions.entry(action.clone()).or_insert_with(|| VLinear {
+			features: features.clone(),
+			weights: vec![F::zero(); num_weights]
+		})
 	}
 }
\ No newline at end of file
@@ -0,0 +1,210 @@
+//! Baseline Module
+//!
+//! Ways of weighting returns before they are used by PolicyGradient
+
+use environment::Space;
+
+use util::{Baseline, VFunction, Stateful};
+use util::checkpoint::CheckpointError;
+
+use stat::normalize;
+
+/// Leaves returns as they are
+#[derive(Clone, Copy, Debug)]
+pub struct NoBaseline;
+
+impl<S: Space> Baseline<S> for NoBaseline {
+	fn apply(&mut self, _: &[S::Element], _: &[usize], returns: Vec<f64>) -> Vec<f64> {
+		returns
+	}
+}
+
+impl Stateful for NoBaseline {
+	/// NoBaseline keeps no state
+	type State = ();
+
+	fn get_state(&self) {}
+	fn set_state(&mut self, _: ()) -> Result<(), CheckpointError> {
+		Ok(())
+	}
+}
+
+/// Normalizes each batch of returns to have mean 0 and variance 1
+#[derive(Clone, Copy, Debug)]
+pub struct Normalize;
+
+impl<S: Space> Baseline<S> for Normalize {
+	fn apply(&mut self, _: &[S::Element], _: &[usize], mut returns: Vec<f64>) -> Vec<f64> {
+		normalize(&mut returns);
+		returns
+	}
+}
+
+impl Stateful for Normalize {
+	/// Normalize keeps no state
+	type State = ();
+
+	fn get_state(&self) {}
+	fn set_state(&mut self, _: ()) -> Result<(), CheckpointError> {
+		Ok(())
+	}
+}
+
+/// Subtracts the mean of every return seen so far
+#[derive(Clone, Debug, Default)]
+pub struct RunningMean {
+	mean: f64,
+	count: usize,
+}
+
+impl<S: Space> Baseline<S> for RunningMean {
+	fn apply(&mut self, _: &[S::Element], _: &[usize], returns: Vec<f64>) -> Vec<f64> {
+		let adjusted = returns.iter().map(|r| r - self.mean).collect();
+		for r in returns {
+			self.count += 1;
+			self.mean += (r - self.mean)/self.count as f64;
+		}
+		adjusted
+	}
+}
+
+impl Stateful for RunningMean {
+	/// The mean return and the number of returns it was calculated from
+	type State = (f64, usize);
+
+	fn get_state(&self) -> (f64, usize) {
+		(self.mean, self.count)
+	}
+	fn set_state(&mut self, state: (f64, usize)) -> Result<(), CheckpointError> {
+		self.mean = state.0;
+		self.count = state.1;
+		Ok(())
+	}
+}
+
+impl RunningMean {
+	/// Creates a new RunningMean that has not seen any returns
+	pub fn new() -> RunningMean {
+		RunningMean::default()
+	}
+}
+
+/// Subtracts the mean of every return seen so far at the same step of an episode
+#[derive(Clone, Debug, Default)]
+pub struct TimeAverage {
+	/// The mean return and number of returns seen at each time step
+	means: Vec<(f64, usize)>,
+}
+
+impl<S: Space> Baseline<S> for TimeAverage {
+	fn apply(&mut self, _: &[S::Element], times: &[usize], returns: Vec<f64>) -> Vec<f64> {
+		let adjusted = returns.iter().zip(times).map(|(r, &t)| {
+			r - self.means.get(t).map_or(0.0, |m| m.0)
+		}).collect();
+
+		for (r, &t) in returns.into_iter().zip(times) {
+			if t >= self.means.len() {
+				self.means.resize(t + 1, (0.0, 0));
+			}
+
+			let (ref mut mean, ref mut count) = self.means[t];
+			*count += 1;
+			*mean += (r - *mean)/(*count as f64);
+		}
+		adjusted
+	}
+}
+
+impl Stateful for TimeAverage {
+	/// The mean return and number of returns seen at each time step
+	type State = Vec<(f64, usize)>;
+
+	fn get_state(&self) -> Vec<(f64, usize)> {
+		self.means.clone()
+	}
+	fn set_state(&mut self, state: Vec<(f64, usize)>) -> Result<(), CheckpointError> {
+		self.means = state;
+		Ok(())
+	}
+}
+
+impl TimeAverage {
+	/// Creates a new TimeAverage that has not seen any returns
+	pub fn new() -> TimeAverage {
+		TimeAverage::default()
+	}
+}
+
+/// Subtracts the value of each state according to a learned value function
+///
+/// The value function is trained towards the observed returns
+#[derive(Clone, Debug)]
+pub struct LearnedBaseline<V> {
+	/// The value function estimating returns
+	pub v_func: V,
+	/// The learning rate of v_func
+	alpha: f64,
+}
+
+impl<S: Space, V: VFunction<S>> Baseline<S> for LearnedBaseline<V> {
+	fn apply(&mut self, states: &[S::Element], _: &[usize], returns: Vec<f64>) -> Vec<f64> {
+		let adjusted = returns.iter().zip(states).map(|(r, s)| r - self.v_func.eval(s)).collect();
+		for (r, s) in returns.into_iter().zip(states) {
+			self.v_func.update(s, r, self.alpha);
+		}
+		adjusted
+	}
+}
+
+impl<V: Stateful> Stateful for LearnedBaseline<V> {
+	/// The state of the value function
+	type State = V::State;
+
+	fn get_state(&self) -> V::State {
+		self.v_func.get_state()
+	}
+	fn set_state(&mut self, state: V::State) -> Result<(), CheckpointError> {
+		self.v_func.set_state(state)
+	}
+}
+
+impl<V> LearnedBaseline<V> {
+	/// Creates a new LearnedBaseline using the given value function and learning rate
+	pub fn new(v_func: V, alpha: f64) -> LearnedBaseline<V> {
+		LearnedBaseline {
+			v_func: v_func,
+			alpha: alpha
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use environment::Range;
+	use util::Baseline;
+
+	use super::*;
+
+	#[test]
+	fn running_mean_subtracts_previous_returns() {
+		let mut baseline = RunningMean::new();
+		let states = vec![0.0; 2];
+
+		let first = Baseline::<Range>::apply(&mut baseline, &states, &[0, 1], vec![1.0, 3.0]);
+		assert_eq!(first, vec![1.0, 3.0]);
+
+		let second = Baseline::<Range>::apply(&mut baseline, &states, &[0, 1], vec![2.0, 4.0]);
+		assert_eq!(second, vec![0.0, 2.0]);
+	}
+
+	#[test]
+	fn time_average_separates_steps() {
+		let mut baseline = TimeAverage::new();
+		let states = vec![0.0; 3];
+
+		Baseline::<Range>::apply(&mut baseline, &states, &[0, 1, 0], vec![2.0, 1.0, 4.0]);
+		let adjusted = Baseline::<Range>::apply(&mut baseline, &states, &[0, 1, 2], vec![3.0, 3.0, 3.0]);
+		assert_eq!(adjusted, vec![0.0, 2.0, 3.0]);
+		assert_eq!(baseline.get_state(), vec![(3.0, 3), (2.0, 2), (3.0, 1)]);
+	}
+}
\ No newline at end of file
@@ -0,0 +1,130 @@
+//! Checkpoint Module
+//!
+//! Saves and loads the state of `Stateful` values. Reading and writing checkpoints
+//! requires the `serialize` feature.
+//!
+//! A checkpoint is a single header line, `renforce-checkpoint <version>`, followed by
+//! the state encoded as JSON. Loading fails with `CheckpointError::Version` if the
+//! checkpoint was written with a newer format version than this build writes.
+
+use std::io;
+use std::fmt;
+use std::error::Error;
+
+#[cfg(feature = "serialize")]
+use std::io::{Read, Write, BufRead, BufReader};
+#[cfg(feature = "serialize")]
+use std::fs::File;
+#[cfg(feature = "serialize")]
+use std::path::Path;
+
+#[cfg(feature = "serialize")]
+use serde::Serialize;
+#[cfg(feature = "serialize")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "serialize")]
+use serde_json;
+
+#[cfg(feature = "serialize")]
+use util::Stateful;
+
+/// The version of the checkpoint format written by this build
+pub const FORMAT_VERSION: u32 = 1;
+
+#[cfg(feature = "serialize")]
+const MAGIC: &'static str = "renforce-checkpoint";
+
+/// The ways saving or loading a checkpoint can fail
+#[derive(Debug)]
+pub enum CheckpointError {
+	/// Reading or writing the checkpoint failed
+	Io(io::Error),
+	/// The checkpoint could not be parsed
+	Format(String),
+	/// The checkpoint was written with a newer format than this build understands
+	Version(u32),
+	/// The saved state does not fit the value it is being restored into
+	Mismatch(String),
+}
+
+impl fmt::Display for CheckpointError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			CheckpointError::Io(ref err) => write!(f, "checkpoint io error: {}", err),
+			CheckpointError::Format(ref msg) => write!(f, "malformed checkpoint: {}", msg),
+			CheckpointError::Version(v) => write!(f, "checkpoint format version {} is newer than supported version {}", v, FORMAT_VERSION),
+			CheckpointError::Mismatch(ref msg) => write!(f, "checkpoint does not match: {}", msg),
+		}
+	}
+}
+
+impl Error for CheckpointError {
+	fn description(&self) -> &str {
+		match *self {
+			CheckpointError::Io(_) => "checkpoint io error",
+			CheckpointError::Format(_) => "malformed checkpoint",
+			CheckpointError::Version(_) => "unsupported checkpoint version",
+			CheckpointError::Mismatch(_) => "checkpoint does not match",
+		}
+	}
+}
+
+impl From<io::Error> for CheckpointError {
+	fn from(err: io::Error) -> CheckpointError {
+		CheckpointError::Io(err)
+	}
+}
+
+/// Writes the state of item to writer
+#[cfg(feature = "serialize")]
+pub fn save<T, W>(item: &T, mut writer: W) -> Result<(), CheckpointError>
+	where T: Stateful,
+		  T::State: Serialize,
+		  W: Write {
+	writeln!(writer, "{} {}", MAGIC, FORMAT_VERSION)?;
+	serde_json::to_writer(writer, &item.get_state()).map_err(|e| CheckpointError::Format(e.to_string()))
+}
+
+/// Restores item to the state stored in reader
+#[cfg(feature = "serialize")]
+pub fn load<T, R>(item: &mut T, reader: R) -> Result<(), CheckpointError>
+	where T: Stateful,
+		  T::State: DeserializeOwned,
+		  R: Read {
+	let mut reader = BufReader::new(reader);
+
+	let mut header = String::new();
+	reader.read_line(&mut header)?;
+	let mut words = header.split_whitespace();
+	if words.next() != Some(MAGIC) {
+		return Err(CheckpointError::Format("missing checkpoint header".to_string()));
+	}
+	let version: u32 = match words.next().and_then(|v| v.parse().ok()) {
+		Some(v) => v,
+		None => return Err(CheckpointError::Format("missing format version".to_string())),
+	};
+	if version > FORMAT_VERSION {
+		return Err(CheckpointError::Version(version));
+	}
+
+	let state = serde_json::from_reader(reader).map_err(|e| CheckpointError::Format(e.to_string()))?;
+	item.set_state(state)
+}
+
+/// Writes the state of item to the file at path, replacing it if it exists
+#[cfg(feature = "serialize")]
+pub fn save_file<T, P>(item: &T, path: P) -> Result<(), CheckpointError>
+	where T: Stateful,
+		  T::State: Serialize,
+		  P: AsRef<Path> {
+	save(item, File::create(path)?)
+}
+
+/// Restores item to the state stored in the file at path
+#[cfg(feature = "serialize")]
+pub fn load_file<T, P>(item: &mut T, path: P) -> Result<(), CheckpointError>
+	where T: Stateful,
+		  T::State: DeserializeOwned,
+		  P: AsRef<Path> {
+	load(item, File::open(path)?)
+}
\ No newline at end of file
@@ -1,6 +1,8 @@
 //! Chooser Module
 
-use rand::{Rng, thread_rng};
+use std::f64;
+
+use rand::Rng;
 
 use util::Chooser;
 
@@ -9,10 +11,12 @@ use util::Chooser;
 pub struct Uniform;
 
 impl<T: Clone> Chooser<T> for Uniform {
-	fn choose(&self, choices: &Vec<T>, _: Vec<f64>) -> T {
-		let mut rng = thread_rng();
+	fn choose<R: Rng>(&self, choices: &Vec<T>, _: Vec<f64>, rng: &mut R) -> T {
 		rng.choose(&choices).unwrap().clone()
 	}
+	fn probabilities(&self, weights: Vec<f64>) -> Vec<f64> {
+		vec![1.0/weights.len() as f64; weights.len()]
+	}
 }
 
 /// Represents a Chooser that picks each element with probability according to a softmax distrobution
@@ -29,28 +33,16 @@ impl Default for Softmax {
 }
 
 impl<T: Clone> Chooser<T> for Softmax {
-	fn choose(&self, choices: &Vec<T>, weights: Vec<f64>) -> T {
-		let mut total = 0.0;
-		let new_weights: Vec<_> = weights.into_iter()
-								 		 .map(|w| {
-								 			let u = (w/self.temp).exp();
-										 	total += u;
-										 	u
-										 })
-										 .collect();
-		let mut rng = thread_rng();
-		let mut index = 0;
-
-		if total == 0.0 {
-			return rng.choose(&choices).unwrap().clone()
-		}
-
-		let mut choice = rng.gen_range(0.0, total);
-		while choice > new_weights[index] {
-			choice -= new_weights[index];
-			index = index + 1;
-		}
-		choices[index].clone()
+	fn choose<R: Rng>(&self, choices: &Vec<T>, weights: Vec<f64>, rng: &mut R) -> T {
+		let probs = Chooser::<T>::probabilities(self, weights);
+		Weighted.choose(choices, probs, rng)
+	}
+	fn probabilities(&self, weights: Vec<f64>) -> Vec<f64> {
+		// Shifting the weights by their max avoids overflow without changing the distribution
+		let max = weights.iter().fold(f64::NEG_INFINITY, |m, &w| m.max(w));
+		let exps: Vec<_> = weights.iter().map(|w| ((w - max)/self.temp).exp()).collect();
+		let total: f64 = exps.iter().sum();
+		exps.into_iter().map(|u| u/total).collect()
 	}
 }
 
@@ -68,10 +60,9 @@ impl Softmax {
 pub struct Weighted; // TODO: Come up with a better name
 
 impl<T: Clone> Chooser<T> for Weighted {
-	fn choose(&self, choices: &Vec<T>, weights: Vec<f64>) -> T {
+	fn choose<R: Rng>(&self, choices: &Vec<T>, weights: Vec<f64>, rng: &mut R) -> T {
 		let total = weights.iter().sum();
 
-		let mut rng = thread_rng();
 		if total == 0.0 {
 			return rng.choose(&choices).unwrap().clone();
 		}
@@ -84,4 +75,11 @@ impl<T: Clone> Chooser<T> for Weighted {
 		}
 		choices[index].clone()
 	}
+	fn probabilities(&self, weights: Vec<f64>) -> Vec<f64> {
+		let total: f64 = weights.iter().sum();
+		if total == 0.0 {
+			return vec![1.0/weights.len() as f64; weights.len()];
+		}
+		weights.into_iter().map(|w| w/total).collect()
+	}
 }
\ No newline at end of file
@@ -2,7 +2,8 @@
 
 use num::Float;
 
-use util::GradientDescAlgo;
+use util::{GradientDescAlgo, Stateful};
+use util::checkpoint::CheckpointError;
 
 /// Simplest possible Gradient Descent algorithm
 /// Gradient step is just gradient * learning_rate
@@ -44,6 +45,29 @@ impl<F: Float> GradientDescAlgo<F> for GradDescMomentum<F> {
     }
 }
 
+impl Stateful for GradientDesc {
+	/// GradientDesc keeps no state between steps
+	type State = ();
+
+	fn get_state(&self) {}
+	fn set_state(&mut self, _: ()) -> Result<(), CheckpointError> {
+		Ok(())
+	}
+}
+
+impl<F: Float> Stateful for GradDescMomentum<F> {
+	/// The running step (velocity), if any steps have been taken
+	type State = Option<Vec<F>>;
+
+	fn get_state(&self) -> Option<Vec<F>> {
+		self.cache.clone()
+	}
+	fn set_state(&mut self, state: Option<Vec<F>>) -> Result<(), CheckpointError> {
+		self.cache = state;
+		Ok(())
+	}
+}
+
 impl Default for GradDescMomentum<f64> {
 	fn default() -> GradDescMomentum<f64> {
 		GradDescMomentum {
@@ -97,6 +121,19 @@ impl<F: Float> GradientDescAlgo<F> for RMSProp<F> {
     }
 }
 
+impl<F: Float> Stateful for RMSProp<F> {
+	/// The running average of squared gradients, if any steps have been taken
+	type State = Option<Vec<F>>;
+
+	fn get_state(&self) -> Option<Vec<F>> {
+		self.cache.clone()
+	}
+	fn set_state(&mut self, state: Option<Vec<F>>) -> Result<(), CheckpointError> {
+		self.cache = state;
+		Ok(())
+	}
+}
+
 impl Default for RMSProp<f64> {
 	fn default() -> RMSProp<f64> {
 		RMSProp {
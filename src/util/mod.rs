@@ -5,16 +5,25 @@ pub mod chooser;
 pub mod approx;
 pub mod feature;
 pub mod graddesc;
+pub mod checkpoint;
+pub mod replay;
+pub mod network;
+pub mod baseline;
+pub mod tree;
 
 mod metric;
 
 use std::fmt::Debug;
 
+use rand::Rng;
+
 use num::Num;
 use num::Float;
 
 use environment::Space;
 
+use util::checkpoint::CheckpointError;
+
 // Is there a clean way to reduce the number of traits?
 // Are things progressing fine as is?
 
@@ -28,6 +37,19 @@ pub trait ParameterizedFunc<T: Num> {
 	fn set_params(&mut self, params: Vec<T>);
 }
 
+/// Something whose learned state can be exported and later restored
+///
+/// The state only holds what changes while training, so it should be restored into a value
+/// constructed the same way as the original (same features, action space, hyperparameters, etc.)
+pub trait Stateful {
+	/// Plain data capturing everything needed to restore self
+	type State;
+	/// Exports the current state of self
+	fn get_state(&self) -> Self::State;
+	/// Restores a previously exported state
+	fn set_state(&mut self, state: Self::State) -> Result<(), CheckpointError>;
+}
+
 /// A differentiable function taking in (state, action) pairs 
 pub trait DifferentiableFunc<S: Space, A: Space, T: Num> : ParameterizedFunc<T> {
 	/// Calculates the gradient of the output with respect to this function's parameters
@@ -48,6 +70,8 @@ pub trait DifferentiableVecFunc<S: Space, T: Num> : ParameterizedFunc<T> {
 pub trait LogDiffFunc<S: Space, A: Space, T: Num> : ParameterizedFunc<T> {
 	/// The gradient of the log of the output with respect to the parameters
 	fn log_grad(&self, state: &S::Element, action: &A::Element) -> Vec<T>;
+	/// The log of the output (for agents, the log-probability or log-density of choosing action in state)
+	fn log_prob(&self, state: &S::Element, action: &A::Element) -> T;
 }
 
 /// Calculates gradient steps
@@ -56,12 +80,25 @@ pub trait GradientDescAlgo<F: Float> {
 	fn calculate(&mut self, grad: Vec<F>, lr: F) -> Vec<F>;
 }
 
+/// Reduces the variance of policy gradient estimates
+pub trait Baseline<S: Space> : Debug {
+	/// Turns the discounted returns following each state into the weights used by the policy gradient
+	///
+	/// times holds how many steps into its episode each state was visited. The baseline may learn
+	/// from the returns, but only after using them.
+	fn apply(&mut self, states: &[S::Element], times: &[usize], returns: Vec<f64>) -> Vec<f64>;
+}
+
 /// Represents something that extracts features from state-action pairs
 pub trait FeatureExtractor<S: Space, A: Space, F: Float> {
 	/// Number of features that can be calculated
 	fn num_features(&self) -> usize;
 	/// Vector containg the values of all the features for this state
 	fn extract(&self, state: &S::Element, action: &A::Element) -> Vec<F>;
+	/// The (index, value) pairs of the features that may be nonzero for this state and action
+	fn extract_sparse(&self, state: &S::Element, action: &A::Element) -> Vec<(usize, F)> {
+		self.extract(state, action).into_iter().enumerate().filter(|&(_, x)| x != F::zero()).collect()
+	}
 }
 
 /// QFunction Trait
@@ -85,12 +122,38 @@ pub trait VFunction<S: Space> : Debug {
 	fn update(&mut self, state: &S::Element, new_val: f64, alpha: f64);
 }
 
+/// A QFunction that can be trained using eligibility traces
+///
+/// The trace records how eligible each part of the function is for being changed by the next TD error
+pub trait TracedQFunction<S: Space, A: Space> : QFunction<S, A> {
+	/// The eligibility of every part of the function
+	type Trace: Debug + Clone;
+	/// Returns a trace in which nothing is eligible
+	fn new_trace(&self) -> Self::Trace;
+	/// Multiplies every eligibility in trace by factor
+	fn decay_trace(&self, trace: &mut Self::Trace, factor: f64);
+	/// Makes (state, action) eligible in the way described by trace_type (alpha is learning rate)
+	fn visit(&self, trace: &mut Self::Trace, state: &S::Element, action: &A::Element, trace_type: TraceType, alpha: f64);
+	/// Adds step times trace to the function
+	fn update_traced(&mut self, trace: &Self::Trace, step: f64);
+}
+
+/// A QFunction that can be fit to a whole dataset at once, as in supervised regression
+pub trait FittableQFunction<S: Space, A: Space> : QFunction<S, A> {
+	/// Fits the function to the (state, action, target) triples in data
+	///
+	/// Parts of the function that data says nothing about (such as actions missing from it) may keep their old values
+	fn fit<R: Rng>(&mut self, data: &[(S::Element, A::Element, f64)], rng: &mut R);
+}
+
 /// Choose Trait
 ///
 /// Represents a way to randomly choose an element of a list given some weights
 pub trait Chooser<T> : Debug {
-	/// returns an element of choices
-	fn choose(&self, choices: &Vec<T>, weights: Vec<f64>) -> T;
+	/// returns an element of choices, using rng as the source of randomness
+	fn choose<R: Rng>(&self, choices: &Vec<T>, weights: Vec<f64>, rng: &mut R) -> T;
+	/// returns the probability that choose picks each choice given these weights
+	fn probabilities(&self, weights: Vec<f64>) -> Vec<f64>;
 }
 
 /// A real-valued feature of elements of some state space
@@ -107,6 +170,23 @@ impl<F: Float, S: Space> Clone for Box<Feature<S, F>> {
 	}
 }
 
+// Describes features (by their Debug output) so saved states can record which ones they were made with
+fn describe_features<S: Space, F: Float>(features: &[Box<Feature<S, F>>]) -> Vec<String> {
+	features.iter().map(|feat| format!("{:?}", feat)).collect()
+}
+
+// Checks that a saved state was made with the described features
+fn check_features<S: Space, F: Float>(features: &[Box<Feature<S, F>>], saved: &[String]) -> Result<(), CheckpointError> {
+	let described = describe_features(features);
+	if described.len() != saved.len() {
+		return Err(CheckpointError::Mismatch(format!("expected {} features but found {}", described.len(), saved.len())));
+	}
+	match described.iter().zip(saved).position(|(x, y)| x != y) {
+		Some(i) => Err(CheckpointError::Mismatch(format!("expected feature {} to be {} but found {}", i, described[i], saved[i]))),
+		None => Ok(()),
+	}
+}
+
 /// A type with a notion of distance
 /// The distance function should satisfy the triangle inequality (and the other [metric](https://www.wikiwand.com/en/Metric_(mathematics)) properties)
 ///
@@ -120,6 +200,17 @@ pub trait Metric {
 	fn dist2(x: &Self, y: &Self) -> f64;
 }
 
+/// How an eligibility trace grows when a (state, action) pair with gradient x is visited
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TraceType {
+	/// e = e + x
+	Accumulating,
+	/// e_i = x_i wherever x_i is nonzero
+	Replacing,
+	/// e = e + (1 - alpha e^T x) x
+	Dutch,
+}
+
 /// Some length of time experienced by an agent
 #[derive(Debug, Clone)]
 pub enum TimePeriod {
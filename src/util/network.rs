@@ -0,0 +1,491 @@
+//! Neural Network Module
+//!
+//! Multilayer perceptrons that can be used anywhere a linear function approximator can
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::fmt::Debug;
+
+use rand::Rng;
+
+use num::Float;
+use num::cast::NumCast;
+
+use environment::{Space, FiniteSpace};
+
+use util::{VFunction, QFunction, Feature};
+use util::{ParameterizedFunc, DifferentiableFunc, DifferentiableVecFunc};
+use util::{Stateful, describe_features, check_features};
+use util::checkpoint::CheckpointError;
+
+/// The nonlinearity applied to the output of a layer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Activation {
+	/// f(x) = x
+	Identity,
+	/// f(x) = 1/(1 + e^-x)
+	Sigmoid,
+	/// f(x) = tanh(x)
+	Tanh,
+	/// f(x) = max(0, x)
+	ReLU,
+}
+
+impl Activation {
+	fn apply<F: Float>(&self, x: F) -> F {
+		match *self {
+			Activation::Identity => x,
+			Activation::Sigmoid => F::one()/(F::one() + (-x).exp()),
+			Activation::Tanh => x.tanh(),
+			Activation::ReLU => x.max(F::zero()),
+		}
+	}
+	// The derivative of the activation, written in terms of its output
+	fn derivative<F: Float>(&self, y: F) -> F {
+		match *self {
+			Activation::Identity => F::one(),
+			Activation::Sigmoid => y*(F::one() - y),
+			Activation::Tanh => F::one() - y*y,
+			Activation::ReLU => if y > F::zero() {F::one()} else {F::zero()},
+		}
+	}
+}
+
+/// The learned state of a VNetwork or QNetwork
+///
+/// Features can not be saved, so they are only recorded (as their Debug output) and
+/// checked against the features of the network the state is loaded into
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct NetworkState<F> {
+	/// Descriptions of the features fed into the network
+	pub features: Vec<String>,
+	/// The number of units in each layer (starting with the input layer)
+	pub sizes: Vec<usize>,
+	/// The weights and biases of every layer
+	pub params: Vec<F>,
+}
+
+// A fully connected feedforward network
+// The parameters of each layer are stored one after the other, weights (row major) then biases
+#[derive(Debug, Clone)]
+struct Network<F: Float> {
+	sizes: Vec<usize>,
+	activations: Vec<Activation>,
+	params: Vec<F>,
+}
+
+impl<F: Float> Network<F> {
+	// Weights are drawn using Xavier initialization and biases start at 0
+	fn new<R: Rng>(sizes: Vec<usize>, activations: Vec<Activation>, rng: &mut R) -> Network<F> {
+		let mut params = Vec::new();
+		for l in 0..activations.len() {
+			let (n_in, n_out) = (sizes[l], sizes[l+1]);
+			let bound = (6.0/(n_in + n_out) as f64).sqrt();
+			for _ in 0..n_in*n_out {
+				params.push(NumCast::from(rng.gen_range(-bound, bound)).unwrap());
+			}
+			params.extend_from_slice(&vec![F::zero(); n_out]);
+		}
+
+		Network {
+			sizes: sizes,
+			activations: activations,
+			params: params
+		}
+	}
+	fn num_outputs(&self) -> usize {
+		self.sizes[self.sizes.len() - 1]
+	}
+	// Returns the output of every layer, starting with the input
+	fn forward(&self, input: Vec<F>) -> Vec<Vec<F>> {
+		let mut outputs = vec![input];
+		let mut offset = 0;
+		for l in 0..self.activations.len() {
+			let (n_in, n_out) = (self.sizes[l], self.sizes[l+1]);
+			let output = {
+				let x = &outputs[l];
+				let weights = &self.params[offset..offset + n_in*n_out];
+				let biases = &self.params[offset + n_in*n_out..offset + n_in*n_out + n_out];
+				(0..n_out).map(|j| {
+					let mut z = biases[j];
+					for i in 0..n_in {
+						z = z + weights[j*n_in + i]*x[i];
+					}
+					self.activations[l].apply(z)
+				}).collect()
+			};
+			outputs.push(output);
+			offset += n_in*n_out + n_out;
+		}
+		outputs
+	}
+	// Backpropagates through the layer outputs of a forward pass, returning the gradient of
+	// sum_j coeffs[j]*output[j] with respect to the parameters
+	fn backward(&self, outputs: &[Vec<F>], coeffs: Vec<F>) -> Vec<F> {
+		let mut grad = vec![F::zero(); self.params.len()];
+		let mut offset = self.params.len();
+		let mut delta = coeffs;
+		for l in (0..self.activations.len()).rev() {
+			let (n_in, n_out) = (self.sizes[l], self.sizes[l+1]);
+			offset -= n_in*n_out + n_out;
+
+			for j in 0..n_out {
+				delta[j] = delta[j]*self.activations[l].derivative(outputs[l+1][j]);
+			}
+
+			let x = &outputs[l];
+			let mut prev_delta = vec![F::zero(); n_in];
+			for j in 0..n_out {
+				for i in 0..n_in {
+					grad[offset + j*n_in + i] = delta[j]*x[i];
+					prev_delta[i] = prev_delta[i] + self.params[offset + j*n_in + i]*delta[j];
+				}
+				grad[offset + n_in*n_out + j] = delta[j];
+			}
+			delta = prev_delta;
+		}
+		grad
+	}
+	// Returns the output at index along with its gradient
+	fn output_grad(&self, input: Vec<F>, index: usize) -> (F, Vec<F>) {
+		let outputs = self.forward(input);
+		let mut coeffs = vec![F::zero(); self.num_outputs()];
+		coeffs[index] = F::one();
+
+		let grad = self.backward(&outputs, coeffs);
+		(outputs[outputs.len() - 1][index], grad)
+	}
+	// The gradient of every output with respect to each parameter
+	fn jacobian(&self, input: Vec<F>) -> Vec<Vec<F>> {
+		let outputs = self.forward(input);
+		let num_outputs = self.num_outputs();
+		let mut jac = vec![Vec::with_capacity(num_outputs); self.params.len()];
+		for k in 0..num_outputs {
+			let mut coeffs = vec![F::zero(); num_outputs];
+			coeffs[k] = F::one();
+			for (p, g) in self.backward(&outputs, coeffs).into_iter().enumerate() {
+				jac[p].push(g);
+			}
+		}
+		jac
+	}
+	// Takes a gradient descent step on the mean squared error of the given (input, output index, target) triples
+	fn fit(&mut self, batch: Vec<(Vec<F>, usize, f64)>, alpha: f64) {
+		if batch.is_empty() {
+			return;
+		}
+
+		let mut total_grad = vec![F::zero(); self.params.len()];
+		for (input, index, target) in batch.iter().cloned() {
+			let (val, grad) = self.output_grad(input, index);
+			let err: F = val - NumCast::from(target).unwrap();
+			for p in 0..grad.len() {
+				total_grad[p] = total_grad[p] + err*grad[p];
+			}
+		}
+
+		let lr: F = NumCast::from(alpha/batch.len() as f64).unwrap();
+		for p in 0..self.params.len() {
+			self.params[p] = self.params[p] - lr*total_grad[p];
+		}
+	}
+	fn get_state(&self, features: Vec<String>) -> NetworkState<F> {
+		NetworkState {
+			features: features,
+			sizes: self.sizes.clone(),
+			params: self.params.clone()
+		}
+	}
+	fn set_state(&mut self, state: NetworkState<F>) -> Result<(), CheckpointError> {
+		if state.sizes != self.sizes {
+			return Err(CheckpointError::Mismatch(format!("expected layer sizes {:?} but found {:?}",
+														 self.sizes, state.sizes)));
+		} else if state.params.len() != self.params.len() {
+			return Err(CheckpointError::Mismatch(format!("expected {} parameters but found {}",
+														 self.params.len(), state.params.len())));
+		}
+		self.params = state.params;
+		Ok(())
+	}
+}
+
+fn layer_sizes(num_inputs: usize, hidden: &[(usize, Activation)], num_outputs: usize) -> (Vec<usize>, Vec<Activation>) {
+	let mut sizes = vec![num_inputs];
+	let mut activations = Vec::with_capacity(hidden.len() + 1);
+	for &(size, activation) in hidden {
+		sizes.push(size);
+		activations.push(activation);
+	}
+	sizes.push(num_outputs);
+	activations.push(Activation::Identity);
+	(sizes, activations)
+}
+
+/// A multilayer perceptron mapping states to a vector of values
+///
+/// The input layer is the vector of features of the state and the output layer is linear.
+/// When used as a VFunction (or DifferentiableFunc) only the first output is used.
+/// Weights updated using squared error cost
+#[derive(Debug, Clone)]
+pub struct VNetwork<F: Float + Debug, S: Space> {
+	features: Vec<Box<Feature<S, F>>>,
+	network: Network<F>,
+}
+
+impl<F: Float + Debug, S: Space> VFunction<S> for VNetwork<F, S> {
+	fn eval(&self, state: &S::Element) -> f64 {
+		self.apply(state)[0].to_f64().unwrap()
+	}
+	fn update(&mut self, state: &S::Element, new_val: f64, alpha: f64) {
+		let input = self.extract(state);
+		self.network.fit(vec![(input, 0, new_val)], alpha);
+	}
+}
+
+impl<F: Float + Debug, S: Space> ParameterizedFunc<F> for VNetwork<F, S> {
+	fn num_params(&self) -> usize {
+		self.network.params.len()
+	}
+	fn get_params(&self) -> Vec<F> {
+		self.network.params.clone()
+	}
+	fn set_params(&mut self, params: Vec<F>) {
+		self.network.params = params;
+	}
+}
+
+impl<S: Space, A: Space, F: Float + Debug> DifferentiableFunc<S, A, F> for VNetwork<F, S> {
+	fn get_grad(&self, state: &S::Element, _: &A::Element) -> Vec<F> {
+		self.network.output_grad(self.extract(state), 0).1
+	}
+	fn calculate(&self, state: &S::Element, _: &A::Element) -> F {
+		self.apply(state)[0]
+	}
+}
+
+impl<F: Float + Debug, S: Space> DifferentiableVecFunc<S, F> for VNetwork<F, S> {
+	fn get_grads(&self, state: &S::Element) -> Vec<Vec<F>> {
+		self.network.jacobian(self.extract(state))
+	}
+	fn apply(&self, state: &S::Element) -> Vec<F> {
+		self.network.forward(self.extract(state)).pop().unwrap()
+	}
+}
+
+impl<F: Float + Debug, S: Space> Stateful for VNetwork<F, S> {
+	type State = NetworkState<F>;
+
+	fn get_state(&self) -> NetworkState<F> {
+		self.network.get_state(describe_features(&self.features))
+	}
+	fn set_state(&mut self, state: NetworkState<F>) -> Result<(), CheckpointError> {
+		check_features(&self.features, &state.features)?;
+		self.network.set_state(state)
+	}
+}
+
+impl<F: Float + Debug, S: Space> VNetwork<F, S> {
+	/// Creates a new VNetwork with the given features, hidden layers (size and activation) and number of outputs
+	pub fn new<R: Rng>(features: Vec<Box<Feature<S, F>>>, hidden: &[(usize, Activation)],
+					   num_outputs: usize, rng: &mut R) -> VNetwork<F, S> {
+		let (sizes, activations) = layer_sizes(features.len(), hidden, num_outputs);
+		VNetwork {
+			features: features,
+			network: Network::new(sizes, activations, rng)
+		}
+	}
+	/// Updates the first output using the average gradient of the squared error over a minibatch of (state, value) pairs
+	pub fn update_batch(&mut self, batch: &[(S::Element, f64)], alpha: f64) {
+		let batch = batch.iter().map(|&(ref state, val)| (self.extract(state), 0, val)).collect();
+		self.network.fit(batch, alpha);
+	}
+
+	fn extract(&self, state: &S::Element) -> Vec<F> {
+		self.features.iter().map(|feat| feat.extract(state)).collect()
+	}
+}
+
+/// A multilayer perceptron with one output for each action
+///
+/// The input layer is the vector of features of the state and the output layer is linear.
+/// Weights updated using squared error cost
+#[derive(Debug, Clone)]
+pub struct QNetwork<F: Float + Debug, S: Space, A: FiniteSpace>
+	where A::Element: Hash + Eq {
+	features: Vec<Box<Feature<S, F>>>,
+	/// Index of the output corresponding to each action
+	indices: HashMap<A::Element, usize>,
+	network: Network<F>,
+}
+
+impl<F: Float + Debug, S: Space, A: FiniteSpace> QFunction<S, A> for QNetwork<F, S, A>
+	where A::Element: Hash + Eq {
+	fn eval(&self, state: &S::Element, action: &A::Element) -> f64 {
+		self.calculate(state, action).to_f64().unwrap()
+	}
+	fn update(&mut self, state: &S::Element, action: &A::Element, new_val: f64, alpha: f64) {
+		let input = self.extract(state);
+		let index = self.indices[action];
+		self.network.fit(vec![(input, index, new_val)], alpha);
+	}
+}
+
+impl<F: Float + Debug, S: Space, A: FiniteSpace> ParameterizedFunc<F> for QNetwork<F, S, A>
+	where A::Element: Hash + Eq {
+	fn num_params(&self) -> usize {
+		self.network.params.len()
+	}
+	fn get_params(&self) -> Vec<F> {
+		self.network.params.clone()
+	}
+	fn set_params(&mut self, params: Vec<F>) {
+		self.network.params = params;
+	}
+}
+
+impl<S: Space, A: FiniteSpace, F: Float + Debug> DifferentiableFunc<S, A, F> for QNetwork<F, S, A>
+	where A::Element: Hash + Eq {
+	fn get_grad(&self, state: &S::Element, action: &A::Element) -> Vec<F> {
+		self.network.output_grad(self.extract(state), self.indices[action]).1
+	}
+	fn calculate(&self, state: &S::Element, action: &A::Element) -> F {
+		self.apply(state)[self.indices[action]]
+	}
+}
+
+impl<F: Float + Debug, S: Space, A: FiniteSpace> DifferentiableVecFunc<S, F> for QNetwork<F, S, A>
+	where A::Element: Hash + Eq {
+	fn get_grads(&self, state: &S::Element) -> Vec<Vec<F>> {
+		self.network.jacobian(self.extract(state))
+	}
+	fn apply(&self, state: &S::Element) -> Vec<F> {
+		self.network.forward(self.extract(state)).pop().unwrap()
+	}
+}
+
+impl<F: Float + Debug, S: Space, A: FiniteSpace> Stateful for QNetwork<F, S, A>
+	where A::Element: Hash + Eq {
+	type State = NetworkState<F>;
+
+	fn get_state(&self) -> NetworkState<F> {
+		self.network.get_state(describe_features(&self.features))
+	}
+	fn set_state(&mut self, state: NetworkState<F>) -> Result<(), CheckpointError> {
+		check_features(&self.features, &state.features)?;
+		self.network.set_state(state)
+	}
+}
+
+impl<F: Float + Debug, S: Space, A: FiniteSpace> QNetwork<F, S, A> where A::Element: Hash + Eq {
+	/// Creates a new QNetwork with the given features and hidden layers (size and activation)
+	pub fn new<R: Rng>(features: Vec<Box<Feature<S, F>>>, hidden: &[(usize, Activation)],
+					   action_space: &A, rng: &mut R) -> QNetwork<F, S, A> {
+		let actions = action_space.enumerate();
+		let mut indices = HashMap::new();
+		for i in 0..actions.len() {
+			indices.insert(actions[i].clone(), i);
+		}
+
+		let (sizes, activations) = layer_sizes(features.len(), hidden, actions.len());
+		QNetwork {
+			features: features,
+			indices: indices,
+			network: Network::new(sizes, activations, rng)
+		}
+	}
+	/// Updates the function using the average gradient of the squared error over a minibatch of (state, action, value) triples
+	pub fn update_batch(&mut self, batch: &[(S::Element, A::Element, f64)], alpha: f64) {
+		let batch = batch.iter().map(|&(ref state, ref action, val)| {
+			(self.extract(state), self.indices[action], val)
+		}).collect();
+		self.network.fit(batch, alpha);
+	}
+
+	fn extract(&self, state: &S::Element) -> Vec<F> {
+		self.features.iter().map(|feat| feat.extract(state)).collect()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use rand::{SeedableRng, StdRng};
+
+	use environment::{Range, Finite};
+
+	use util::{VFunction, QFunction, DifferentiableFunc, DifferentiableVecFunc, ParameterizedFunc};
+	use util::feature::IFeature;
+
+	use super::{VNetwork, QNetwork, Activation};
+
+	const EPSILON: f64 = 0.0001;
+
+	fn network(activation: Activation) -> VNetwork<f64, Vec<Range>> {
+		let mut rng = StdRng::from_seed(&[1, 2]);
+		VNetwork::new(vec![Box::new(IFeature::new(0)), Box::new(IFeature::new(1))],
+					  &[(5, activation), (4, activation)], 2, &mut rng)
+	}
+
+	// Compares backpropagated gradients to finite differences
+	fn check_grads(activation: Activation) {
+		let mut net = network(activation);
+		let state = vec![0.3, -0.7];
+		let grads = net.get_grads(&state);
+		let params = net.get_params();
+
+		for p in 0..params.len() {
+			let mut shifted = params.clone();
+			shifted[p] += EPSILON;
+			net.set_params(shifted);
+			let up = net.apply(&state);
+
+			let mut shifted = params.clone();
+			shifted[p] -= EPSILON;
+			net.set_params(shifted);
+			let down = net.apply(&state);
+
+			for k in 0..2 {
+				let approx = (up[k] - down[k])/(2.0*EPSILON);
+				assert!((approx - grads[p][k]).abs() < 1e-5, "param {} output {}: {} vs {}", p, k, approx, grads[p][k]);
+			}
+		}
+	}
+
+	#[test]
+	fn grads_tanh() {
+		check_grads(Activation::Tanh);
+	}
+	#[test]
+	fn grads_sigmoid() {
+		check_grads(Activation::Sigmoid);
+	}
+	#[test]
+	fn grads_relu() {
+		check_grads(Activation::ReLU);
+	}
+	#[test]
+	fn fits_xor() {
+		let mut net = network(Activation::Tanh);
+		let batch = vec![(vec![0.0, 0.0], 0.0), (vec![0.0, 1.0], 1.0), (vec![1.0, 0.0], 1.0), (vec![1.0, 1.0], 0.0)];
+		for _ in 0..5000 {
+			net.update_batch(&batch, 0.5);
+		}
+
+		for &(ref state, val) in &batch {
+			assert!((net.eval(state) - val).abs() < 0.1, "f({:?}) = {}", state, net.eval(state));
+		}
+	}
+	#[test]
+	fn q_outputs_match_actions() {
+		let mut rng = StdRng::from_seed(&[3]);
+		let mut q_func: QNetwork<f64, Vec<Range>, Finite> = QNetwork::new(vec![Box::new(IFeature::new(0))],
+																		  &[(3, Activation::ReLU)], &Finite::new(3), &mut rng);
+		let state = vec![0.5];
+		for _ in 0..200 {
+			q_func.update(&state, &1, 2.0, 0.1);
+		}
+
+		assert!((q_func.eval(&state, &1) - 2.0).abs() < EPSILON);
+		assert_eq!(q_func.calculate(&state, &2), q_func.apply(&state)[2]);
+	}
+}
\ No newline at end of file
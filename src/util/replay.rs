@@ -0,0 +1,413 @@
+//! Replay Module
+//!
+//! Stores observed transitions so they can be learned from more than once
+
+use rand::Rng;
+
+use environment::{Space, Transition};
+use util::Stateful;
+use util::checkpoint::CheckpointError;
+
+// Added to the magnitude of TD errors so no transition is ever impossible to sample
+const MIN_PRIORITY: f64 = 0.01;
+
+/// How transitions are drawn from a ReplayBuffer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sampling {
+	/// Every stored transition is equally likely
+	Uniform,
+	/// A transition is drawn with probability proportional to (|TD error| + 0.01)^alpha
+	Proportional(f64),
+	/// A transition is drawn with probability proportional to (1/rank)^alpha,
+	/// where the transition with the largest |TD error| has rank 1
+	Rank(f64),
+}
+
+/// A fixed capacity store of transitions
+///
+/// Once full, the oldest transitions are overwritten first.
+/// New transitions are given the highest priority seen so far, so they are likely to be sampled at least once.
+/// Prioritized sampling draws some transitions more often than they were observed, so every sample
+/// comes with an importance sampling weight, (1/(N*P(i)))^beta scaled so the largest weight in the
+/// minibatch is 1, that learners should scale their updates by.
+/// Rank based priorities are only re-sorted once as many transitions have been drawn as are stored.
+#[derive(Debug)]
+pub struct ReplayBuffer<S: Space, A: Space> {
+	/// The stored transitions
+	transitions: Vec<Transition<S, A>>,
+	/// Maximum number of transitions stored
+	capacity: usize,
+	/// Index the next transition will be stored at
+	next: usize,
+	/// How transitions are sampled
+	sampling: Sampling,
+	/// Magnitude of the latest TD error of each transition
+	errors: Vec<f64>,
+	/// Largest TD error magnitude seen so far
+	max_error: f64,
+	/// Sampling priority of each transition
+	tree: SumTree,
+	/// Whether the rank based priorities in tree are out of date
+	stale: bool,
+	/// Number of transitions left to draw before out of date rank based priorities are re-sorted
+	draws_until_rerank: usize,
+	/// How strongly importance sampling weights correct for prioritized sampling (0 is not at all, 1 is fully)
+	beta: f64,
+}
+
+/// The stored transitions and priorities of a ReplayBuffer
+///
+/// Should be restored into a ReplayBuffer with the same capacity and sampling
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct ReplayBufferState<S, A> {
+	/// The stored transitions
+	pub transitions: Vec<(S, A, f64, S)>,
+	/// Index the next transition will be stored at
+	pub next: usize,
+	/// Magnitude of the latest TD error of each transition
+	pub errors: Vec<f64>,
+	/// Largest TD error magnitude seen so far
+	pub max_error: f64,
+	/// Sampling priority of each transition
+	pub priorities: Vec<f64>,
+	/// Whether the rank based priorities are out of date
+	pub stale: bool,
+	/// Number of transitions left to draw before out of date rank based priorities are re-sorted
+	pub draws_until_rerank: usize,
+}
+
+impl<S: Space, A: Space> Stateful for ReplayBuffer<S, A> {
+	type State = ReplayBufferState<S::Element, A::Element>;
+
+	fn get_state(&self) -> Self::State {
+		ReplayBufferState {
+			transitions: self.transitions.clone(),
+			next: self.next,
+			errors: self.errors.clone(),
+			max_error: self.max_error,
+			priorities: (0..self.len()).map(|i| self.tree.get(i)).collect(),
+			stale: self.stale,
+			draws_until_rerank: self.draws_until_rerank
+		}
+	}
+	fn set_state(&mut self, state: Self::State) -> Result<(), CheckpointError> {
+		let len = state.transitions.len();
+		if len > self.capacity {
+			return Err(CheckpointError::Mismatch(format!("expected at most {} transitions but found {}", self.capacity, len)));
+		}
+		if state.errors.len() != len || state.priorities.len() != len {
+			return Err(CheckpointError::Mismatch(format!("expected an error and priority for each of {} transitions", len)));
+		}
+		if state.next >= self.capacity || (len < self.capacity && state.next != len) {
+			return Err(CheckpointError::Mismatch(format!("next index {} does not fit {} transitions", state.next, len)));
+		}
+
+		self.tree = SumTree::new(self.capacity);
+		for (i, &priority) in state.priorities.iter().enumerate() {
+			self.tree.set(i, priority);
+		}
+		self.transitions = state.transitions;
+		self.next = state.next;
+		self.errors = state.errors;
+		self.max_error = state.max_error;
+		self.stale = state.stale;
+		self.draws_until_rerank = state.draws_until_rerank;
+		Ok(())
+	}
+}
+
+impl<S: Space, A: Space> ReplayBuffer<S, A> {
+	/// Creates a new, empty ReplayBuffer that samples uniformly
+	pub fn new(capacity: usize) -> ReplayBuffer<S, A> {
+		assert!(capacity > 0, "capacity must be positive");
+
+		ReplayBuffer {
+			transitions: Vec::with_capacity(capacity),
+			capacity: capacity,
+			next: 0,
+			sampling: Sampling::Uniform,
+			errors: Vec::with_capacity(capacity),
+			max_error: 1.0,
+			tree: SumTree::new(capacity),
+			stale: false,
+			draws_until_rerank: 0,
+			beta: 1.0,
+		}
+	}
+	/// Updates sampling field of self
+	pub fn sampling(mut self, sampling: Sampling) -> ReplayBuffer<S, A> {
+		match sampling {
+			Sampling::Proportional(alpha) | Sampling::Rank(alpha) => assert!(alpha >= 0.0, "alpha must be non-negative"),
+			Sampling::Uniform => {},
+		}
+
+		self.sampling = sampling;
+		for i in 0..self.transitions.len() {
+			let error = self.errors[i];
+			self.set_error(i, error);
+		}
+		self
+	}
+	/// Updates beta field of self
+	pub fn beta(mut self, beta: f64) -> ReplayBuffer<S, A> {
+		assert!(0.0 <= beta && beta <= 1.0, "beta must be between 0 and 1");
+
+		self.beta = beta;
+		self
+	}
+	/// Returns the number of stored transitions
+	pub fn len(&self) -> usize {
+		self.transitions.len()
+	}
+	/// Returns whether no transitions are stored
+	pub fn is_empty(&self) -> bool {
+		self.transitions.is_empty()
+	}
+	/// Returns the maximum number of transitions that can be stored
+	pub fn capacity(&self) -> usize {
+		self.capacity
+	}
+	/// Returns the transition stored at index
+	pub fn get(&self, index: usize) -> &Transition<S, A> {
+		&self.transitions[index]
+	}
+	/// Stores a transition, overwriting the oldest one if the buffer is full
+	pub fn push(&mut self, transition: Transition<S, A>) {
+		let index = self.next;
+		if index < self.transitions.len() {
+			self.transitions[index] = transition;
+		} else {
+			self.transitions.push(transition);
+			self.errors.push(0.0);
+		}
+
+		let max_error = self.max_error;
+		self.set_error(index, max_error);
+		if let Sampling::Rank(_) = self.sampling {
+			// Ranked first until the next re-sort
+			self.tree.set(index, 1.0);
+		}
+		self.next = (self.next + 1) % self.capacity;
+	}
+	/// Draws (with replacement) num transitions, returning their indices and importance sampling weights
+	pub fn sample<R: Rng>(&mut self, num: usize, rng: &mut R) -> Vec<(usize, f64)> {
+		if self.is_empty() {
+			return Vec::new();
+		}
+
+		if self.stale && self.draws_until_rerank == 0 {
+			self.rerank();
+			self.draws_until_rerank = self.len();
+		}
+		self.draws_until_rerank = self.draws_until_rerank.saturating_sub(num);
+
+		let len = self.len();
+		match self.sampling {
+			Sampling::Uniform => (0..num).map(|_| (rng.gen_range(0, len), 1.0)).collect(),
+			_ => {
+				let total = self.tree.total();
+				let indices: Vec<_> = (0..num).map(|_| {
+					let index = self.tree.find(rng.gen_range(0.0, total));
+					index.min(len - 1)
+				}).collect();
+
+				let beta = self.beta;
+				let weights: Vec<_> = indices.iter().map(|&i| {
+					(total/(len as f64 * self.tree.get(i))).powf(beta)
+				}).collect();
+				let max_weight = weights.iter().cloned().fold(0.0, f64::max);
+				indices.into_iter().zip(weights.into_iter().map(|w| w/max_weight)).collect()
+			}
+		}
+	}
+	/// Updates the priority of the transition at index using its latest TD error
+	pub fn update_priority(&mut self, index: usize, td_error: f64) {
+		let error = td_error.abs();
+		self.max_error = self.max_error.max(error);
+		self.set_error(index, error);
+	}
+
+	fn set_error(&mut self, index: usize, error: f64) {
+		self.errors[index] = error;
+		match self.sampling {
+			Sampling::Uniform => {},
+			Sampling::Proportional(alpha) => self.tree.set(index, (error + MIN_PRIORITY).powf(alpha)),
+			Sampling::Rank(_) => self.stale = true,
+		}
+	}
+	// Recomputes every rank based priority
+	fn rerank(&mut self) {
+		if let Sampling::Rank(alpha) = self.sampling {
+			let mut order: Vec<usize> = (0..self.len()).collect();
+			// Largest errors first, with NaN errors ranked last
+			order.sort_by(|&i, &j| {
+				let (x, y) = (self.errors[i], self.errors[j]);
+				match (x.is_nan(), y.is_nan()) {
+					(false, false) => y.partial_cmp(&x).unwrap(),
+					(x_nan, y_nan) => x_nan.cmp(&y_nan)
+				}
+			});
+
+			for (rank, index) in order.into_iter().enumerate() {
+				self.tree.set(index, (1.0/(rank + 1) as f64).powf(alpha));
+			}
+		}
+		self.stale = false;
+	}
+}
+
+/// A binary tree whose internal nodes hold the sum of their children
+///
+/// Allows sampling proportionally to the leaves and updating them in logarithmic time
+#[derive(Debug, Clone)]
+struct SumTree {
+	/// Index of the first leaf (the number of leaves)
+	leaves: usize,
+	/// The nodes of the tree. The root is at index 1 and the children of node i are 2i and 2i+1
+	nodes: Vec<f64>,
+}
+
+impl SumTree {
+	fn new(size: usize) -> SumTree {
+		let leaves = size.next_power_of_two();
+		SumTree {
+			leaves: leaves,
+			nodes: vec![0.0; 2*leaves]
+		}
+	}
+	fn total(&self) -> f64 {
+		self.nodes[1]
+	}
+	fn get(&self, index: usize) -> f64 {
+		self.nodes[index + self.leaves]
+	}
+	fn set(&mut self, index: usize, value: f64) {
+		let mut node = index + self.leaves;
+		self.nodes[node] = value;
+		while node > 1 {
+			node /= 2;
+			self.nodes[node] = self.nodes[2*node] + self.nodes[2*node + 1];
+		}
+	}
+	// Returns the index of the leaf containing value when the leaves are laid end to end
+	fn find(&self, mut value: f64) -> usize {
+		let mut node = 1;
+		while node < self.leaves {
+			let left = 2*node;
+			node = if value < self.nodes[left] || self.nodes[left + 1] <= 0.0 {
+				left
+			} else {
+				value -= self.nodes[left];
+				left + 1
+			};
+		}
+		node - self.leaves
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use rand::{SeedableRng, StdRng};
+
+	use environment::Finite;
+
+	use super::{ReplayBuffer, Sampling, SumTree};
+
+	fn filled(capacity: usize, num: u32, sampling: Sampling) -> ReplayBuffer<Finite, Finite> {
+		let mut replay = ReplayBuffer::new(capacity).sampling(sampling);
+		for i in 0..num {
+			replay.push((i, 0, 0.0, i + 1));
+		}
+		replay
+	}
+
+	#[test]
+	fn sum_tree_find() {
+		let mut tree = SumTree::new(3);
+		tree.set(0, 1.0);
+		tree.set(1, 2.0);
+		tree.set(2, 3.0);
+
+		assert_eq!(tree.total(), 6.0);
+		assert_eq!(tree.find(0.5), 0);
+		assert_eq!(tree.find(1.5), 1);
+		assert_eq!(tree.find(5.9), 2);
+	}
+	#[test]
+	fn push_overwrites_oldest() {
+		let replay = filled(3, 5, Sampling::Uniform);
+
+		assert_eq!(replay.len(), 3);
+		assert_eq!(replay.get(0).0, 3);
+		assert_eq!(replay.get(1).0, 4);
+		assert_eq!(replay.get(2).0, 2);
+	}
+	#[test]
+	fn proportional_prefers_large_errors() {
+		let mut rng = StdRng::from_seed(&[1]);
+		let mut replay = filled(4, 4, Sampling::Proportional(1.0));
+		for i in 0..4 {
+			replay.update_priority(i, if i == 2 {-10.0} else {0.0});
+		}
+
+		let samples = replay.sample(1000, &mut rng);
+		let count = samples.iter().filter(|&&(i, _)| i == 2).count();
+		assert!(count > 950, "index 2 sampled {} times", count);
+	}
+	#[test]
+	fn rank_prefers_large_errors() {
+		let mut rng = StdRng::from_seed(&[2]);
+		let mut replay = filled(4, 4, Sampling::Rank(1.0));
+		for i in 0..4 {
+			replay.update_priority(i, i as f64);
+		}
+
+		// Probabilities are proportional to 1/4, 1/3, 1/2, 1
+		let samples = replay.sample(2000, &mut rng);
+		let counts: Vec<_> = (0..4).map(|i| samples.iter().filter(|&&(s, _)| s == i).count()).collect();
+		assert!(counts[0] < counts[1] && counts[1] < counts[2] && counts[2] < counts[3], "counts were {:?}", counts);
+	}
+	#[test]
+	fn weights_undo_priorities() {
+		let mut rng = StdRng::from_seed(&[3]);
+		let mut replay = filled(2, 2, Sampling::Proportional(1.0));
+		replay.update_priority(0, 0.99);
+		replay.update_priority(1, 2.99);
+
+		// Index 1 is drawn 3 times as often, so it gets a third of the weight
+		for (i, weight) in replay.sample(20, &mut rng) {
+			let expected = if i == 0 {1.0} else {1.0/3.0};
+			assert!((weight - expected).abs() < 1e-9, "index {} has weight {}", i, weight);
+		}
+	}
+	#[test]
+	fn rank_resorts_periodically() {
+		let mut rng = StdRng::from_seed(&[4]);
+		let mut replay = filled(4, 4, Sampling::Rank(1.0));
+		replay.update_priority(0, 10.0);
+		replay.sample(1, &mut rng);
+		assert_eq!(replay.tree.get(0), 1.0);
+
+		// Not re-sorted until 4 more transitions are drawn
+		replay.update_priority(3, 20.0);
+		replay.sample(3, &mut rng);
+		assert_eq!(replay.tree.get(0), 1.0);
+		replay.sample(1, &mut rng);
+		assert_eq!(replay.tree.get(0), 0.5);
+		assert_eq!(replay.tree.get(3), 1.0);
+	}
+	#[test]
+	fn rank_puts_nan_errors_last() {
+		let mut rng = StdRng::from_seed(&[5]);
+		let mut replay = filled(3, 3, Sampling::Rank(1.0));
+		replay.update_priority(0, f64::NAN);
+		replay.update_priority(1, 1.0);
+		replay.update_priority(2, 2.0);
+		replay.sample(1, &mut rng);
+
+		assert_eq!(replay.tree.get(2), 1.0);
+		assert_eq!(replay.tree.get(1), 0.5);
+		assert_eq!(replay.tree.get(0), 1.0/3.0);
+	}
+}
\ No newline at end of file
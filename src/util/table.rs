@@ -6,7 +6,13 @@ use std::f64;
 
 use environment::FiniteSpace;
 
-use util::{QFunction, VFunction};
+use rand::Rng;
+
+use util::{QFunction, VFunction, TracedQFunction, FittableQFunction, TraceType, Stateful};
+use util::checkpoint::CheckpointError;
+
+// Traces smaller than this are treated as 0
+const MIN_TRACE: f64 = 1e-10;
 
 /// QTable
 ///
@@ -33,6 +39,66 @@ impl<S: FiniteSpace, A: FiniteSpace> QFunction<S, A> for QTable<S, A>
 	}
 }
 
+impl<S: FiniteSpace, A: FiniteSpace> TracedQFunction<S, A> for QTable<S, A>
+	where S::Element: Hash + Eq, A::Element: Hash + Eq {
+	/// The eligibility of every (state, action) pair with a nonzero trace
+	type Trace = HashMap<(S::Element, A::Element), f64>;
+
+	fn new_trace(&self) -> Self::Trace {
+		HashMap::new()
+	}
+	fn decay_trace(&self, trace: &mut Self::Trace, factor: f64) {
+		for e in trace.values_mut() {
+			*e *= factor;
+		}
+		// Forget pairs whose traces have faded away so the trace doesn't grow forever
+		trace.retain(|_, e| e.abs() > MIN_TRACE);
+	}
+	fn visit(&self, trace: &mut Self::Trace, state: &S::Element, action: &A::Element, trace_type: TraceType, alpha: f64) {
+		let e = trace.entry((state.clone(), action.clone())).or_insert(0.0);
+		*e = match trace_type {
+			TraceType::Accumulating => *e + 1.0,
+			TraceType::Replacing => 1.0,
+			TraceType::Dutch => *e + 1.0 - alpha * *e,
+		};
+	}
+	fn update_traced(&mut self, trace: &Self::Trace, step: f64) {
+		for (pair, e) in trace {
+			*self.map.entry(pair.clone()).or_insert(0.0) += step*e;
+		}
+	}
+}
+
+impl<S: FiniteSpace, A: FiniteSpace> FittableQFunction<S, A> for QTable<S, A>
+	where S::Element: Hash + Eq, A::Element: Hash + Eq {
+	/// Sets the value of every pair in data to the average of its targets. Other pairs keep their values
+	fn fit<R: Rng>(&mut self, data: &[(S::Element, A::Element, f64)], _: &mut R) {
+		let mut sums = HashMap::new();
+		for &(ref s, ref a, target) in data {
+			let sum = sums.entry((s.clone(), a.clone())).or_insert((0.0, 0));
+			sum.0 += target;
+			sum.1 += 1;
+		}
+		for (pair, (total, count)) in sums {
+			self.map.insert(pair, total/(count as f64));
+		}
+	}
+}
+
+impl<S: FiniteSpace, A: FiniteSpace> Stateful for QTable<S, A>
+	where S::Element: Hash + Eq, A::Element: Hash + Eq {
+	/// Every stored (state, action, value) triple
+	type State = Vec<(S::Element, A::Element, f64)>;
+
+	fn get_state(&self) -> Self::State {
+		self.map.iter().map(|(&(ref s, ref a), &v)| (s.clone(), a.clone(), v)).collect()
+	}
+	fn set_state(&mut self, state: Self::State) -> Result<(), CheckpointError> {
+		self.map = state.into_iter().map(|(s, a, v)| ((s, a), v)).collect();
+		Ok(())
+	}
+}
+
 impl<S: FiniteSpace, A: FiniteSpace> QTable<S, A> 
 	where S::Element: Hash + Eq, A::Element: Hash + Eq {
 	/// Returns a new QTable where all values are initialized to 0
@@ -65,4 +131,26 @@ impl<S: FiniteSpace> VFunction<S> for VTable<S> where S::Element: Hash + Eq {
 		let old_val = self.eval(state);
 		self.map.insert(state.clone(), old_val + alpha*(new_val - old_val));
 	}
+}
+
+impl<S: FiniteSpace> Stateful for VTable<S> where S::Element: Hash + Eq {
+	/// Every stored (state, value) pair
+	type State = Vec<(S::Element, f64)>;
+
+	fn get_state(&self) -> Self::State {
+		self.map.iter().map(|(s, &v)| (s.clone(), v)).collect()
+	}
+	fn set_state(&mut self, state: Self::State) -> Result<(), CheckpointError> {
+		self.map = state.into_iter().collect();
+		Ok(())
+	}
+}
+
+impl<S: FiniteSpace> VTable<S> where S::Element: Hash + Eq {
+	/// Returns a new VTable where all values are initialized to 0
+	pub fn new() -> VTable<S> {
+		VTable {
+			map: HashMap::new()
+		}
+	}
 }
\ No newline at end of file
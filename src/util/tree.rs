@@ -0,0 +1,276 @@
+//! Tree Module
+//!
+//! Regression trees, used to fit Q-functions to datasets
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::cmp::Ordering;
+use std::f64;
+
+use rand::Rng;
+
+use environment::{Space, FiniteSpace};
+
+use util::{QFunction, FittableQFunction, Feature, Stateful};
+use util::checkpoint::CheckpointError;
+
+/// A node of a regression tree
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum TreeNode {
+	/// Predicts the stored value
+	Leaf(f64),
+	/// Sends states whose feature is below threshold to the left node, and the rest to the right node
+	Split {
+		/// Index of the feature compared
+		feature: usize,
+		/// Value the feature is compared to
+		threshold: f64,
+		/// Index of the node used when the feature is below threshold
+		left: usize,
+		/// Index of the node used otherwise
+		right: usize,
+	},
+}
+
+/// How regression trees choose where to split
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SplitRule {
+	/// Tries every midpoint between consecutive values of every feature, as in [CART](https://www.wikiwand.com/en/Decision_tree_learning)
+	Best,
+	/// Tries one uniformly random threshold per feature, as in [Extra-Trees](http://www.montefiore.ulg.ac.be/~ernst/uploads/news/id63/extremely-randomized-trees.pdf)
+	Random,
+}
+
+/// The learned state of a QForest
+///
+/// Features can not be saved, so they are only recorded (as their Debug output) for reference
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct QForestState<E> {
+	/// Descriptions of the features shared by every action
+	pub features: Vec<String>,
+	/// The nodes of every tree used for each action (roots first)
+	pub trees: Vec<(E, Vec<Vec<TreeNode>>)>,
+}
+
+/// Represents an ensemble of regression trees for each action
+///
+/// The value of a (state, action) pair is the average prediction of the action's trees on the state's
+/// features. Trees are grown until their leaves hold fewer than min_split samples or can not be split.
+/// Updates move the leaves a state falls in, so the trees can be fine-tuned but never grow.
+#[derive(Debug, Clone)]
+pub struct QForest<S: Space, A: FiniteSpace> where A::Element: Hash + Eq {
+	/// Every tree uses the same set of features
+	features: Vec<Box<Feature<S, f64>>>,
+	actions: Vec<A::Element>,
+	/// The trees used for each action
+	trees: HashMap<A::Element, Vec<Vec<TreeNode>>>,
+	/// The number of trees fit for each action
+	num_trees: usize,
+	/// Nodes with fewer samples than this become leaves
+	min_split: usize,
+	/// How nodes are split
+	split_rule: SplitRule,
+}
+
+impl<S: Space, A: FiniteSpace> QFunction<S, A> for QForest<S, A> where A::Element: Hash + Eq {
+	fn eval(&self, state: &S::Element, action: &A::Element) -> f64 {
+		match self.trees.get(action) {
+			Some(trees) => {
+				let x = self.extract(state);
+				trees.iter().map(|tree| tree[leaf(tree, &x)].value()).sum::<f64>() / (trees.len() as f64)
+			},
+			None => 0.0
+		}
+	}
+	fn update(&mut self, state: &S::Element, action: &A::Element, new_val: f64, alpha: f64) {
+		let step = alpha*(new_val - self.eval(state, action));
+		let x = self.extract(state);
+		let trees = self.trees.entry(action.clone()).or_insert_with(|| vec![vec![TreeNode::Leaf(0.0)]]);
+		// Every tree moves by the same amount, so the average does too
+		for tree in trees.iter_mut() {
+			let index = leaf(tree, &x);
+			tree[index] = TreeNode::Leaf(tree[index].value() + step);
+		}
+	}
+}
+
+impl<S: Space, A: FiniteSpace> FittableQFunction<S, A> for QForest<S, A> where A::Element: Hash + Eq {
+	/// Grows new trees for every action in data. Other actions keep their trees
+	fn fit<R: Rng>(&mut self, data: &[(S::Element, A::Element, f64)], rng: &mut R) {
+		for a in self.actions.clone() {
+			let (xs, ys): (Vec<_>, Vec<_>) = data.iter().filter(|d| d.1 == a)
+														.map(|&(ref s, _, target)| (self.extract(s), target))
+														.unzip();
+			if ys.is_empty() {
+				continue;
+			}
+
+			let trees = (0..self.num_trees).map(|_| {
+				let mut tree = Vec::new();
+				self.grow(&mut tree, &xs, &ys, (0..ys.len()).collect(), rng);
+				tree
+			}).collect();
+			self.trees.insert(a, trees);
+		}
+	}
+}
+
+impl<S: Space, A: FiniteSpace> Stateful for QForest<S, A> where A::Element: Hash + Eq {
+	type State = QForestState<A::Element>;
+
+	fn get_state(&self) -> QForestState<A::Element> {
+		let trees = self.actions.iter()
+								.filter(|a| self.trees.contains_key(a))
+								.map(|a| (a.clone(), self.trees[a].clone()))
+								.collect();
+		QForestState {
+			features: self.features.iter().map(|feat| format!("{:?}", feat)).collect(),
+			trees: trees
+		}
+	}
+	fn set_state(&mut self, state: QForestState<A::Element>) -> Result<(), CheckpointError> {
+		for &(ref a, ref trees) in &state.trees {
+			if !self.actions.contains(a) {
+				return Err(CheckpointError::Mismatch(format!("unknown action {:?}", a)));
+			}
+			for tree in trees {
+				let valid = !tree.is_empty() && tree.iter().all(|node| match *node {
+					TreeNode::Leaf(_) => true,
+					TreeNode::Split {feature, left, right, ..} => {
+						feature < self.features.len() && left < tree.len() && right < tree.len()
+					}
+				});
+				if !valid {
+					return Err(CheckpointError::Mismatch(format!("invalid tree for action {:?}", a)));
+				}
+			}
+		}
+
+		self.trees = state.trees.into_iter().collect();
+		Ok(())
+	}
+}
+
+impl<S: Space, A: FiniteSpace> QForest<S, A> where A::Element: Hash + Eq {
+	/// Creates a new, empty QForest fitting 10 Extra-Trees per action
+	pub fn new(action_space: &A) -> QForest<S, A> {
+		QForest {
+			features: Vec::new(),
+			actions: action_space.enumerate(),
+			trees: HashMap::new(),
+			num_trees: 10,
+			min_split: 2,
+			split_rule: SplitRule::Random
+		}
+	}
+	/// Adds feat to list of features. Should not be called after any calls to eval, update or fit
+	pub fn add(&mut self, feat: Box<Feature<S, f64>>) {
+		self.features.push(feat);
+	}
+	/// Sets num_trees field of self
+	pub fn num_trees(mut self, num_trees: usize) -> QForest<S, A> {
+		assert!(num_trees > 0, "num_trees must be positive");
+
+		self.num_trees = num_trees;
+		self
+	}
+	/// Sets min_split field of self
+	pub fn min_split(mut self, min_split: usize) -> QForest<S, A> {
+		self.min_split = min_split;
+		self
+	}
+	/// Sets split_rule field of self
+	pub fn split_rule(mut self, split_rule: SplitRule) -> QForest<S, A> {
+		self.split_rule = split_rule;
+		self
+	}
+
+	fn extract(&self, state: &S::Element) -> Vec<f64> {
+		self.features.iter().map(|feat| feat.extract(state)).collect()
+	}
+	// Adds a subtree fit to the given samples to tree, returning the index of its root
+	fn grow<R: Rng>(&self, tree: &mut Vec<TreeNode>, xs: &[Vec<f64>], ys: &[f64], samples: Vec<usize>, rng: &mut R) -> usize {
+		let index = tree.len();
+		let mean = samples.iter().map(|&i| ys[i]).sum::<f64>() / (samples.len() as f64);
+		tree.push(TreeNode::Leaf(mean));
+
+		if samples.len() < self.min_split || samples.iter().all(|&i| ys[i] == ys[samples[0]]) {
+			return index;
+		}
+		if let Some((feature, threshold)) = self.choose_split(xs, ys, &samples, rng) {
+			let (left, right): (Vec<_>, Vec<_>) = samples.into_iter().partition(|&i| xs[i][feature] < threshold);
+			let left = self.grow(tree, xs, ys, left, rng);
+			let right = self.grow(tree, xs, ys, right, rng);
+			tree[index] = TreeNode::Split {
+				feature: feature,
+				threshold: threshold,
+				left: left,
+				right: right
+			};
+		}
+		index
+	}
+	// The (feature, threshold) split of samples with the lowest squared error, if there are any
+	fn choose_split<R: Rng>(&self, xs: &[Vec<f64>], ys: &[f64], samples: &[usize], rng: &mut R) -> Option<(usize, f64)> {
+		let mut best = None;
+		let mut best_err = f64::INFINITY;
+		for feature in 0..self.features.len() {
+			let mut sorted: Vec<_> = samples.iter().map(|&i| (xs[i][feature], ys[i])).collect();
+			sorted.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+			let (min, max) = (sorted[0].0, sorted[sorted.len()-1].0);
+			if min >= max {
+				continue;
+			}
+
+			let thresholds = match self.split_rule {
+				SplitRule::Best => sorted.windows(2).filter(|w| w[0].0 < w[1].0)
+													.map(|w| (w[0].0 + w[1].0)/2.0)
+													.collect(),
+				SplitRule::Random => vec![rng.gen_range(min, max)],
+			};
+
+			// Sweep the sorted samples, moving them to the left side as each threshold passes them
+			let total: f64 = sorted.iter().map(|s| s.1).sum();
+			let total_sq: f64 = sorted.iter().map(|s| s.1*s.1).sum();
+			let (mut sum, mut sum_sq, mut count) = (0.0, 0.0, 0);
+			for threshold in thresholds {
+				while count < sorted.len() && sorted[count].0 < threshold {
+					sum += sorted[count].1;
+					sum_sq += sorted[count].1*sorted[count].1;
+					count += 1;
+				}
+				if count == 0 || count == sorted.len() {
+					continue;
+				}
+
+				let rest = (sorted.len() - count) as f64;
+				let err = (sum_sq - sum*sum/(count as f64)) + (total_sq - sum_sq - (total - sum).powi(2)/rest);
+				if err < best_err {
+					best = Some((feature, threshold));
+					best_err = err;
+				}
+			}
+		}
+		best
+	}
+}
+
+impl TreeNode {
+	fn value(&self) -> f64 {
+		match *self {
+			TreeNode::Leaf(val) => val,
+			TreeNode::Split {..} => panic!("only leaves have values"),
+		}
+	}
+}
+
+// Index of the leaf of tree that x falls in
+fn leaf(tree: &[TreeNode], x: &[f64]) -> usize {
+	let mut index = 0;
+	while let TreeNode::Split {feature, threshold, left, right} = tree[index] {
+		index = if x[feature] < threshold {left} else {right};
+	}
+	index
+}
\ No newline at end of file
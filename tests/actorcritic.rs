@@ -0,0 +1,53 @@
+// Checks that the actor-critic trainer works with both discrete and continuous action agents
+
+extern crate renforce as re;
+extern crate rand;
+
+mod common;
+
+use rand::{SeedableRng, StdRng};
+
+use re::prelude::*;
+
+use re::environment::Range;
+
+use re::trainer::ActorCritic;
+
+use re::util::network::{VNetwork, QNetwork, Activation};
+use re::util::graddesc::GradientDesc;
+
+use common::{Walk, Aim, position};
+
+#[test]
+fn policy_agent_actor_critic() {
+	let mut env = Walk::new();
+	let mut rng = StdRng::from_seed(&[1]);
+
+	let log_func = QNetwork::new(position(), &[(8, Activation::Tanh)], &env.action_space(), &mut rng);
+	let mut agent = PolicyAgent::new(env.action_space(), log_func, 1.0);
+	let critic: VNetwork<f64, Vec<Range>> = VNetwork::new(position(), &[(8, Activation::Tanh)], 1, &mut rng);
+	let mut trainer = ActorCritic::default(critic, GradientDesc).lr(0.05).iters(30)
+																.eval_period(TimePeriod::EPISODES(2));
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	let rights = (0..100).filter(|_| agent.get_action(&vec![0.0], &mut rng) == 1).count();
+	assert!(rights > 50, "stepped right {} times out of 100", rights);
+}
+
+#[test]
+fn gaussian_agent_actor_critic() {
+	let mut env = Aim;
+	let mut rng = StdRng::from_seed(&[2]);
+
+	let mean_func: VNetwork<f64, Vec<Range>> = VNetwork::new(position(), &[(4, Activation::Tanh)], 1, &mut rng);
+	let mut agent: GaussianAgent<f64, f64, Vec<Range>, Vec<Range>, _> = GaussianAgent::new(mean_func, 0.3);
+	let critic: VNetwork<f64, Vec<Range>> = VNetwork::new(position(), &[], 1, &mut rng);
+	let mut trainer = ActorCritic::default(critic, GradientDesc).lr(0.01).iters(200)
+																.eval_period(TimePeriod::EPISODES(20));
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	let mean = agent.mean_func.apply(&vec![1.0])[0];
+	assert!((mean - 0.5).abs() < 0.2, "mean action was {}", mean);
+	// The critic should have learned the expected reward of the final policy, about -0.09
+	assert!(trainer.get_critic().eval(&vec![1.0]) < 0.0);
+}
\ No newline at end of file
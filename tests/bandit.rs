@@ -7,7 +7,7 @@
 extern crate renforce as re;
 extern crate rand;
 
-use rand::thread_rng;
+use rand::{thread_rng, SeedableRng, StdRng};
 use rand::distributions::IndependentSample;
 use rand::distributions::normal::Normal;
 
@@ -17,18 +17,20 @@ use re::environment::Finite;
 
 use re::trainer::*;
 
-use re::model::PlainModel;
+use re::model::{PlainModel, DeterministicTable};
 
 use re::util::table::QTable;
 use re::util::approx::QLinear;
 use re::util::chooser::Uniform;
 use re::util::graddesc::GradientDesc;
+use re::util::replay::{ReplayBuffer, Sampling};
 
 const SOLVED_VALUE: f64 = 9000.0;
 const TRAINING_ITERS: usize = 100;
 
 struct NArmedBandit {
-	arms: Vec<Normal>
+	arms: Vec<Normal>,
+	rng: StdRng
 }
 
 impl Environment for NArmedBandit {
@@ -40,10 +42,9 @@ impl Environment for NArmedBandit {
 		Finite::new(self.num_arms() as u32)
 	}
 	fn step(&mut self, action: &u32) -> Observation<()> {
-		let mut rng = thread_rng();
 		let action = *action as usize;
 		let reward = if action < self.arms.len() {
-			self.arms[action].ind_sample(&mut rng)
+			self.arms[action].ind_sample(&mut self.rng)
 		} else {
 			0.0
 		};
@@ -66,7 +67,11 @@ impl Environment for NArmedBandit {
 
 impl NArmedBandit {
 	pub fn new() -> NArmedBandit {
-		NArmedBandit{arms: vec![]}
+		NArmedBandit{arms: vec![], rng: StdRng::new().unwrap()}
+	}
+	pub fn seed(mut self, seed: usize) -> NArmedBandit {
+		self.rng = StdRng::from_seed(&[seed]);
+		self
 	}
 	pub fn add(&mut self, mean: f64, var: f64) {
 		self.arms.push(Normal::new(mean, var));
@@ -123,12 +128,13 @@ fn test_env() -> NArmedBandit {
 #[test]
 fn qlearner_bandit() {
 	let mut env = test_env();
+	let mut rng = thread_rng();
 
 	let q_func = QTable::new();
 	let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.2, Uniform);
 
 	let mut trainer = QLearner::default(env.action_space()).train_period(TimePeriod::TIMESTEPS(10000));
-	trainer.train(&mut agent, &mut env);
+	trainer.train(&mut agent, &mut env, &mut rng);
 
 	let mut obs = env.reset();
 	let mut iters = TRAINING_ITERS;
@@ -136,7 +142,7 @@ fn qlearner_bandit() {
 
 	agent.set_epsilon(0.05);
 	while iters != 0 {
-		let action = agent.get_action(&obs.state);
+		let action = agent.get_action(&obs.state, &mut rng);
 		obs = env.step(&action);
 
 		reward += obs.reward;
@@ -150,12 +156,13 @@ fn qlearner_bandit() {
 #[test]
 fn sarsalearner_bandit() {
 	let mut env = test_env();
+	let mut rng = thread_rng();
 
 	let q_func = QTable::new();
 	let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.2, Uniform);
 
 	let mut trainer = SARSALearner::default().train_period(TimePeriod::TIMESTEPS(10000));
-	trainer.train(&mut agent, &mut env);
+	trainer.train(&mut agent, &mut env, &mut rng);
 
 	let mut obs = env.reset();
 	let mut iters = TRAINING_ITERS;
@@ -163,7 +170,7 @@ fn sarsalearner_bandit() {
 
 	agent.set_epsilon(0.05);
 	while iters != 0 {
-		let action = agent.get_action(&obs.state);
+		let action = agent.get_action(&obs.state, &mut rng);
 		obs = env.step(&action);
 
 		reward += obs.reward;
@@ -174,16 +181,245 @@ fn sarsalearner_bandit() {
 	assert!(reward >= SOLVED_VALUE);
 }
 
+#[test]
+fn qlearner_replay_bandit() {
+	let mut env = test_env();
+	let mut rng = thread_rng();
+
+	let q_func = QTable::new();
+	let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.2, Uniform);
+
+	let replay = ReplayBuffer::new(1000).sampling(Sampling::Proportional(0.6));
+	let mut trainer = QLearner::default(env.action_space()).train_period(TimePeriod::TIMESTEPS(3000))
+														   .replay(replay, 8);
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	let mut obs = env.reset();
+	let mut iters = TRAINING_ITERS;
+	let mut reward = 0.0;
+
+	agent.set_epsilon(0.05);
+	while iters != 0 {
+		let action = agent.get_action(&obs.state, &mut rng);
+		obs = env.step(&action);
+
+		reward += obs.reward;
+		iters -= 1;
+	}
+
+	println!("Q Learning (prioritized replay) reward: {}", reward);
+	assert!(reward >= SOLVED_VALUE);
+}
+
+#[test]
+fn sarsalearner_replay_bandit() {
+	let mut env = test_env();
+	let mut rng = thread_rng();
+
+	let q_func = QTable::new();
+	let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.2, Uniform);
+
+	let replay = ReplayBuffer::new(1000).sampling(Sampling::Rank(0.7));
+	let mut trainer = SARSALearner::default().train_period(TimePeriod::TIMESTEPS(3000))
+											 .replay(replay, 8);
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	let mut obs = env.reset();
+	let mut iters = TRAINING_ITERS;
+	let mut reward = 0.0;
+
+	agent.set_epsilon(0.05);
+	while iters != 0 {
+		let action = agent.get_action(&obs.state, &mut rng);
+		obs = env.step(&action);
+
+		reward += obs.reward;
+		iters -= 1;
+	}
+
+	println!("SARSA (rank based replay) reward: {}", reward);
+	assert!(reward >= SOLVED_VALUE);
+}
+
+#[test]
+fn expected_sarsa_bandit() {
+	let mut env = test_env();
+	let mut rng = thread_rng();
+
+	let q_func = QTable::new();
+	let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.2, Uniform);
+
+	let mut trainer = ExpectedSARSA::default(env.action_space()).train_period(TimePeriod::TIMESTEPS(10000));
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	let mut obs = env.reset();
+	let mut iters = TRAINING_ITERS;
+	let mut reward = 0.0;
+
+	agent.set_epsilon(0.05);
+	while iters != 0 {
+		let action = agent.get_action(&obs.state, &mut rng);
+		obs = env.step(&action);
+
+		reward += obs.reward;
+		iters -= 1;
+	}
+
+	println!("Expected SARSA reward: {}", reward);
+	assert!(reward >= SOLVED_VALUE);
+}
+
+#[test]
+fn nstep_qlearner_bandit() {
+	let mut env = test_env();
+	let mut rng = thread_rng();
+
+	let q_func = QTable::new();
+	let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.2, Uniform);
+
+	let mut trainer = NStepQLearner::default(env.action_space()).train_period(TimePeriod::TIMESTEPS(10000));
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	let mut obs = env.reset();
+	let mut iters = TRAINING_ITERS;
+	let mut reward = 0.0;
+
+	agent.set_epsilon(0.05);
+	while iters != 0 {
+		let action = agent.get_action(&obs.state, &mut rng);
+		obs = env.step(&action);
+
+		reward += obs.reward;
+		iters -= 1;
+	}
+
+	println!("n-step Q Learning reward: {}", reward);
+	assert!(reward >= SOLVED_VALUE);
+}
+
+#[test]
+fn nstep_sarsa_bandit() {
+	let mut env = test_env();
+	let mut rng = thread_rng();
+
+	let q_func = QTable::new();
+	let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.2, Uniform);
+
+	let mut trainer = NStepSARSA::default().train_period(TimePeriod::TIMESTEPS(10000));
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	let mut obs = env.reset();
+	let mut iters = TRAINING_ITERS;
+	let mut reward = 0.0;
+
+	agent.set_epsilon(0.05);
+	while iters != 0 {
+		let action = agent.get_action(&obs.state, &mut rng);
+		obs = env.step(&action);
+
+		reward += obs.reward;
+		iters -= 1;
+	}
+
+	println!("n-step SARSA reward: {}", reward);
+	assert!(reward >= SOLVED_VALUE);
+}
+
+#[test]
+fn sarsa_lambda_bandit() {
+	let mut env = test_env();
+	let mut rng = thread_rng();
+
+	let q_func = QTable::new();
+	let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.2, Uniform);
+
+	let mut trainer = SARSALambda::default().lambda(0.5).train_period(TimePeriod::TIMESTEPS(10000));
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	let mut obs = env.reset();
+	let mut iters = TRAINING_ITERS;
+	let mut reward = 0.0;
+
+	agent.set_epsilon(0.05);
+	while iters != 0 {
+		let action = agent.get_action(&obs.state, &mut rng);
+		obs = env.step(&action);
+
+		reward += obs.reward;
+		iters -= 1;
+	}
+
+	println!("SARSA(lambda) reward: {}", reward);
+	assert!(reward >= SOLVED_VALUE);
+}
+
+#[test]
+fn watkins_bandit() {
+	let mut env = test_env();
+	let mut rng = thread_rng();
+
+	let q_func = QTable::new();
+	let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.2, Uniform);
+
+	let mut trainer = WatkinsQLambda::default(env.action_space()).train_period(TimePeriod::TIMESTEPS(10000));
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	let mut obs = env.reset();
+	let mut iters = TRAINING_ITERS;
+	let mut reward = 0.0;
+
+	agent.set_epsilon(0.05);
+	while iters != 0 {
+		let action = agent.get_action(&obs.state, &mut rng);
+		obs = env.step(&action);
+
+		reward += obs.reward;
+		iters -= 1;
+	}
+
+	println!("Watkins Q(lambda) reward: {}", reward);
+	assert!(reward >= SOLVED_VALUE);
+}
+
+#[test]
+fn doubleq_bandit() {
+	let mut env = test_env();
+	let mut rng = thread_rng();
+
+	let q_func = QTable::new();
+	let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.2, Uniform);
+
+	let mut trainer = DoubleQLearner::default(env.action_space(), QTable::new()).train_period(TimePeriod::TIMESTEPS(10000));
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	let mut obs = env.reset();
+	let mut iters = TRAINING_ITERS;
+	let mut reward = 0.0;
+
+	agent.set_epsilon(0.05);
+	while iters != 0 {
+		let action = agent.get_action(&obs.state, &mut rng);
+		obs = env.step(&action);
+
+		reward += obs.reward;
+		iters -= 1;
+	}
+
+	println!("Double Q Learning reward: {}", reward);
+	assert!(reward >= SOLVED_VALUE);
+}
+
 #[test]
 fn cem_bandit() {
 	let mut env = test_env();
+	let mut rng = thread_rng();
 
 	let q_func = QLinear::default(&env.action_space());
 	let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.2, Uniform);
 
 	let mut trainer = CrossEntropy::default().eval_period(TimePeriod::TIMESTEPS(50))
 											 .iters(30).elite(0.1);
-	trainer.train(&mut agent, &mut env);
+	trainer.train(&mut agent, &mut env, &mut rng);
 
 	let mut obs = env.reset();
 	let mut iters = TRAINING_ITERS;
@@ -191,7 +427,7 @@ fn cem_bandit() {
 
 	agent.set_epsilon(0.05);
 	while iters != 0 {
-		let action = agent.get_action(&obs.state);
+		let action = agent.get_action(&obs.state, &mut rng);
 		obs = env.step(&action);
 
 		reward += obs.reward;
@@ -202,16 +438,46 @@ fn cem_bandit() {
 	assert!(reward >= SOLVED_VALUE);
 }
 
+#[test]
+fn cmaes_bandit() {
+	let mut env = test_env();
+	let mut rng = thread_rng();
+
+	let q_func = QLinear::default(&env.action_space());
+	let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.2, Uniform);
+
+	let mut trainer = CMAES::default().eval_period(TimePeriod::TIMESTEPS(50))
+									   .num_samples(100).iters(30);
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	let mut obs = env.reset();
+	let mut iters = TRAINING_ITERS;
+	let mut reward = 0.0;
+
+	agent.set_epsilon(0.05);
+	while iters != 0 {
+		let action = agent.get_action(&obs.state, &mut rng);
+		obs = env.step(&action);
+
+		reward += obs.reward;
+		iters -= 1;
+	}
+
+	println!("CMA-ES reward: {}", reward);
+	assert!(reward >= SOLVED_VALUE);
+}
+
 #[test]
 fn dyna_bandit() {
 	let mut env = test_env();
+	let mut rng = thread_rng();
 
 	let q_func = QTable::new();
 	let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.2, Uniform);
 	let model = PlainModel::new();
 
 	let mut trainer = DynaQ::default(env.action_space(), model).train_period(TimePeriod::TIMESTEPS(500));
-	trainer.train(&mut agent, &mut env);
+	trainer.train(&mut agent, &mut env, &mut rng);
 
 	let mut obs = env.reset();
 	let mut iters = TRAINING_ITERS;
@@ -219,7 +485,7 @@ fn dyna_bandit() {
 
 	agent.set_epsilon(0.05);
 	while iters != 0 {
-		let action = agent.get_action(&obs.state);
+		let action = agent.get_action(&obs.state, &mut rng);
 		obs = env.step(&action);
 
 		reward += obs.reward;
@@ -230,9 +496,68 @@ fn dyna_bandit() {
 	assert!(reward >= SOLVED_VALUE);
 }
 
+#[test]
+fn dyna_deterministic_bandit() {
+	let mut env = test_env();
+	let mut rng = thread_rng();
+
+	let q_func = QTable::new();
+	let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.2, Uniform);
+	let model = DeterministicTable::new();
+
+	let mut trainer = DynaQ::default(env.action_space(), model).train_period(TimePeriod::TIMESTEPS(1000));
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	let mut obs = env.reset();
+	let mut iters = TRAINING_ITERS;
+	let mut reward = 0.0;
+
+	agent.set_epsilon(0.05);
+	while iters != 0 {
+		let action = agent.get_action(&obs.state, &mut rng);
+		obs = env.step(&action);
+
+		reward += obs.reward;
+		iters -= 1;
+	}
+
+	println!("Dyna-Q (deterministic model) reward: {}", reward);
+	assert!(reward >= SOLVED_VALUE);
+}
+
+#[test]
+fn sweeping_bandit() {
+	let mut env = test_env().seed(1);
+	let mut rng = StdRng::from_seed(&[3]);
+
+	let q_func = QTable::new();
+	let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.2, Uniform);
+	let model = PlainModel::new();
+
+	let mut trainer = PrioritizedSweeping::default(env.action_space(), model).train_period(TimePeriod::TIMESTEPS(500));
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	let mut obs = env.reset();
+	let mut iters = TRAINING_ITERS;
+	let mut reward = 0.0;
+
+	agent.set_epsilon(0.05);
+	while iters != 0 {
+		let action = agent.get_action(&obs.state, &mut rng);
+		obs = env.step(&action);
+
+		reward += obs.reward;
+		iters -= 1;
+	}
+
+	println!("Prioritized sweeping reward: {}", reward);
+	assert!(reward >= SOLVED_VALUE);
+}
+
 #[test]
 fn fqi_bandit() {
 	let mut env = test_env();
+	let mut rng = thread_rng();
 
 	let q_func: QTable<(), Finite> = QTable::new();
 	let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.2, Uniform);
@@ -242,13 +567,13 @@ fn fqi_bandit() {
 	// Collect transitions
 	let mut transitions = Vec::new();
 	for _ in 0..1000 {
-		let action = agent.get_action(&());
+		let action = agent.get_action(&(), &mut rng);
 		let obs = env.step(&action);
 
 		transitions.push(((), action, obs.reward, obs.state));
 	}
 
-	trainer.train(&mut agent, transitions);
+	trainer.train(&mut agent, transitions, &mut rng);
 
 	let mut obs = env.reset();
 	let mut iters = TRAINING_ITERS;
@@ -256,7 +581,7 @@ fn fqi_bandit() {
 
 	agent.set_epsilon(0.05);
 	while iters != 0 {
-		let action = agent.get_action(&obs.state);
+		let action = agent.get_action(&obs.state, &mut rng);
 		obs = env.step(&action);
 
 		reward += obs.reward;
@@ -270,22 +595,23 @@ fn fqi_bandit() {
 #[test]
 fn lspi_bandit() {
 	let mut env = test_env();
+	let mut rng = thread_rng();
 
 	let q_func: QLinear<f64, (), Finite> = QLinear::default(&env.action_space());
 	let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.2, Uniform);
 
-	let mut trainer = LSPolicyIteration::default();
+	let mut trainer = LSPolicyIteration::default(env.action_space());
 
 	// Collect transitions
 	let mut transitions = Vec::new();
 	for _ in 0..5000 {
-		let action = agent.get_action(&());
+		let action = agent.get_action(&(), &mut rng);
 		let obs = env.step(&action);
 
 		transitions.push(((), action, obs.reward, obs.state));
 	}
 
-	trainer.train(&mut agent, transitions);
+	trainer.train(&mut agent, transitions, &mut rng);
 
 	let mut obs = env.reset();
 	let mut iters = TRAINING_ITERS;
@@ -293,7 +619,7 @@ fn lspi_bandit() {
 
 	agent.set_epsilon(0.05);
 	while iters != 0 {
-		let action = agent.get_action(&obs.state);
+		let action = agent.get_action(&obs.state, &mut rng);
 		obs = env.step(&action);
 
 		reward += obs.reward;
@@ -307,19 +633,20 @@ fn lspi_bandit() {
 #[test]
 fn pg_bandit() {
 	let mut env = test_env();
+	let mut rng = thread_rng();
 
 	let q_func = QLinear::default(&env.action_space());
 	let mut agent = PolicyAgent::new(env.action_space(), q_func, 0.01);
 
-	let mut trainer = PolicyGradient::default(GradientDesc).eval_period(TimePeriod::TIMESTEPS(500));
-	trainer.train(&mut agent, &mut env);
+	let mut trainer = PolicyGradient::default(GradientDesc).lr(0.00001).eval_period(TimePeriod::TIMESTEPS(500));
+	trainer.train(&mut agent, &mut env, &mut rng);
 
 	let mut obs = env.reset();
 	let mut iters = TRAINING_ITERS;
 	let mut reward = 0.0;
 
 	while iters != 0 {
-		let action = agent.get_action(&obs.state);
+		let action = agent.get_action(&obs.state, &mut rng);
 		obs = env.step(&action);
 
 		reward += obs.reward;
@@ -333,12 +660,13 @@ fn pg_bandit() {
 #[test]
 fn nes_bandit() {
 	let mut env = test_env();
+	let mut rng = thread_rng();
 
 	let q_func = QLinear::default(&env.action_space());
 	let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.2, Uniform);
 
 	let mut trainer = NaturalEvo::default().eval_period(TimePeriod::TIMESTEPS(5));
-	trainer.train(&mut agent, &mut env);
+	trainer.train(&mut agent, &mut env, &mut rng);
 
 	let mut obs = env.reset();
 	let mut iters = TRAINING_ITERS;
@@ -346,7 +674,7 @@ fn nes_bandit() {
 
 	agent.set_epsilon(0.05);
 	while iters != 0 {
-		let action = agent.get_action(&obs.state);
+		let action = agent.get_action(&obs.state, &mut rng);
 		obs = env.step(&action);
 
 		reward += obs.reward;
@@ -0,0 +1,289 @@
+// Tests saving and restoring learned agents
+// The save/load tests only run when the serialize feature is enabled
+
+extern crate renforce as re;
+extern crate rand;
+
+use rand::{SeedableRng, StdRng};
+
+use re::prelude::*;
+
+use re::environment::{Finite, Range};
+
+use re::trainer::{QLearner, CrossEntropy, CMAES, DynaQ, PrioritizedSweeping};
+
+use re::model::PlainModel;
+
+use re::util::Stateful;
+use re::util::table::QTable;
+use re::util::approx::QLinear;
+use re::util::chooser::Uniform;
+use re::util::graddesc::GradDescMomentum;
+use re::util::feature::IFeature;
+use re::util::checkpoint::CheckpointError;
+
+struct NumberChooser;
+
+impl Environment for NumberChooser {
+	type State = Finite;
+	type Action = Finite;
+
+	fn state_space(&self) -> Finite {
+		Finite::new(3)
+	}
+	fn action_space(&self) -> Finite {
+		Finite::new(10)
+	}
+	fn step(&mut self, action: &u32) -> Observation<Finite> {
+		Observation {
+			state: *action % 3,
+			reward: if *action%2 == 1 {1.0} else {-1.0},
+			done: false
+		}
+	}
+	fn reset(&mut self) -> Observation<Finite> {
+		Observation {
+			state: 0,
+			reward: 0.0,
+			done: false
+		}
+	}
+	fn render(&self) {
+	}
+}
+
+// Stops training once the given number of iterations are done
+struct StopAfter(usize);
+
+impl<S: Space, A: Space, T> Observer<S, A, T> for StopAfter {
+	fn on_iter_end(&mut self, _: &mut T, iter: usize, _: f64) -> Signal {
+		if iter + 1 >= self.0 {Signal::Stop} else {Signal::Continue}
+	}
+}
+
+fn trained_agent() -> EGreedyQAgent<Finite, Finite, QTable<Finite, Finite>, Uniform> {
+	let mut env = NumberChooser;
+	let mut rng = StdRng::from_seed(&[1, 2, 3]);
+	let mut agent = EGreedyQAgent::new(QTable::new(), env.action_space(), 0.3, Uniform);
+	let mut trainer = QLearner::default(env.action_space()).train_period(TimePeriod::TIMESTEPS(500));
+
+	trainer.train(&mut agent, &mut env, &mut rng);
+	agent
+}
+
+fn all_values<T: QFunction<Finite, Finite>>(agent: &T) -> Vec<f64> {
+	let mut vals = Vec::new();
+	for s in 0..3 {
+		for a in 0..10 {
+			vals.push(agent.eval(&s, &a));
+		}
+	}
+	vals
+}
+
+#[test]
+fn qtable_state_roundtrip() {
+	let agent = trained_agent();
+
+	let mut restored = EGreedyQAgent::new(QTable::new(), NumberChooser.action_space(), 0.0, Uniform);
+	restored.set_state(agent.get_state()).unwrap();
+
+	assert_eq!(all_values(&agent), all_values(&restored));
+	assert_eq!(restored.get_state().epsilon, 0.3);
+}
+
+#[test]
+fn qlinear_state_mismatch() {
+	let action_space = Finite::new(2);
+	let mut q_func: QLinear<f64, Vec<Range>, Finite> = QLinear::new(&action_space);
+	q_func.add(Box::new(IFeature::new(0)));
+	q_func.set_params(vec![1.0, 2.0, 3.0, 4.0]);
+
+	let mut no_feats: QLinear<f64, Vec<Range>, Finite> = QLinear::new(&action_space);
+	match no_feats.set_state(q_func.get_state()) {
+		Err(CheckpointError::Mismatch(_)) => {},
+		res => panic!("expected a mismatch, got {:?}", res),
+	}
+}
+
+#[test]
+fn qlinear_state_different_features() {
+	let action_space = Finite::new(2);
+	let mut q_func: QLinear<f64, Vec<Range>, Finite> = QLinear::new(&action_space);
+	q_func.add(Box::new(IFeature::new(0)));
+	q_func.set_params(vec![1.0, 2.0, 3.0, 4.0]);
+
+	// Same number of features (and weights), but it reads another component of the state
+	let mut other: QLinear<f64, Vec<Range>, Finite> = QLinear::new(&action_space);
+	other.add(Box::new(IFeature::new(1)));
+	match other.set_state(q_func.get_state()) {
+		Err(CheckpointError::Mismatch(_)) => {},
+		res => panic!("expected a mismatch, got {:?}", res),
+	}
+	assert_eq!(other.get_params(), vec![0.0; 4]);
+}
+
+#[test]
+fn cem_resumes_interrupted_train() {
+	let mut env = NumberChooser;
+	let action_space = env.action_space();
+	let new_agent = || EGreedyQAgent::new(QLinear::default(&action_space), action_space, 0.1, Uniform);
+	let new_trainer = |iters| CrossEntropy::default().eval_period(TimePeriod::TIMESTEPS(10))
+													 .num_samples(20).iters(iters);
+
+	let mut rng = StdRng::from_seed(&[7]);
+	let mut agent = new_agent();
+	new_trainer(4).train(&mut agent, &mut env, &mut rng);
+
+	// Same run, but stopped after 2 iterations and resumed in a new trainer
+	let mut rng = StdRng::from_seed(&[7]);
+	let mut resumed_agent = new_agent();
+	let mut trainer = new_trainer(4);
+	trainer.train_with(&mut resumed_agent, &mut env, &mut rng, &mut StopAfter(2));
+
+	let state = trainer.get_state();
+	assert_eq!(state.curr_iter, 2);
+	let mut trainer = new_trainer(4);
+	trainer.set_state(state).unwrap();
+	trainer.train(&mut resumed_agent, &mut env, &mut rng);
+
+	assert_eq!(agent.get_params(), resumed_agent.get_params());
+}
+
+#[test]
+fn cmaes_resumes_interrupted_train() {
+	let mut env = NumberChooser;
+	let action_space = env.action_space();
+	let new_agent = || EGreedyQAgent::new(QLinear::default(&action_space), action_space, 0.1, Uniform);
+	let new_trainer = |iters| CMAES::default().eval_period(TimePeriod::TIMESTEPS(10)).iters(iters);
+
+	let mut rng = StdRng::from_seed(&[7]);
+	let mut agent = new_agent();
+	new_trainer(4).train(&mut agent, &mut env, &mut rng);
+
+	// Same run, but stopped after 2 iterations and resumed in a new trainer
+	let mut rng = StdRng::from_seed(&[7]);
+	let mut resumed_agent = new_agent();
+	let mut trainer = new_trainer(4);
+	trainer.train_with(&mut resumed_agent, &mut env, &mut rng, &mut StopAfter(2));
+
+	let state = trainer.get_state();
+	assert_eq!(state.curr_iter, 2);
+	let mut trainer = new_trainer(4);
+	trainer.set_state(state).unwrap();
+	trainer.train(&mut resumed_agent, &mut env, &mut rng);
+
+	assert_eq!(agent.get_params(), resumed_agent.get_params());
+}
+
+#[test]
+fn dynaq_resumes_training() {
+	let mut env = NumberChooser;
+	let action_space = env.action_space();
+	let new_trainer = || DynaQ::default(action_space, PlainModel::new()).train_period(TimePeriod::TIMESTEPS(100));
+
+	let mut rng = StdRng::from_seed(&[8]);
+	let mut agent = EGreedyQAgent::new(QTable::new(), env.action_space(), 0.3, Uniform);
+	let mut trainer = new_trainer();
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	let mut resumed_agent = EGreedyQAgent::new(QTable::new(), env.action_space(), 0.3, Uniform);
+	resumed_agent.set_state(agent.get_state()).unwrap();
+	let mut resumed_trainer = new_trainer();
+	resumed_trainer.set_state(trainer.get_state()).unwrap();
+
+	let mut resumed_rng = rng.clone();
+	trainer.train(&mut agent, &mut env, &mut rng);
+	resumed_trainer.train(&mut resumed_agent, &mut env, &mut resumed_rng);
+
+	assert_eq!(all_values(&agent), all_values(&resumed_agent));
+}
+
+#[test]
+fn sweeping_resumes_training() {
+	let mut env = NumberChooser;
+	let action_space = env.action_space();
+	let new_trainer = || {
+		PrioritizedSweeping::default(action_space, PlainModel::new()).kappa(0.01)
+															   .train_period(TimePeriod::TIMESTEPS(100))
+	};
+
+	let mut rng = StdRng::from_seed(&[9]);
+	let mut agent = EGreedyQAgent::new(QTable::new(), env.action_space(), 0.3, Uniform);
+	let mut trainer = new_trainer();
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	let mut resumed_agent = EGreedyQAgent::new(QTable::new(), env.action_space(), 0.3, Uniform);
+	resumed_agent.set_state(agent.get_state()).unwrap();
+	let mut resumed_trainer = new_trainer();
+	resumed_trainer.set_state(trainer.get_state()).unwrap();
+
+	let mut resumed_rng = rng.clone();
+	trainer.train(&mut agent, &mut env, &mut rng);
+	resumed_trainer.train(&mut resumed_agent, &mut env, &mut resumed_rng);
+
+	assert_eq!(all_values(&agent), all_values(&resumed_agent));
+}
+
+#[test]
+fn momentum_state_roundtrip() {
+	let mut momentum = GradDescMomentum::new(0.9);
+	momentum.calculate(vec![1.0, -2.0], 0.1);
+
+	let mut restored = GradDescMomentum::new(0.9);
+	restored.set_state(momentum.get_state()).unwrap();
+
+	assert_eq!(momentum.calculate(vec![0.5, 0.5], 0.1), restored.calculate(vec![0.5, 0.5], 0.1));
+}
+
+#[cfg(feature = "serialize")]
+#[test]
+fn save_load_qtable_agent() {
+	use re::util::checkpoint::{save, load};
+
+	let agent = trained_agent();
+	let mut bytes = Vec::new();
+	save(&agent, &mut bytes).unwrap();
+
+	let mut restored = EGreedyQAgent::new(QTable::new(), NumberChooser.action_space(), 0.0, Uniform);
+	load(&mut restored, &bytes[..]).unwrap();
+
+	assert_eq!(all_values(&agent), all_values(&restored));
+}
+
+#[cfg(feature = "serialize")]
+#[test]
+fn save_load_qlinear_agent() {
+	use re::util::checkpoint::{save, load};
+
+	let action_space = Finite::new(2);
+	let new_agent = || {
+		let mut q_func: QLinear<f64, Vec<Range>, Finite> = QLinear::new(&action_space);
+		q_func.add(Box::new(IFeature::new(0)));
+		PolicyAgent::new(action_space, q_func, 0.5)
+	};
+
+	let mut agent = new_agent();
+	agent.set_params(vec![1.0, 2.0, 3.0, 4.0]);
+	let mut bytes = Vec::new();
+	save(&agent, &mut bytes).unwrap();
+
+	let mut restored = new_agent();
+	load(&mut restored, &bytes[..]).unwrap();
+
+	assert_eq!(restored.get_params(), vec![1.0, 2.0, 3.0, 4.0]);
+	assert_eq!(restored.get_temp(), 0.5);
+}
+
+#[cfg(feature = "serialize")]
+#[test]
+fn load_rejects_newer_version() {
+	use re::util::checkpoint::load;
+
+	let mut agent = trained_agent();
+	let bytes = b"renforce-checkpoint 99\n{}";
+	match load(&mut agent, &bytes[..]) {
+		Err(CheckpointError::Version(99)) => {},
+		res => panic!("expected a version error, got {:?}", res),
+	}
+}
\ No newline at end of file
@@ -0,0 +1,128 @@
+// Checks that CMA-ES adapts its covariance to minimize an ill-conditioned quadratic
+
+extern crate renforce as re;
+extern crate rand;
+
+use std::f64;
+
+use rand::{Rng, SeedableRng, StdRng};
+
+use re::prelude::*;
+
+use re::environment::Range;
+
+use re::trainer::CMAES;
+
+// Acts by outputting its parameters
+#[derive(Debug)]
+struct Point {
+	params: Vec<f64>,
+}
+
+impl Agent<(), Vec<Range>> for Point {
+	fn get_action<R: Rng>(&self, _: &(), _: &mut R) -> Vec<f64> {
+		self.params.clone()
+	}
+}
+
+impl ParameterizedFunc<f64> for Point {
+	fn num_params(&self) -> usize {
+		self.params.len()
+	}
+	fn get_params(&self) -> Vec<f64> {
+		self.params.clone()
+	}
+	fn set_params(&mut self, params: Vec<f64>) {
+		self.params = params;
+	}
+}
+
+// Pays -((x + y - 3)^2 + 100(x - y - 1)^2), which is best at (2, 1)
+struct Valley;
+
+impl Environment for Valley {
+	type State = ();
+	type Action = Vec<Range>;
+
+	fn state_space(&self) {}
+	fn action_space(&self) -> Vec<Range> {
+		vec![Range::new(-10.0, 10.0); 2]
+	}
+	fn step(&mut self, action: &Vec<f64>) -> Observation<()> {
+		let (x, y) = (action[0], action[1]);
+		Observation {
+			state: (),
+			reward: -((x + y - 3.0).powi(2) + 100.0*(x - y - 1.0).powi(2)),
+			done: true
+		}
+	}
+	fn reset(&mut self) -> Observation<()> {
+		Observation {
+			state: (),
+			reward: 0.0,
+			done: false
+		}
+	}
+	fn render(&self) {
+	}
+}
+
+// Like Valley, but pays NaN whenever x is above 2.5
+struct BrokenValley;
+
+impl Environment for BrokenValley {
+	type State = ();
+	type Action = Vec<Range>;
+
+	fn state_space(&self) {}
+	fn action_space(&self) -> Vec<Range> {
+		Valley.action_space()
+	}
+	fn step(&mut self, action: &Vec<f64>) -> Observation<()> {
+		let mut obs = Valley.step(action);
+		if action[0] > 2.5 {
+			obs.reward = f64::NAN;
+		}
+		obs
+	}
+	fn reset(&mut self) -> Observation<()> {
+		Valley.reset()
+	}
+	fn render(&self) {
+	}
+}
+
+#[test]
+fn cmaes_finds_valley_minimum() {
+	let mut rng = StdRng::from_seed(&[1]);
+	let mut agent = Point {params: vec![-3.0, 4.0]};
+
+	let mut trainer = CMAES::default().iters(150);
+	trainer.train(&mut agent, &mut Valley, &mut rng);
+	assert!((agent.params[0] - 2.0).abs() < 1e-3 && (agent.params[1] - 1.0).abs() < 1e-3, "ended at {:?}", agent.params);
+	assert!(trainer.get_deviation() < 0.1);
+
+	// The valley runs along (1, 1), so that direction should be the most spread out
+	let cov = trainer.get_state().covariance;
+	assert!(cov[0][1] > 0.5*(cov[0][0]*cov[1][1]).sqrt(), "covariance {:?}", cov);
+}
+
+#[test]
+fn cmaes_same_seed() {
+	let run = || {
+		let mut rng = StdRng::from_seed(&[2]);
+		let mut agent = Point {params: vec![0.0, 0.0]};
+		CMAES::default().num_samples(8).iters(5).train(&mut agent, &mut Valley, &mut rng);
+		agent.params
+	};
+	assert_eq!(run(), run());
+}
+
+#[test]
+fn cmaes_ignores_nan_scores() {
+	let mut rng = StdRng::from_seed(&[3]);
+	let mut agent = Point {params: vec![3.0, 4.0]};
+
+	CMAES::default().iters(150).train(&mut agent, &mut BrokenValley, &mut rng);
+	assert!((agent.params[0] - 2.0).abs() < 1e-2 && (agent.params[1] - 1.0).abs() < 1e-2, "ended at {:?}", agent.params);
+}
\ No newline at end of file
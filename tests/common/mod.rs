@@ -0,0 +1,90 @@
+// Environments and features shared by the integration tests
+// Not every test uses all of them
+#![allow(dead_code)]
+
+use re::prelude::*;
+
+use re::environment::{Finite, Range};
+
+use re::util::Feature;
+use re::util::feature::IFeature;
+
+// Takes 10 steps along a line, being rewarded for stepping right
+pub struct Walk {
+	pos: f64,
+	steps: u32,
+}
+
+impl Walk {
+	pub fn new() -> Walk {
+		Walk {pos: 0.0, steps: 0}
+	}
+}
+
+impl Environment for Walk {
+	type State = Vec<Range>;
+	type Action = Finite;
+
+	fn state_space(&self) -> Vec<Range> {
+		vec![Range::new(-1.0, 1.0)]
+	}
+	fn action_space(&self) -> Finite {
+		Finite::new(2)
+	}
+	fn step(&mut self, action: &u32) -> Observation<Vec<Range>> {
+		self.pos += if *action == 1 {0.1} else {-0.1};
+		self.steps += 1;
+		Observation {
+			state: vec![self.pos],
+			reward: *action as f64,
+			done: self.steps == 10
+		}
+	}
+	fn reset(&mut self) -> Observation<Vec<Range>> {
+		self.pos = 0.0;
+		self.steps = 0;
+		Observation {
+			state: vec![0.0],
+			reward: 0.0,
+			done: false
+		}
+	}
+	fn render(&self) {
+	}
+}
+
+// Rewards actions close to 0.5 (the state is ignored)
+pub struct Aim;
+
+impl Environment for Aim {
+	type State = Vec<Range>;
+	type Action = Vec<Range>;
+
+	fn state_space(&self) -> Vec<Range> {
+		vec![Range::new(0.0, 1.0)]
+	}
+	fn action_space(&self) -> Vec<Range> {
+		vec![Range::new(-2.0, 2.0)]
+	}
+	fn step(&mut self, action: &Vec<f64>) -> Observation<Vec<Range>> {
+		Observation {
+			state: vec![1.0],
+			reward: -(action[0] - 0.5).powi(2),
+			done: true
+		}
+	}
+	fn reset(&mut self) -> Observation<Vec<Range>> {
+		Observation {
+			state: vec![1.0],
+			reward: 0.0,
+			done: false
+		}
+	}
+	fn render(&self) {
+	}
+}
+
+// The only component of the state, as the only feature
+pub fn position() -> Vec<Box<Feature<Vec<Range>, f64>>> {
+	vec![Box::new(IFeature::new(0))]
+}
\ No newline at end of file
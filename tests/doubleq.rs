@@ -0,0 +1,149 @@
+// Compares the value estimates of Double Q-learning and Q-learning
+
+extern crate renforce as re;
+extern crate rand;
+
+mod common;
+
+use rand::{Rng, SeedableRng, StdRng};
+use rand::distributions::IndependentSample;
+use rand::distributions::normal::Normal;
+
+use re::prelude::*;
+
+use re::environment::{Finite, Range};
+
+use re::trainer::{QLearner, DoubleQLearner};
+
+use re::util::table::QTable;
+use re::util::approx::QLinear;
+use re::util::chooser::Uniform;
+
+use common::position;
+
+// Every arm pays out N(0, 1), so every action is worth 0
+struct NoisyArms {
+	rng: StdRng,
+}
+
+impl Environment for NoisyArms {
+	type State = ();
+	type Action = Finite;
+
+	fn state_space(&self) {}
+	fn action_space(&self) -> Finite {
+		Finite::new(10)
+	}
+	fn step(&mut self, _: &u32) -> Observation<()> {
+		Observation {
+			state: (),
+			reward: Normal::new(0.0, 1.0).ind_sample(&mut self.rng),
+			done: false
+		}
+	}
+	fn reset(&mut self) -> Observation<()> {
+		Observation {
+			state: (),
+			reward: 0.0,
+			done: false
+		}
+	}
+	fn render(&self) {
+	}
+}
+
+fn mean_val<Q: QFunction<(), Finite>>(q_func: &Q) -> f64 {
+	(0..10).map(|a| q_func.eval(&(), &a)).sum::<f64>()/10.0
+}
+
+#[test]
+fn double_q_reduces_overestimation() {
+	let period = TimePeriod::TIMESTEPS(20000);
+
+	let mut env = NoisyArms {rng: StdRng::from_seed(&[1])};
+	let mut agent = EGreedyQAgent::new(QTable::new(), env.action_space(), 1.0, Uniform);
+	QLearner::new(env.action_space(), 0.5, 0.1, period.clone()).train(&mut agent, &mut env, &mut StdRng::from_seed(&[2]));
+	let single = mean_val(&agent);
+
+	let mut env = NoisyArms {rng: StdRng::from_seed(&[1])};
+	let mut agent = EGreedyQAgent::new(QTable::new(), env.action_space(), 1.0, Uniform);
+	DoubleQLearner::new(env.action_space(), QTable::new(), 0.5, 0.1, period).train(&mut agent, &mut env, &mut StdRng::from_seed(&[2]));
+	// The agent holds the sum of both estimates
+	let double = mean_val(&agent)/2.0;
+
+	println!("Q-learning: {}, Double Q-learning: {}", single, double);
+	assert!(single > 0.3, "Q-learning estimated {}", single);
+	assert!(double.abs() < single/2.0, "Double Q-learning estimated {}", double);
+}
+
+#[test]
+fn linear_matches_tabular() {
+	let action_space = Finite::new(2);
+	let transitions = vec![((), 0, 1.0, ()), ((), 1, -1.0, ()), ((), 0, 0.5, ()), ((), 1, 2.0, ()), ((), 0, 0.0, ())];
+
+	let mut table: GreedyQAgent<(), Finite, QTable<(), Finite>> = GreedyQAgent::new(QTable::new(), action_space);
+	let mut table_trainer = DoubleQLearner::new(action_space, QTable::new(), 0.9, 0.5, TimePeriod::EPISODES(1));
+	let mut rng = StdRng::from_seed(&[3]);
+	for t in transitions.clone() {
+		table_trainer.train_step(&mut table, t, &mut rng);
+	}
+
+	let mut linear: GreedyQAgent<(), Finite, QLinear<f64, (), Finite>> = GreedyQAgent::new(QLinear::default(&action_space), action_space);
+	let mut linear_trainer = DoubleQLearner::new(action_space, QLinear::default(&action_space), 0.9, 0.5, TimePeriod::EPISODES(1));
+	let mut rng = StdRng::from_seed(&[3]);
+	for t in transitions {
+		linear_trainer.train_step(&mut linear, t, &mut rng);
+	}
+
+	for a in 0..2 {
+		assert!((table.eval(&(), &a) - linear.eval(&(), &a)).abs() < 1e-10);
+		assert!((table_trainer.get_q_func().eval(&(), &a) - linear_trainer.get_q_func().eval(&(), &a)).abs() < 1e-10);
+	}
+}
+
+// Index of the first largest value of q_func in state
+fn best_action<Q: QFunction<Vec<Range>, Finite>>(q_func: &Q, state: &Vec<f64>) -> u32 {
+	if q_func.eval(state, &1) > q_func.eval(state, &0) {1} else {0}
+}
+
+#[test]
+fn linear_sum_tracks_both_estimates() {
+	let action_space = Finite::new(2);
+	let new_func = || {
+		let mut q_func: QLinear<f64, Vec<Range>, Finite> = QLinear::new(&action_space);
+		for feat in position() {
+			q_func.add(feat);
+		}
+		q_func
+	};
+	let transitions = vec![(vec![0.2], 0, 1.0, vec![0.4]), (vec![0.4], 1, -1.0, vec![0.9]), (vec![0.9], 0, 0.5, vec![0.1]),
+						   (vec![0.1], 1, 2.0, vec![0.6]), (vec![0.6], 0, 0.0, vec![0.3]), (vec![0.3], 1, 1.5, vec![0.8])];
+
+	let mut agent = GreedyQAgent::new(new_func(), action_space);
+	let mut trainer = DoubleQLearner::new(action_space, new_func(), 0.9, 0.5, TimePeriod::EPISODES(1));
+	let mut rng = StdRng::from_seed(&[4]);
+	for t in transitions.clone() {
+		trainer.train_step(&mut agent, t, &mut rng);
+	}
+
+	// Learn both estimates separately, making the same random choices
+	let (mut q_a, mut q_b) = (new_func(), new_func());
+	let mut rng = StdRng::from_seed(&[4]);
+	for (state, action, reward, next) in transitions {
+		if rng.gen::<bool>() {
+			let target = reward + 0.9*q_b.eval(&next, &best_action(&q_a, &next));
+			q_a.update(&state, &action, target, 0.5);
+		} else {
+			let target = reward + 0.9*q_a.eval(&next, &best_action(&q_b, &next));
+			q_b.update(&state, &action, target, 0.5);
+		}
+	}
+
+	for &x in &[0.0, 0.5, 1.0] {
+		for a in 0..2 {
+			let state = vec![x];
+			assert!((agent.eval(&state, &a) - q_a.eval(&state, &a) - q_b.eval(&state, &a)).abs() < 1e-10);
+			assert!((trainer.get_q_func().eval(&state, &a) - q_b.eval(&state, &a)).abs() < 1e-10);
+		}
+	}
+}
\ No newline at end of file
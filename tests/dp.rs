@@ -0,0 +1,102 @@
+// Checks the dynamic programming solvers against an MDP with known values
+
+extern crate renforce as re;
+extern crate rand;
+
+use rand::{SeedableRng, StdRng};
+
+use re::prelude::*;
+
+use re::environment::Finite;
+
+use re::model::{Model, DPSolver, DPMethod};
+
+use re::util::table::{QTable, VTable};
+
+// In state 0, action 0 earns 0.5 and stays while action 1 moves to state 1
+// In state 1, action 0 earns 10 and ends the episode (state 2) while action 1 moves to a random nonterminal state
+struct SmallMDP;
+
+impl Model<Finite, Finite> for SmallMDP {
+	fn transition(&self, curr: &u32, action: &u32, next: &u32) -> f64 {
+		match (*curr, *action, *next) {
+			(0, 0, 0) | (0, 1, 1) | (1, 0, 2) => 1.0,
+			(1, 1, 0) | (1, 1, 1) => 0.5,
+			_ => 0.0
+		}
+	}
+	fn reward(&self, curr: &u32, action: &u32, _: &u32) -> f64 {
+		match (*curr, *action) {
+			(0, 0) => 0.5,
+			(1, 0) => 10.0,
+			_ => 0.0
+		}
+	}
+	fn update(&mut self, _: Transition<Finite, Finite>) {
+	}
+	fn successors(&self, curr: &u32, action: &u32) -> Vec<(u32, f64)> {
+		(0..3).map(|next| (next, self.transition(curr, action, &next))).filter(|&(_, p)| p > 0.0).collect()
+	}
+}
+
+// With gamma = 0.9
+const VALUES: [f64; 3] = [9.0, 10.0, 0.0];
+
+fn solver(method: DPMethod) -> DPSolver<Finite, Finite> {
+	DPSolver::new(Finite::new(3), Finite::new(2), 0.9, method)
+}
+
+#[test]
+fn methods_agree_on_values() {
+	let methods = [DPMethod::ValueIteration, DPMethod::PolicyIteration, DPMethod::ModifiedPolicyIteration(3)];
+	for &method in &methods {
+		let mut v_func = VTable::new();
+		let report = solver(method).solve_v(&SmallMDP, &mut v_func);
+
+		assert!(report.converged, "{:?} did not converge", method);
+		for s in 0..3 {
+			assert!((v_func.eval(&s) - VALUES[s as usize]).abs() < 1e-6, "{:?} found V({}) = {}", method, s, v_func.eval(&s));
+		}
+	}
+}
+
+#[test]
+fn solved_agent_acts_optimally() {
+	let mut rng = StdRng::from_seed(&[1]);
+	let (agent, _) = solver(DPMethod::PolicyIteration).solve_agent(&SmallMDP);
+
+	assert_eq!((agent.get_action(&0, &mut rng), agent.get_action(&1, &mut rng)), (1, 0));
+
+	let mut q_func = QTable::new();
+	solver(DPMethod::ValueIteration).solve_q(&SmallMDP, &mut q_func);
+	assert!((q_func.eval(&0, &0) - (0.5 + 0.9*9.0)).abs() < 1e-6);
+	assert!((q_func.eval(&1, &1) - 0.9*(9.0 + 10.0)/2.0).abs() < 1e-6);
+}
+
+#[test]
+fn reports_describe_progress() {
+	let report = solver(DPMethod::PolicyIteration).solve_v(&SmallMDP, &mut VTable::new());
+	assert!(report.iterations <= 3);
+	assert_eq!(report.policy_changes[0], 3);
+	assert_eq!(report.policy_changes.last(), Some(&0));
+
+	// Value iteration is a contraction, so the changes shrink by at least a factor of gamma
+	let report = solver(DPMethod::ValueIteration).solve_v(&SmallMDP, &mut VTable::new());
+	for w in report.deltas.windows(2) {
+		assert!(w[1] <= 0.9*w[0] + 1e-12);
+	}
+
+	let report = solver(DPMethod::ValueIteration).max_iters(2).solve_v(&SmallMDP, &mut VTable::new());
+	assert!(!report.converged);
+	assert_eq!((report.iterations, report.deltas.len()), (2, 2));
+}
+
+#[test]
+fn solve_v_starts_from_given_values() {
+	let mut v_func = VTable::new();
+	let report = solver(DPMethod::ValueIteration).solve_v(&SmallMDP, &mut v_func);
+
+	let warm_report = solver(DPMethod::ValueIteration).solve_v(&SmallMDP, &mut v_func);
+	assert!(warm_report.converged);
+	assert!(warm_report.iterations < report.iterations);
+}
\ No newline at end of file
@@ -0,0 +1,109 @@
+// Tests the DQN trainer on a small environment using a neural network
+
+extern crate renforce as re;
+extern crate rand;
+
+mod common;
+
+use rand::{SeedableRng, StdRng};
+
+use re::prelude::*;
+
+use re::environment::{Finite, Range};
+
+use re::trainer::{DQN, TargetUpdate};
+
+use re::util::network::{QNetwork, Activation};
+use re::util::replay::{ReplayBuffer, Sampling};
+use re::util::chooser::Uniform;
+
+use common::{Walk, position};
+
+fn check_learned<T: QFunction<Vec<Range>, Finite>>(agent: &T) {
+	for &x in &[-0.5, 0.0, 0.5] {
+		let state = vec![x];
+		assert!(agent.eval(&state, &1) > agent.eval(&state, &0), "does not prefer stepping right at {}", x);
+	}
+}
+
+#[test]
+fn dqn_sync() {
+	let mut env = Walk::new();
+	let mut rng = StdRng::from_seed(&[1]);
+
+	let q_func = QNetwork::new(position(), &[(8, Activation::Tanh)], &env.action_space(), &mut rng);
+	let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.2, Uniform);
+	let mut trainer = DQN::default(env.action_space()).gamma(0.5).alpha(0.05)
+													  .replay(ReplayBuffer::new(500), 16)
+													  .target_update(TargetUpdate::Sync(100))
+													  .train_period(TimePeriod::TIMESTEPS(2000));
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	check_learned(&agent);
+}
+
+#[test]
+fn double_dqn_polyak() {
+	let mut env = Walk::new();
+	let mut rng = StdRng::from_seed(&[2]);
+
+	let q_func = QNetwork::new(position(), &[(8, Activation::Tanh)], &env.action_space(), &mut rng);
+	let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.2, Uniform);
+	let replay = ReplayBuffer::new(500).sampling(Sampling::Proportional(0.6));
+	let mut trainer = DQN::default(env.action_space()).gamma(0.5).alpha(0.05)
+													  .replay(replay, 16)
+													  .target_update(TargetUpdate::Polyak(0.01))
+													  .double(true)
+													  .train_period(TimePeriod::TIMESTEPS(2000));
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	check_learned(&agent);
+}
+
+#[test]
+fn target_lags_behind() {
+	let mut env = Walk::new();
+	let mut rng = StdRng::from_seed(&[3]);
+
+	let q_func = QNetwork::new(position(), &[(4, Activation::ReLU)], &env.action_space(), &mut rng);
+	let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.2, Uniform);
+	let initial = agent.get_params();
+	let mut trainer = DQN::default(env.action_space()).replay(ReplayBuffer::new(100), 4)
+													  .target_update(TargetUpdate::Sync(50))
+													  .train_period(TimePeriod::TIMESTEPS(49));
+
+	trainer.train(&mut agent, &mut env, &mut rng);
+	assert_eq!(trainer.get_state().target_params, initial);
+	assert!(agent.get_params() != initial);
+
+	trainer.train_step(&mut agent, (vec![0.0], 1, 1.0, vec![0.1]), &mut rng);
+	assert_eq!(trainer.get_state().target_params, agent.get_params());
+}
+#[test]
+fn dqn_resumes_with_replay() {
+	let mut env = Walk::new();
+	let mut rng = StdRng::from_seed(&[4]);
+	let action_space = env.action_space();
+	// Small enough that the replay wraps around before the checkpoint
+	let new_trainer = || {
+		DQN::default(action_space).replay(ReplayBuffer::new(100).sampling(Sampling::Rank(0.7)), 8)
+								  .train_period(TimePeriod::TIMESTEPS(150))
+	};
+
+	let q_func = QNetwork::new(position(), &[(4, Activation::Tanh)], &action_space, &mut rng);
+	let mut agent = EGreedyQAgent::new(q_func, action_space, 0.2, Uniform);
+	let mut trainer = new_trainer();
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	let q_func = QNetwork::new(position(), &[(4, Activation::Tanh)], &action_space, &mut StdRng::from_seed(&[5]));
+	let mut resumed_agent = EGreedyQAgent::new(q_func, action_space, 0.2, Uniform);
+	resumed_agent.set_params(agent.get_params());
+	let mut resumed_trainer = new_trainer();
+	resumed_trainer.set_state(trainer.get_state()).unwrap();
+	assert_eq!(resumed_trainer.get_replay().len(), 100);
+
+	let mut resumed_rng = rng.clone();
+	trainer.train(&mut agent, &mut Walk::new(), &mut rng);
+	resumed_trainer.train(&mut resumed_agent, &mut Walk::new(), &mut resumed_rng);
+	assert_eq!(agent.get_params(), resumed_agent.get_params());
+}
\ No newline at end of file
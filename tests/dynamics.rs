@@ -0,0 +1,223 @@
+// Checks learned models of continuous environments and planning with them
+
+extern crate renforce as re;
+extern crate rand;
+
+use rand::{Rng, SeedableRng, StdRng};
+use rand::distributions::IndependentSample;
+use rand::distributions::normal::Normal;
+
+use re::prelude::*;
+
+use re::environment::{Finite, Range};
+
+use re::model::{GenerativeModel, LinearDynamics};
+
+use re::trainer::GenerativeDynaQ;
+
+use re::util::approx::QLinear;
+use re::util::chooser::Uniform;
+use re::util::feature::IFeature;
+use re::util::checkpoint::CheckpointError;
+
+type Plane = Vec<Range>;
+
+fn plane_model() -> LinearDynamics<Plane, Finite> {
+	LinearDynamics::new(vec![Box::new(IFeature::new(0)), Box::new(IFeature::new(1))], Finite::new(2)).ridge(1e-6)
+}
+
+// A linear system whose dynamics depend on the action
+fn plane_step(state: &Vec<f64>, action: u32) -> (Vec<f64>, f64) {
+	let (x, y) = (state[0], state[1]);
+	if action == 0 {
+		(vec![x + 0.1, 0.5*y + x], 2.0*x - y)
+	} else {
+		(vec![x - y, y - 0.2], 1.0 + x)
+	}
+}
+
+#[test]
+fn linear_dynamics_fits_linear_system() {
+	let mut model = plane_model();
+	let mut rng = StdRng::from_seed(&[1]);
+
+	assert_eq!(model.predict(&vec![1.0, 2.0], &0), (vec![1.0, 2.0], 0.0));
+	for _ in 0..50 {
+		let state = vec![rng.gen_range(-1.0, 1.0), rng.gen_range(-1.0, 1.0)];
+		let action = rng.gen_range(0, 2);
+		let (next, reward) = plane_step(&state, action);
+		model.update((state, action, reward, next));
+	}
+
+	for &(state, action) in &[([0.3, -0.7], 0), ([-2.0, 1.5], 1), ([0.9, 0.1], 1)] {
+		let (expected, expected_reward) = plane_step(&state.to_vec(), action);
+		let (next, reward) = model.generate(&state.to_vec(), &action, &mut rng);
+		assert!(model.get_variances().iter().all(|&var| var < 1e-6));
+		for (x, y) in next.iter().zip(expected) {
+			assert!((x - y).abs() < 1e-3, "predicted {:?}", next);
+		}
+		assert!((reward - expected_reward).abs() < 1e-3);
+	}
+}
+
+#[test]
+fn linear_dynamics_estimates_noise() {
+	let mut model: LinearDynamics<Vec<Range>, Finite> = LinearDynamics::new(vec![Box::new(IFeature::new(0))], Finite::new(1));
+	let mut rng = StdRng::from_seed(&[2]);
+	let normal = Normal::new(0.0, 0.5);
+
+	for _ in 0..2000 {
+		let x = rng.gen_range(-1.0, 1.0);
+		let next = 0.5*x + normal.ind_sample(&mut rng);
+		model.update((vec![x], 0, 1.0, vec![next]));
+	}
+	let variances = model.get_variances();
+	assert!((variances[0] - 0.25).abs() < 0.03, "state variance was {}", variances[0]);
+	assert!(variances[1] < 1e-6);
+
+	let samples: Vec<_> = (0..2000).map(|_| model.generate(&vec![0.4], &0, &mut rng).0[0]).collect();
+	let mean = samples.iter().sum::<f64>()/2000.0;
+	let var = samples.iter().map(|x| (x - mean)*(x - mean)).sum::<f64>()/2000.0;
+	assert!((mean - 0.2).abs() < 0.05 && (var - 0.25).abs() < 0.05, "samples had mean {} and variance {}", mean, var);
+
+	let model = model.noise(0.0);
+	assert_eq!(model.generate(&vec![0.4], &0, &mut rng), model.predict(&vec![0.4], &0));
+}
+
+#[test]
+fn linear_dynamics_state_roundtrip() {
+	let mut model = plane_model();
+	model.update((vec![0.5, 0.5], 1, 1.0, vec![0.0, 0.3]));
+
+	let mut restored = plane_model();
+	restored.set_state(model.get_state()).unwrap();
+	assert_eq!(restored.predict(&vec![0.2, 0.1], &1), model.predict(&vec![0.2, 0.1], &1));
+
+	let mut smaller: LinearDynamics<Plane, Finite> = LinearDynamics::new(vec![Box::new(IFeature::new(0))], Finite::new(2));
+	match smaller.set_state(model.get_state()) {
+		Err(CheckpointError::Mismatch(_)) => {},
+		res => panic!("expected a mismatch, got {:?}", res)
+	}
+}
+
+// Moves left (action 0) or right (action 1) along [-1, 1], rewarded by its position after each of 10 steps
+struct Drift {
+	x: f64,
+	steps: usize,
+}
+
+impl Environment for Drift {
+	type State = Vec<Range>;
+	type Action = Finite;
+
+	fn state_space(&self) -> Vec<Range> {
+		vec![Range::new(-1.0, 1.0)]
+	}
+	fn action_space(&self) -> Finite {
+		Finite::new(2)
+	}
+	fn step(&mut self, action: &u32) -> Observation<Vec<Range>> {
+		self.x = (self.x + if *action == 1 {0.1} else {-0.1}).max(-1.0).min(1.0);
+		self.steps += 1;
+		Observation {
+			state: vec![self.x],
+			reward: self.x,
+			done: self.steps == 10
+		}
+	}
+	fn reset(&mut self) -> Observation<Vec<Range>> {
+		self.x = 0.0;
+		self.steps = 0;
+		Observation {
+			state: vec![0.0],
+			reward: 0.0,
+			done: false
+		}
+	}
+	fn render(&self) {
+	}
+}
+
+fn drift_agent() -> GreedyQAgent<Vec<Range>, Finite, QLinear<f64, Vec<Range>, Finite>> {
+	let mut q_func = QLinear::default(&Finite::new(2));
+	q_func.add(Box::new(IFeature::new(0)));
+	GreedyQAgent::new(q_func, Finite::new(2))
+}
+
+// Stays at 0, earning 1 for each of 5 steps
+struct Still {
+	steps: usize,
+}
+
+impl Environment for Still {
+	type State = Vec<Range>;
+	type Action = Finite;
+
+	fn state_space(&self) -> Vec<Range> {
+		vec![Range::new(-1.0, 1.0)]
+	}
+	fn action_space(&self) -> Finite {
+		Finite::new(2)
+	}
+	fn step(&mut self, _: &u32) -> Observation<Vec<Range>> {
+		self.steps += 1;
+		Observation {
+			state: vec![0.0],
+			reward: 1.0,
+			done: self.steps == 5
+		}
+	}
+	fn reset(&mut self) -> Observation<Vec<Range>> {
+		self.steps = 0;
+		Observation {
+			state: vec![0.0],
+			reward: 0.0,
+			done: false
+		}
+	}
+	fn render(&self) {
+	}
+}
+
+#[test]
+fn planning_updates_untried_actions() {
+	let mut env = Still {steps: 0};
+	let mut rng = StdRng::from_seed(&[3]);
+	let new_trainer = |num_samples| {
+		let model = LinearDynamics::new(vec![Box::new(IFeature::new(0))], Finite::new(2));
+		GenerativeDynaQ::default(Finite::new(2), model).num_samples(num_samples).train_period(TimePeriod::EPISODES(1))
+	};
+
+	// Ties go to action 0, which then looks better than action 1, so action 1 is never tried
+	let mut agent = drift_agent();
+	new_trainer(0).train(&mut agent, &mut env, &mut rng);
+	assert_eq!(agent.eval(&vec![0.0], &1), 0.0);
+
+	// Before action 1 is observed, the model expects it to stay put without reward
+	let mut agent = drift_agent();
+	new_trainer(5).train(&mut agent, &mut env, &mut rng);
+	assert!(agent.eval(&vec![0.0], &1) > 0.0);
+}
+
+#[test]
+fn generative_dynaq_learns_to_drift() {
+	let mut env = Drift {x: 0.0, steps: 0};
+	let mut rng = StdRng::from_seed(&[4]);
+
+	let model = LinearDynamics::new(vec![Box::new(IFeature::new(0))], Finite::new(2));
+	let mut q_func = QLinear::default(&Finite::new(2));
+	q_func.add(Box::new(IFeature::new(0)));
+	let mut agent = EGreedyQAgent::new(q_func, Finite::new(2), 0.5, Uniform);
+
+	let mut trainer = GenerativeDynaQ::default(Finite::new(2), model).gamma(0.9).alpha(0.05)
+																	   .train_period(TimePeriod::EPISODES(20));
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	// The model learns to move by 0.1 either way
+	let (next, _) = trainer.get_model().predict(&vec![0.3], &1);
+	assert!((next[0] - 0.4).abs() < 0.01);
+
+	for &x in &[-0.5, 0.0, 0.5] {
+		assert!(agent.eval(&vec![x], &1) > agent.eval(&vec![x], &0), "moves left at {}", x);
+	}
+}
\ No newline at end of file
@@ -0,0 +1,119 @@
+// Checks that fitting Q-functions solves their regression problems, and that fitted Q iteration uses them
+
+extern crate renforce as re;
+extern crate rand;
+
+use rand::{SeedableRng, StdRng};
+
+use re::prelude::*;
+
+use re::environment::{Finite, Range};
+
+use re::trainer::FittedQIteration;
+
+use re::util::FittableQFunction;
+use re::util::table::QTable;
+use re::util::approx::QLinear;
+use re::util::tree::{QForest, SplitRule};
+use re::util::feature::IFeature;
+
+type Line = Vec<Range>;
+
+// Pays 1 for points at or beyond 0.5 and nothing for the rest
+fn step_data() -> Vec<(Vec<f64>, u32, f64)> {
+	(0..11).map(|i| {
+		let x = i as f64/10.0;
+		(vec![x], 0, if x >= 0.5 {1.0} else {0.0})
+	}).collect()
+}
+
+fn new_forest() -> QForest<Line, Finite> {
+	let mut forest = QForest::new(&Finite::new(2));
+	forest.add(Box::new(IFeature::new(0)));
+	forest
+}
+
+#[test]
+fn table_fit_averages_targets() {
+	let mut rng = StdRng::from_seed(&[1]);
+	let mut table: QTable<Finite, Finite> = QTable::new();
+	table.update(&1, &0, 4.0, 1.0);
+
+	table.fit(&[(0, 0, 1.0), (0, 0, 3.0), (1, 1, 5.0)], &mut rng);
+	assert_eq!((table.eval(&0, &0), table.eval(&1, &1)), (2.0, 5.0));
+	assert_eq!(table.eval(&1, &0), 4.0);
+}
+
+#[test]
+fn linear_fit_solves_least_squares() {
+	let mut rng = StdRng::from_seed(&[1]);
+	let mut linear: QLinear<f64, Line, Finite> = QLinear::new(&Finite::new(2));
+	linear.add(Box::new(IFeature::new(0)));
+
+	let data: Vec<_> = (0..5).map(|i| (vec![i as f64], 0, 2.0*(i as f64) + 1.0)).collect();
+	linear.fit(&data, &mut rng);
+	assert!((linear.eval(&vec![10.0], &0) - 21.0).abs() < 1e-4);
+	assert_eq!(linear.eval(&vec![10.0], &1), 0.0);
+
+	// Without a feature, the least squares fit is the mean
+	let mut constant: QLinear<f64, Line, Finite> = QLinear::new(&Finite::new(2));
+	constant.fit(&data, &mut rng);
+	assert!((constant.eval(&vec![10.0], &0) - 5.0).abs() < 1e-4);
+}
+
+#[test]
+fn forest_fits_step() {
+	let mut rng = StdRng::from_seed(&[1]);
+	for &rule in &[SplitRule::Best, SplitRule::Random] {
+		let mut forest = new_forest().split_rule(rule);
+		forest.fit(&step_data(), &mut rng);
+
+		assert_eq!((forest.eval(&vec![0.25], &0), forest.eval(&vec![0.75], &0)), (0.0, 1.0));
+		assert_eq!(forest.eval(&vec![0.75], &1), 0.0);
+	}
+
+	// A single tree that can not split just averages its targets
+	let mut stump = new_forest().num_trees(1).min_split(100);
+	stump.fit(&step_data(), &mut rng);
+	assert_eq!(stump.eval(&vec![0.0], &0), 6.0/11.0);
+}
+
+#[test]
+fn forest_updates_leaves() {
+	let mut rng = StdRng::from_seed(&[1]);
+	let mut forest = new_forest();
+	forest.fit(&step_data(), &mut rng);
+
+	forest.update(&vec![0.75], &0, 3.0, 0.5);
+	assert!((forest.eval(&vec![0.75], &0) - 2.0).abs() < 1e-10);
+	assert_eq!(forest.eval(&vec![0.25], &0), 0.0);
+
+	forest.update(&vec![0.75], &1, 3.0, 0.5);
+	assert_eq!((forest.eval(&vec![0.75], &1), forest.eval(&vec![0.25], &1)), (1.5, 1.5));
+}
+
+#[test]
+fn forest_state_roundtrip() {
+	let mut rng = StdRng::from_seed(&[1]);
+	let mut forest = new_forest();
+	forest.fit(&step_data(), &mut rng);
+
+	let mut copy = new_forest();
+	copy.set_state(forest.get_state()).unwrap();
+	assert_eq!(copy.eval(&vec![0.75], &0), forest.eval(&vec![0.75], &0));
+
+	let mut featureless: QForest<Line, Finite> = QForest::new(&Finite::new(2));
+	assert!(featureless.set_state(forest.get_state()).is_err());
+}
+
+#[test]
+fn fqi_backs_up_values() {
+	let mut rng = StdRng::from_seed(&[1]);
+	let mut agent: GreedyQAgent<Finite, Finite, QTable<Finite, Finite>> = GreedyQAgent::new(QTable::new(), Finite::new(2));
+
+	// Walks from state 0 to state 3, only rewarded on the last step
+	let transitions = vec![(0, 0, 0.0, 1), (1, 0, 0.0, 2), (2, 0, 1.0, 3)];
+	FittedQIteration::new(Finite::new(2), 0.5, 3).train(&mut agent, transitions, &mut rng);
+	assert_eq!((agent.eval(&0, &0), agent.eval(&1, &0), agent.eval(&2, &0)), (0.25, 0.5, 1.0));
+	assert_eq!(agent.eval(&0, &1), 0.0);
+}
\ No newline at end of file
@@ -0,0 +1,77 @@
+// Checks that least-squares policy iteration improves its policy until the weights settle
+
+extern crate renforce as re;
+extern crate rand;
+
+use rand::{SeedableRng, StdRng};
+
+use re::prelude::*;
+
+use re::environment::{Finite, Range};
+
+use re::trainer::LSPolicyIteration;
+
+use re::util::FeatureExtractor;
+use re::util::approx::QLinear;
+use re::util::feature::IFeature;
+
+type Agent = GreedyQAgent<(), Finite, QLinear<f64, (), Finite>>;
+
+// Action 0 pays 1 and action 1 pays nothing, and neither ever ends the episode
+fn transitions() -> Vec<((), u32, f64, ())> {
+	vec![((), 0, 1.0, ()), ((), 1, 0.0, ())]
+}
+
+// Starts out preferring action 1
+fn new_agent() -> Agent {
+	let mut agent = GreedyQAgent::new(QLinear::default(&Finite::new(2)), Finite::new(2));
+	agent.set_params(vec![0.0, 5.0]);
+	agent
+}
+
+#[test]
+fn iterates_to_greedy_values() {
+	let mut rng = StdRng::from_seed(&[1]);
+
+	// Evaluating the initial policy (always action 1) once
+	let mut agent = new_agent();
+	LSPolicyIteration::new(Finite::new(2), 0.5).max_iters(1).train(&mut agent, transitions(), &mut rng);
+	assert!((agent.eval(&(), &0) - 1.0).abs() < 1e-4 && agent.eval(&(), &1).abs() < 1e-4);
+
+	// With gamma 0.5, always taking action 0 is worth 2
+	let mut agent = new_agent();
+	LSPolicyIteration::new(Finite::new(2), 0.5).train(&mut agent, transitions(), &mut rng);
+	assert!((agent.eval(&(), &0) - 2.0).abs() < 1e-4 && (agent.eval(&(), &1) - 1.0).abs() < 1e-4);
+}
+
+#[test]
+fn ridge_handles_unseen_actions() {
+	let mut rng = StdRng::from_seed(&[1]);
+	let mut agent: Agent = GreedyQAgent::new(QLinear::default(&Finite::new(2)), Finite::new(2));
+
+	// Nothing is known about action 1, so its part of the system is all zeros
+	LSPolicyIteration::new(Finite::new(2), 0.5).train(&mut agent, vec![((), 0, 1.0, ())], &mut rng);
+	assert!((agent.eval(&(), &0) - 2.0).abs() < 1e-4);
+	assert_eq!(agent.eval(&(), &1), 0.0);
+}
+
+#[test]
+fn sparse_features_match_dense() {
+	let mut q_func: QLinear<f64, Vec<Range>, Finite> = QLinear::default(&Finite::new(3));
+	q_func.add(Box::new(IFeature::new(0)));
+
+	let state = vec![2.0];
+	for action in 0..3 {
+		let dense = q_func.extract(&state, &action);
+		let mut sparse = vec![0.0; dense.len()];
+		for (i, x) in q_func.extract_sparse(&state, &action) {
+			sparse[i] = x;
+		}
+		assert_eq!(sparse, dense);
+	}
+}
+#[test]
+#[should_panic]
+fn rejects_zero_ridge() {
+	LSPolicyIteration::new(Finite::new(2), 0.5).ridge(0.0);
+}
\ No newline at end of file
@@ -0,0 +1,122 @@
+// Checks the probabilities, successors and samples given by learned models
+
+extern crate renforce as re;
+extern crate rand;
+
+use rand::{SeedableRng, StdRng};
+
+use re::environment::{Finite, Transition};
+
+use re::model::{Model, PlainModel, DeterministicModel, DeterministicTable, DeterministicAdaptor};
+use re::model::{DPSolver, DPMethod};
+
+use re::util::{Stateful, VFunction};
+use re::util::table::VTable;
+
+fn observed_model() -> PlainModel<Finite, Finite> {
+	let mut model = PlainModel::new();
+	for &(next, reward) in &[(1, 1.0), (2, 2.0), (1, 1.0), (1, 3.0)] {
+		model.update((0, 0, reward, next));
+	}
+	model.update((1, 0, 0.0, 0));
+	model
+}
+
+#[test]
+fn plain_model_counts_observations() {
+	let model = observed_model();
+
+	assert_eq!((model.transition(&0, &0, &1), model.transition(&0, &0, &2), model.transition(&0, &0, &0)), (0.75, 0.25, 0.0));
+	assert_eq!(model.transition(&0, &1, &1), 0.0);
+	assert_eq!(model.successors(&0, &0), vec![(1, 0.75), (2, 0.25)]);
+	assert!(model.successors(&2, &0).is_empty());
+	// Only the last reward is remembered
+	assert_eq!(model.reward(&0, &0, &1), 3.0);
+}
+
+#[test]
+fn plain_model_samples_successors() {
+	let model = observed_model();
+	let mut rng = StdRng::from_seed(&[1]);
+
+	assert_eq!(model.sample(&0, &1, &mut rng), None);
+	assert_eq!(model.sample(&1, &0, &mut rng), Some((0, 0.0)));
+
+	let mut counts = [0; 3];
+	for _ in 0..1000 {
+		let (next, reward) = model.sample(&0, &0, &mut rng).unwrap();
+		assert_eq!(reward, model.reward(&0, &0, &next));
+		counts[next as usize] += 1;
+	}
+	assert_eq!(counts[0], 0);
+	assert!(700 < counts[1] && counts[1] < 800);
+}
+
+#[test]
+fn plain_model_state_roundtrip() {
+	let model = observed_model();
+
+	let mut restored: PlainModel<Finite, Finite> = PlainModel::new();
+	restored.set_state(model.get_state()).unwrap();
+	assert_eq!(restored.successors(&0, &0), model.successors(&0, &0));
+	assert_eq!(restored.reward(&1, &0, &0), model.reward(&1, &0, &0));
+
+	let mut rng = StdRng::from_seed(&[2]);
+	let mut restored_rng = rng.clone();
+	for _ in 0..20 {
+		assert_eq!(model.sample(&0, &0, &mut rng), restored.sample(&0, &0, &mut restored_rng));
+	}
+}
+
+#[test]
+fn deterministic_table_remembers_last_observation() {
+	let mut model: DeterministicTable<Finite, Finite> = DeterministicTable::new();
+	let mut rng = StdRng::from_seed(&[3]);
+
+	assert_eq!((model.transition2(&0, &0), model.reward2(&0, &0)), (None, 0.0));
+	assert_eq!(model.sample(&0, &0, &mut rng), None);
+
+	Model::update(&mut model, (0, 0, 1.0, 1));
+	Model::update(&mut model, (0, 0, 2.0, 2));
+	assert_eq!((model.transition2(&0, &0), model.reward2(&0, &0)), (Some(2), 2.0));
+	assert_eq!((model.transition(&0, &0, &2), model.transition(&0, &0, &1)), (1.0, 0.0));
+	assert_eq!((model.reward(&0, &0, &2), model.reward(&0, &0, &1)), (2.0, 0.0));
+	assert_eq!(model.successors(&0, &0), vec![(2, 1.0)]);
+	assert_eq!(model.sample(&0, &0, &mut rng), Some((2, 2.0)));
+
+	let mut restored: DeterministicTable<Finite, Finite> = DeterministicTable::new();
+	restored.set_state(model.get_state()).unwrap();
+	assert_eq!(restored.successors(&0, &0), model.successors(&0, &0));
+}
+
+// Moves one state to the right (action 1) or left (action 0) along states 0..4, earning 1 for reaching 3
+#[derive(Debug)]
+struct Walk;
+
+impl DeterministicModel<Finite, Finite> for Walk {
+	fn transition2(&self, curr: &u32, action: &u32) -> Option<u32> {
+		if *curr == 3 {
+			None
+		} else if *action == 1 {
+			Some(curr + 1)
+		} else {
+			Some(if *curr == 0 {0} else {curr - 1})
+		}
+	}
+	fn reward2(&self, curr: &u32, action: &u32) -> f64 {
+		if *curr == 2 && *action == 1 {1.0} else {0.0}
+	}
+	fn update(&mut self, _: Transition<Finite, Finite>) {
+	}
+}
+
+#[test]
+fn adaptor_plans_with_deterministic_model() {
+	let model = DeterministicAdaptor::new(Walk);
+	let mut v_func = VTable::new();
+	DPSolver::new(Finite::new(4), Finite::new(2), 0.5, DPMethod::ValueIteration).solve_v(&model, &mut v_func);
+
+	let values: Vec<_> = (0..4).map(|s| v_func.eval(&s)).collect();
+	assert_eq!(values, vec![0.25, 0.5, 1.0, 0.0]);
+	assert_eq!(model.successors(&1, &0), vec![(0, 1.0)]);
+}
\ No newline at end of file
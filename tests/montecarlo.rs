@@ -0,0 +1,129 @@
+// Checks which returns Monte Carlo control learns from
+
+extern crate renforce as re;
+extern crate rand;
+
+use rand::{SeedableRng, StdRng};
+
+use re::prelude::*;
+
+use re::environment::Finite;
+
+use re::trainer::{MonteCarlo, OffPolicyMonteCarlo, VisitType};
+
+use re::util::table::QTable;
+use re::util::chooser::Uniform;
+
+// Stays in state 0 for 3 steps, earning 1 each step
+struct Loop {
+	steps: usize,
+}
+
+impl Environment for Loop {
+	type State = Finite;
+	type Action = Finite;
+
+	fn state_space(&self) -> Finite {
+		Finite::new(1)
+	}
+	fn action_space(&self) -> Finite {
+		Finite::new(2)
+	}
+	fn step(&mut self, _: &u32) -> Observation<Finite> {
+		self.steps += 1;
+		Observation {
+			state: 0,
+			reward: 1.0,
+			done: self.steps == 3
+		}
+	}
+	fn reset(&mut self) -> Observation<Finite> {
+		self.steps = 0;
+		Observation {
+			state: 0,
+			reward: 0.0,
+			done: false
+		}
+	}
+	fn render(&self) {
+	}
+}
+
+// Moves from state 0 to state 1 whatever the action, then pays 1 for action 0 and nothing for action 1
+struct TwoStep {
+	pos: u32,
+}
+
+impl Environment for TwoStep {
+	type State = Finite;
+	type Action = Finite;
+
+	fn state_space(&self) -> Finite {
+		Finite::new(2)
+	}
+	fn action_space(&self) -> Finite {
+		Finite::new(2)
+	}
+	fn step(&mut self, action: &u32) -> Observation<Finite> {
+		self.pos += 1;
+		Observation {
+			state: self.pos,
+			reward: if self.pos == 2 && *action == 0 {1.0} else {0.0},
+			done: self.pos == 2
+		}
+	}
+	fn reset(&mut self) -> Observation<Finite> {
+		self.pos = 0;
+		Observation {
+			state: 0,
+			reward: 0.0,
+			done: false
+		}
+	}
+	fn render(&self) {
+	}
+}
+
+#[test]
+fn first_and_every_visit_differ() {
+	let mut env = Loop {steps: 0};
+	let mut rng = StdRng::from_seed(&[1]);
+
+	// Ties go to action 0, so the episode visits (0, 0) three times with returns 3, 2 and 1
+	let mut agent = GreedyQAgent::new(QTable::new(), env.action_space());
+	MonteCarlo::new(1.0, 1.0, TimePeriod::EPISODES(1)).train(&mut agent, &mut env, &mut rng);
+	assert_eq!(agent.eval(&0, &0), 3.0);
+
+	let mut agent = GreedyQAgent::new(QTable::new(), env.action_space());
+	MonteCarlo::new(1.0, 1.0, TimePeriod::EPISODES(1)).visit_type(VisitType::EveryVisit)
+											   .train(&mut agent, &mut env, &mut rng);
+	assert_eq!(agent.eval(&0, &0), 1.0);
+}
+
+#[test]
+fn unfinished_episodes_are_ignored() {
+	let mut env = Loop {steps: 0};
+	let mut rng = StdRng::from_seed(&[1]);
+
+	let mut agent = GreedyQAgent::new(QTable::new(), env.action_space());
+	MonteCarlo::new(1.0, 1.0, TimePeriod::TIMESTEPS(5)).train(&mut agent, &mut env, &mut rng);
+	assert_eq!(agent.eval(&0, &0), 3.0);
+}
+
+#[test]
+fn off_policy_learns_greedy_values() {
+	let mut env = TwoStep {pos: 0};
+	let mut rng = StdRng::from_seed(&[2]);
+
+	// Acting randomly, the first step is only worth 0.5
+	let mut on_policy = EGreedyQAgent::new(QTable::new(), env.action_space(), 1.0, Uniform);
+	MonteCarlo::new(1.0, 0.05, TimePeriod::EPISODES(300)).train(&mut on_policy, &mut env, &mut rng);
+	assert!((on_policy.eval(&0, &0) - 0.5).abs() < 0.25);
+
+	let mut agent = EGreedyQAgent::new(QTable::new(), env.action_space(), 1.0, Uniform);
+	let mut trainer = OffPolicyMonteCarlo::new(env.action_space(), 1.0, TimePeriod::EPISODES(300));
+	trainer.train(&mut agent, &mut env, &mut rng);
+	assert_eq!((agent.eval(&1, &0), agent.eval(&1, &1)), (1.0, 0.0));
+	assert!(agent.eval(&0, &0) > 0.9 && agent.eval(&0, &1) > 0.9);
+	assert_eq!(trainer.get_state().weights.len(), 4);
+}
\ No newline at end of file
@@ -0,0 +1,108 @@
+// Checks that neural networks plug into the existing agents and trainers
+
+extern crate renforce as re;
+extern crate rand;
+
+mod common;
+
+use rand::{SeedableRng, StdRng};
+
+use re::prelude::*;
+
+use re::environment::Range;
+
+use re::trainer::{QLearner, PolicyGradient};
+
+use re::util::network::{VNetwork, QNetwork, Activation};
+use re::util::chooser::Uniform;
+use re::util::graddesc::GradientDesc;
+use re::util::baseline::LearnedBaseline;
+
+use common::{Walk, Aim, position};
+
+#[test]
+fn qnetwork_qlearner() {
+	let mut env = Walk::new();
+	let mut rng = StdRng::from_seed(&[1]);
+
+	let q_func = QNetwork::new(position(), &[(8, Activation::Tanh)], &env.action_space(), &mut rng);
+	let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.2, Uniform);
+	let mut trainer = QLearner::default(env.action_space()).gamma(0.5).alpha(0.05)
+														   .train_period(TimePeriod::TIMESTEPS(3000));
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	for &x in &[-0.5, 0.0, 0.5] {
+		let state = vec![x];
+		assert!(agent.eval(&state, &1) > agent.eval(&state, &0), "does not prefer stepping right at {}", x);
+	}
+}
+
+#[test]
+fn qnetwork_policy_gradient() {
+	let mut env = Walk::new();
+	let mut rng = StdRng::from_seed(&[2]);
+
+	let log_func = QNetwork::new(position(), &[(8, Activation::Tanh)], &env.action_space(), &mut rng);
+	let mut agent = PolicyAgent::new(env.action_space(), log_func, 1.0);
+	let mut trainer = PolicyGradient::default(GradientDesc).lr(0.05).iters(30)
+														   .eval_period(TimePeriod::EPISODES(2));
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	let rights = (0..100).filter(|_| agent.get_action(&vec![0.0], &mut rng) == 1).count();
+	assert!(rights > 50, "stepped right {} times out of 100", rights);
+}
+
+#[test]
+fn vnetwork_learned_baseline() {
+	let mut env = Walk::new();
+	let mut rng = StdRng::from_seed(&[6]);
+
+	let log_func = QNetwork::new(position(), &[(8, Activation::Tanh)], &env.action_space(), &mut rng);
+	let v_func: VNetwork<f64, Vec<Range>> = VNetwork::new(position(), &[(8, Activation::Tanh)], 1, &mut rng);
+	let mut agent = PolicyAgent::new(env.action_space(), log_func, 1.0);
+	let mut trainer = PolicyGradient::default(GradientDesc).lr(0.01).iters(30)
+														   .eval_period(TimePeriod::EPISODES(2))
+														   .baseline(LearnedBaseline::new(v_func, 0.05));
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	let rights = (0..100).filter(|_| agent.get_action(&vec![0.0], &mut rng) == 1).count();
+	assert!(rights > 50, "stepped right {} times out of 100", rights);
+	assert!(trainer.get_baseline().v_func.eval(&vec![0.0]) > 1.0, "baseline did not learn the returns");
+}
+
+#[test]
+fn vnetwork_gaussian_agent() {
+	let mut env = Aim;
+	let mut rng = StdRng::from_seed(&[3]);
+
+	let mean_func: VNetwork<f64, Vec<Range>> = VNetwork::new(position(), &[(4, Activation::Tanh)], 1, &mut rng);
+	let mut agent: GaussianAgent<f64, f64, Vec<Range>, Vec<Range>, _> = GaussianAgent::new(mean_func, 0.3);
+	let mut trainer = PolicyGradient::default(GradientDesc).lr(0.01).iters(200)
+														   .eval_period(TimePeriod::EPISODES(20));
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	let mean = agent.mean_func.apply(&vec![1.0])[0];
+	assert!((mean - 0.5).abs() < 0.2, "mean action was {}", mean);
+}
+
+#[cfg(feature = "serialize")]
+#[test]
+fn save_load_qnetwork() {
+	use re::environment::Finite;
+	use re::util::checkpoint::{save, load};
+
+	let action_space = Finite::new(2);
+	let new_func = |seed| {
+		let mut rng = StdRng::from_seed(&[seed]);
+		QNetwork::new(position(), &[(3, Activation::ReLU)], &action_space, &mut rng)
+	};
+
+	let q_func: QNetwork<f64, Vec<Range>, Finite> = new_func(4);
+	let mut bytes = Vec::new();
+	save(&q_func, &mut bytes).unwrap();
+
+	let mut restored = new_func(5);
+	load(&mut restored, &bytes[..]).unwrap();
+
+	assert_eq!(q_func.get_params(), restored.get_params());
+}
\ No newline at end of file
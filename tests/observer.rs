@@ -0,0 +1,170 @@
+// Tests that trainers report their progress to observers and respect requests to stop
+
+extern crate renforce as re;
+extern crate rand;
+
+use rand::{SeedableRng, StdRng};
+
+use re::prelude::*;
+
+use re::environment::Finite;
+
+use re::trainer::{QLearner, CrossEntropy};
+
+use re::util::table::QTable;
+use re::util::approx::QLinear;
+use re::util::chooser::Uniform;
+
+// Every episode lasts exactly 5 steps, each worth a reward of 1
+struct Countdown {
+	steps: u32,
+}
+
+impl Environment for Countdown {
+	type State = Finite;
+	type Action = Finite;
+
+	fn state_space(&self) -> Finite {
+		Finite::new(5)
+	}
+	fn action_space(&self) -> Finite {
+		Finite::new(2)
+	}
+	fn step(&mut self, _: &u32) -> Observation<Finite> {
+		self.steps += 1;
+		Observation {
+			state: self.steps % 5,
+			reward: 1.0,
+			done: self.steps % 5 == 0
+		}
+	}
+	fn reset(&mut self) -> Observation<Finite> {
+		self.steps = 0;
+		Observation {
+			state: 0,
+			reward: 0.0,
+			done: false
+		}
+	}
+	fn render(&self) {
+	}
+}
+
+#[derive(Default)]
+struct Recorder {
+	steps: usize,
+	returns: Vec<f64>,
+	scores: Vec<f64>,
+	iters: Vec<usize>,
+	stop_after: Option<usize>,
+	stop_after_iters: Option<usize>,
+}
+
+impl<S: Space, A: Space, T> Observer<S, A, T> for Recorder {
+	fn on_step(&mut self, _: &mut T, _: &Transition<S, A>) -> Signal {
+		self.steps += 1;
+		match self.stop_after {
+			Some(n) if self.steps >= n => Signal::Stop,
+			_ => Signal::Continue,
+		}
+	}
+	fn on_episode_end(&mut self, _: &mut T, ep_return: f64) -> Signal {
+		self.returns.push(ep_return);
+		Signal::Continue
+	}
+	fn on_iter_end(&mut self, _: &mut T, iter: usize, score: f64) -> Signal {
+		self.scores.push(score);
+		self.iters.push(iter);
+		match self.stop_after_iters {
+			Some(n) if self.iters.len() >= n => Signal::Stop,
+			_ => Signal::Continue,
+		}
+	}
+}
+
+#[test]
+fn online_reports_episodes() {
+	let mut env = Countdown {steps: 0};
+	let mut rng = StdRng::from_seed(&[1]);
+	let mut agent = EGreedyQAgent::new(QTable::new(), env.action_space(), 0.1, Uniform);
+	let mut trainer = QLearner::default(env.action_space()).train_period(TimePeriod::EPISODES(4));
+
+	let mut recorder = Recorder::default();
+	trainer.train_with(&mut agent, &mut env, &mut rng, &mut recorder);
+
+	assert_eq!(recorder.steps, 20);
+	assert_eq!(recorder.returns, vec![5.0; 4]);
+	assert_eq!(recorder.scores, vec![5.0; 4]);
+	assert_eq!(recorder.iters, vec![0, 1, 2, 3]);
+}
+
+#[test]
+fn online_stops_early() {
+	let mut env = Countdown {steps: 0};
+	let mut rng = StdRng::from_seed(&[2]);
+	let mut agent = EGreedyQAgent::new(QTable::new(), env.action_space(), 0.1, Uniform);
+	let mut trainer = QLearner::default(env.action_space()).train_period(TimePeriod::EPISODES(100));
+
+	let mut recorder = Recorder {stop_after: Some(7), ..Recorder::default()};
+	trainer.train_with(&mut agent, &mut env, &mut rng, &mut recorder);
+
+	assert_eq!(recorder.steps, 7);
+	assert_eq!(recorder.returns, vec![5.0]);
+	assert_eq!(recorder.scores, vec![5.0, 2.0]);
+}
+
+#[test]
+fn online_stops_after_iteration() {
+	let mut env = Countdown {steps: 0};
+	let mut rng = StdRng::from_seed(&[5]);
+	let mut agent = EGreedyQAgent::new(QTable::new(), env.action_space(), 0.1, Uniform);
+	let mut trainer = QLearner::default(env.action_space()).train_period(TimePeriod::EPISODES(100));
+
+	let mut recorder = Recorder {stop_after_iters: Some(2), ..Recorder::default()};
+	trainer.train_with(&mut agent, &mut env, &mut rng, &mut recorder);
+
+	assert_eq!(recorder.steps, 10);
+	assert_eq!(recorder.iters, vec![0, 1]);
+}
+
+#[test]
+fn episodic_stops_after_iteration() {
+	let mut env = Countdown {steps: 0};
+	let mut rng = StdRng::from_seed(&[3]);
+	let mut agent = EGreedyQAgent::new(QLinear::default(&env.action_space()), env.action_space(), 0.1, Uniform);
+	let mut trainer = CrossEntropy::default().eval_period(TimePeriod::EPISODES(1))
+											 .num_samples(10).iters(5);
+
+	// Asks to stop during the first sample, but the iteration is still finished
+	let mut recorder = Recorder {stop_after: Some(1), ..Recorder::default()};
+	trainer.train_with(&mut agent, &mut env, &mut rng, &mut recorder);
+
+	assert_eq!(recorder.steps, 50);
+	assert_eq!(recorder.scores, vec![5.0]);
+	assert_eq!(trainer.get_state().curr_iter, 1);
+}
+
+type TableAgent = EGreedyQAgent<Finite, Finite, QTable<Finite, Finite>, Uniform>;
+
+// Halves epsilon after every episode
+struct Decay;
+
+impl Observer<Finite, Finite, TableAgent> for Decay {
+	fn on_episode_end(&mut self, agent: &mut TableAgent, _: f64) -> Signal {
+		let epsilon = agent.get_state().epsilon;
+		agent.set_epsilon(epsilon / 2.0);
+		Signal::Continue
+	}
+}
+
+#[test]
+fn observer_decays_epsilon() {
+	let mut env = Countdown {steps: 0};
+	let mut rng = StdRng::from_seed(&[4]);
+	let mut agent = EGreedyQAgent::new(QTable::new(), env.action_space(), 0.8, Uniform);
+	let mut trainer = QLearner::default(env.action_space()).train_period(TimePeriod::EPISODES(3));
+
+	trainer.train_with(&mut agent, &mut env, &mut rng, &mut Decay);
+
+	assert_eq!(agent.get_state().epsilon, 0.1);
+}
\ No newline at end of file
@@ -2,6 +2,9 @@
 // The agent needs to learn to output an odd number
 
 extern crate renforce as re;
+extern crate rand;
+
+use rand::thread_rng;
 
 use re::environment::{Environment, Observation};
 use re::environment::Finite;
@@ -46,10 +49,11 @@ impl Environment for NumberChooser {
 #[test]
 fn learn_to_choose_odd() {
 	let mut env = NumberChooser;
+	let mut rng = thread_rng();
 	let mut agent = GreedyQAgent::new(QTable::new(), env.action_space());
 	let mut trainer = QLearner::new(env.action_space(), 0.9, 0.9, TimePeriod::TIMESTEPS(100));
 
-	trainer.train(&mut agent, &mut env);
+	trainer.train(&mut agent, &mut env, &mut rng);
 
-	assert!(agent.get_action(&())%2 == 1, "The agent should have learned to pick odd numbers");
+	assert!(agent.get_action(&(), &mut rng)%2 == 1, "The agent should have learned to pick odd numbers");
 }
\ No newline at end of file
@@ -0,0 +1,305 @@
+// Checks that evaluating parameter samples on several threads is reproducible and reports everything to observers
+
+extern crate renforce as re;
+extern crate rand;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rand::{Rng, SeedableRng, StdRng};
+
+use re::prelude::*;
+
+use re::environment::{Finite, Range};
+
+use re::trainer::{CrossEntropy, NaturalEvo};
+
+use re::util::Chooser;
+use re::util::chooser::Softmax;
+
+const SEED: &'static [usize] = &[2, 7, 1, 8];
+
+// Rewards the agent for picking numbers close to 7
+struct Target;
+
+impl Environment for Target {
+	type State = ();
+	type Action = Finite;
+
+	fn state_space(&self) {}
+	fn action_space(&self) -> Finite {
+		Finite::new(10)
+	}
+	fn step(&mut self, action: &u32) -> Observation<()> {
+		Observation {
+			state: (),
+			reward: -(*action as f64 - 7.0).abs(),
+			done: *action == 7
+		}
+	}
+	fn reset(&mut self) -> Observation<()> {
+		Observation {
+			state: (),
+			reward: 0.0,
+			done: false
+		}
+	}
+	fn render(&self) {
+	}
+}
+
+// Like Target, but the target wanders randomly every step, using its own rng
+struct Wandering {
+	rng: StdRng,
+	target: f64,
+}
+
+impl Wandering {
+	fn new() -> Wandering {
+		Wandering {rng: StdRng::from_seed(&[3, 1, 4]), target: 7.0}
+	}
+}
+
+impl Environment for Wandering {
+	type State = ();
+	type Action = Finite;
+
+	fn state_space(&self) {}
+	fn action_space(&self) -> Finite {
+		Finite::new(10)
+	}
+	fn step(&mut self, action: &u32) -> Observation<()> {
+		self.target += self.rng.gen_range(-1.0, 1.0);
+		Observation {
+			state: (),
+			reward: -(*action as f64 - self.target).abs(),
+			done: false
+		}
+	}
+	fn reset(&mut self) -> Observation<()> {
+		Observation {
+			state: (),
+			reward: 0.0,
+			done: false
+		}
+	}
+	fn render(&self) {
+	}
+}
+
+// Picks numbers with a softmax over its parameters
+#[derive(Debug, Clone)]
+struct Picker {
+	params: Vec<f64>,
+}
+
+impl Picker {
+	fn new() -> Picker {
+		Picker {params: vec![0.0; 10]}
+	}
+}
+
+impl Agent<(), Finite> for Picker {
+	fn get_action<R: Rng>(&self, _: &(), rng: &mut R) -> u32 {
+		Softmax::default().choose(&(0..10).collect::<Vec<_>>(), self.params.clone(), rng)
+	}
+}
+
+impl ParameterizedFunc<f64> for Picker {
+	fn num_params(&self) -> usize {
+		self.params.len()
+	}
+	fn get_params(&self) -> Vec<f64> {
+		self.params.clone()
+	}
+	fn set_params(&mut self, params: Vec<f64>) {
+		self.params = params;
+	}
+}
+
+// Acts by outputting its parameters
+#[derive(Debug, Clone)]
+struct Point {
+	params: Vec<f64>,
+}
+
+impl Agent<(), Vec<Range>> for Point {
+	fn get_action<R: Rng>(&self, _: &(), _: &mut R) -> Vec<f64> {
+		self.params.clone()
+	}
+}
+
+impl ParameterizedFunc<f64> for Point {
+	fn num_params(&self) -> usize {
+		self.params.len()
+	}
+	fn get_params(&self) -> Vec<f64> {
+		self.params.clone()
+	}
+	fn set_params(&mut self, params: Vec<f64>) {
+		self.params = params;
+	}
+}
+
+// Pays -((x - 2)^2 + (y - 1)^2) in one step
+struct Bowl;
+
+impl Environment for Bowl {
+	type State = ();
+	type Action = Vec<Range>;
+
+	fn state_space(&self) {}
+	fn action_space(&self) -> Vec<Range> {
+		vec![Range::new(-10.0, 10.0); 2]
+	}
+	fn step(&mut self, action: &Vec<f64>) -> Observation<()> {
+		Observation {
+			state: (),
+			reward: -((action[0] - 2.0).powi(2) + (action[1] - 1.0).powi(2)),
+			done: true
+		}
+	}
+	fn reset(&mut self) -> Observation<()> {
+		Observation {
+			state: (),
+			reward: 0.0,
+			done: false
+		}
+	}
+	fn render(&self) {
+	}
+}
+
+// Counts what it sees, asking to stop once it has seen stop_after episodes
+#[derive(Default)]
+struct Counter {
+	steps: usize,
+	episodes: usize,
+	iters: usize,
+	stop_after: Option<usize>,
+}
+
+impl<T> Observer<(), Finite, T> for Counter {
+	fn on_step(&mut self, _: &mut T, _: &((), u32, f64, ())) -> Signal {
+		self.steps += 1;
+		Signal::Continue
+	}
+	fn on_episode_end(&mut self, _: &mut T, _: f64) -> Signal {
+		self.episodes += 1;
+		match self.stop_after {
+			Some(n) if self.episodes >= n => Signal::Stop,
+			_ => Signal::Continue,
+		}
+	}
+	fn on_iter_end(&mut self, _: &mut T, _: usize, _: f64) -> Signal {
+		self.iters += 1;
+		Signal::Continue
+	}
+}
+
+fn train_cem(num_threads: usize) -> Vec<f64> {
+	let mut rng = StdRng::from_seed(SEED);
+	let mut agent = Picker::new();
+	let mut trainer = CrossEntropy::default().eval_period(TimePeriod::TIMESTEPS(10))
+											 .num_samples(20).iters(5);
+
+	trainer.train_parallel(&mut agent, || Target, num_threads, &mut rng);
+	agent.get_params()
+}
+
+fn train_nes(num_threads: usize) -> Vec<f64> {
+	let mut rng = StdRng::from_seed(SEED);
+	let mut agent = Picker::new();
+	let mut trainer = NaturalEvo::default().eval_period(TimePeriod::TIMESTEPS(10))
+										   .num_samples(20).iters(5);
+
+	trainer.train_parallel(&mut agent, || Target, num_threads, &mut rng);
+	agent.get_params()
+}
+
+fn train_cem_wandering(num_threads: usize) -> Vec<f64> {
+	let mut rng = StdRng::from_seed(SEED);
+	let mut agent = Picker::new();
+	let mut trainer = CrossEntropy::default().eval_period(TimePeriod::TIMESTEPS(10))
+											 .num_samples(20).iters(5);
+
+	trainer.train_parallel(&mut agent, Wandering::new, num_threads, &mut rng);
+	agent.get_params()
+}
+
+#[test]
+fn cem_parallel_same_seed() {
+	let params = train_cem(1);
+	assert_eq!(params, train_cem(1));
+	assert_eq!(params, train_cem(3));
+	assert_eq!(params, train_cem(8));
+}
+
+#[test]
+fn nes_parallel_same_seed() {
+	let params = train_nes(1);
+	assert_eq!(params, train_nes(2));
+	assert_eq!(params, train_nes(5));
+}
+
+#[test]
+fn parallel_same_seed_with_random_environment() {
+	let params = train_cem_wandering(1);
+	assert_eq!(params, train_cem_wandering(2));
+	assert_eq!(params, train_cem_wandering(6));
+}
+
+#[test]
+fn cem_parallel_approaches_bowl_minimum() {
+	let mut rng = StdRng::from_seed(SEED);
+	let mut agent = Point {params: vec![0.0, 0.0]};
+
+	let mut trainer = CrossEntropy::default().iters(20);
+	trainer.train_parallel(&mut agent, || Bowl, 4, &mut rng);
+	// Starts a distance of sqrt(5) away
+	let dist2 = (agent.params[0] - 2.0).powi(2) + (agent.params[1] - 1.0).powi(2);
+	assert!(dist2 < 0.5, "ended at {:?}", agent.params);
+}
+
+#[test]
+fn parallel_observer_sees_every_step() {
+	let mut rng = StdRng::from_seed(SEED);
+	let mut agent = Picker::new();
+	let mut trainer = CrossEntropy::default().eval_period(TimePeriod::TIMESTEPS(10))
+											 .num_samples(20).iters(3);
+
+	let mut counter = Counter::default();
+	trainer.train_parallel_with(&mut agent, || Target, 4, &mut rng, &mut counter);
+	assert_eq!(counter.steps, 3*20*10);
+	assert_eq!(counter.iters, 3);
+	assert!(counter.episodes > 0);
+}
+
+#[test]
+fn parallel_observer_stops_after_iteration() {
+	let mut rng = StdRng::from_seed(SEED);
+	let mut agent = Picker::new();
+	let mut trainer = NaturalEvo::default().eval_period(TimePeriod::EPISODES(1))
+										   .num_samples(20).iters(3);
+
+	let mut counter = Counter {stop_after: Some(1), ..Counter::default()};
+	trainer.train_parallel_with(&mut agent, || Target, 4, &mut rng, &mut counter);
+	assert_eq!(counter.episodes, 20);
+	assert_eq!(counter.iters, 1);
+}
+
+#[test]
+fn parallel_environment_made_per_sample() {
+	let mut rng = StdRng::from_seed(SEED);
+	let mut agent = Picker::new();
+	let mut trainer = NaturalEvo::default().eval_period(TimePeriod::EPISODES(1))
+										   .num_samples(20).iters(3);
+
+	let made = Arc::new(AtomicUsize::new(0));
+	let counter = made.clone();
+	trainer.train_parallel(&mut agent, move || {
+		counter.fetch_add(1, Ordering::SeqCst);
+		Target
+	}, 4, &mut rng);
+	assert_eq!(made.load(Ordering::SeqCst), 3*20);
+}
\ No newline at end of file
@@ -0,0 +1,99 @@
+// Checks that PPO works with both discrete and continuous action agents
+
+extern crate renforce as re;
+extern crate rand;
+
+mod common;
+
+use rand::{SeedableRng, StdRng};
+
+use re::prelude::*;
+
+use re::environment::{Finite, Range};
+
+use re::trainer::PPO;
+
+use re::util::network::{VNetwork, QNetwork, Activation};
+use re::util::graddesc::GradientDesc;
+
+use common::{Walk, Aim, position};
+
+#[test]
+fn policy_agent_log_probs() {
+	let mut rng = StdRng::from_seed(&[1]);
+	let action_space = Finite::new(3);
+
+	let log_func = QNetwork::new(position(), &[(4, Activation::Tanh)], &action_space, &mut rng);
+	let agent: PolicyAgent<f64, Vec<Range>, Finite, _> = PolicyAgent::new(action_space, log_func, 0.5);
+
+	let total: f64 = (0..3).map(|a| agent.log_prob(&vec![0.3], &a).exp()).sum();
+	assert!((total - 1.0).abs() < 1e-10, "probabilities summed to {}", total);
+}
+
+#[test]
+fn gaussian_agent_log_probs() {
+	let mut rng = StdRng::from_seed(&[2]);
+
+	let mean_func: VNetwork<f64, Vec<Range>> = VNetwork::new(position(), &[], 1, &mut rng);
+	let agent: GaussianAgent<f64, f64, Vec<Range>, Vec<Range>, _> = GaussianAgent::new(mean_func, 0.5);
+
+	let mean = agent.mean_func.apply(&vec![1.0])[0];
+	let peak = agent.log_prob(&vec![1.0], &vec![mean]);
+	assert!((peak + (0.5*(2.0*std::f64::consts::PI).sqrt()).ln()).abs() < 1e-10);
+	assert!(agent.log_prob(&vec![1.0], &vec![mean + 1.0]) < peak);
+}
+
+#[test]
+fn policy_agent_ppo() {
+	let mut env = Walk::new();
+	let mut rng = StdRng::from_seed(&[3]);
+
+	let log_func = QNetwork::new(position(), &[(8, Activation::Tanh)], &env.action_space(), &mut rng);
+	let mut agent = PolicyAgent::new(env.action_space(), log_func, 1.0);
+	let mut trainer = PPO::default(GradientDesc).lr(0.05).iters(30).batch_size(10)
+												.entropy_coeff(0.01)
+												.eval_period(TimePeriod::EPISODES(2));
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	let rights = (0..100).filter(|_| agent.get_action(&vec![0.0], &mut rng) == 1).count();
+	assert!(rights > 50, "stepped right {} times out of 100", rights);
+}
+
+#[test]
+fn gaussian_agent_ppo() {
+	let mut env = Aim;
+	let mut rng = StdRng::from_seed(&[4]);
+
+	let mean_func: VNetwork<f64, Vec<Range>> = VNetwork::new(position(), &[(4, Activation::Tanh)], 1, &mut rng);
+	let mut agent: GaussianAgent<f64, f64, Vec<Range>, Vec<Range>, _> = GaussianAgent::new(mean_func, 0.3);
+	let mut trainer = PPO::default(GradientDesc).lr(0.005).iters(200).batch_size(5)
+												.eval_period(TimePeriod::EPISODES(20));
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	let mean = agent.mean_func.apply(&vec![1.0])[0];
+	assert!((mean - 0.5).abs() < 0.2, "mean action was {}", mean);
+}
+
+type LinearGaussian = GaussianAgent<f64, f64, Vec<Range>, Vec<Range>, VNetwork<f64, Vec<Range>>>;
+
+#[test]
+fn kl_limit_restrains_updates() {
+	let mut env = Aim;
+	let new_agent = |seed| -> LinearGaussian {
+		let mut rng = StdRng::from_seed(&[seed]);
+		GaussianAgent::new(VNetwork::new(position(), &[], 1, &mut rng), 0.3)
+	};
+
+	let (mut free, mut limited) = (new_agent(5), new_agent(5));
+	let start = free.mean_func.apply(&vec![1.0])[0];
+
+	let trainer = || PPO::default(GradientDesc).lr(0.005).iters(1).epochs(20).clip(10.0)
+											   .eval_period(TimePeriod::EPISODES(20));
+	trainer().train(&mut free, &mut env, &mut StdRng::from_seed(&[6]));
+	trainer().max_kl(0.01).train(&mut limited, &mut env, &mut StdRng::from_seed(&[6]));
+
+	let moved = |agent: &LinearGaussian| {
+		(agent.mean_func.apply(&vec![1.0])[0] - start).abs()
+	};
+	assert!(moved(&limited) < moved(&free), "limited moved {}, free moved {}", moved(&limited), moved(&free));
+}
\ No newline at end of file
@@ -0,0 +1,162 @@
+// Checks that training is reproducible when every source of randomness is seeded
+// Two runs using rngs built from the same seed should end with identical agents
+
+extern crate renforce as re;
+extern crate rand;
+
+mod common;
+
+use rand::{SeedableRng, StdRng};
+
+use re::prelude::*;
+
+use re::environment::{Finite, Range};
+
+use re::trainer::{QLearner, DynaQ, PolicyGradient, CrossEntropy, NaturalEvo, ActorCritic};
+
+use re::model::PlainModel;
+
+use re::util::table::QTable;
+use re::util::approx::{VLinear, QLinear};
+use re::util::chooser::Softmax;
+use re::util::graddesc::GradientDesc;
+
+use common::{Aim, position};
+
+const SEED: &'static [usize] = &[3, 1, 4, 1, 5];
+
+// Rewards the agent for picking numbers close to 7
+struct Target;
+
+impl Environment for Target {
+	type State = ();
+	type Action = Finite;
+
+	fn state_space(&self) {}
+	fn action_space(&self) -> Finite {
+		Finite::new(10)
+	}
+	fn step(&mut self, action: &u32) -> Observation<()> {
+		Observation {
+			state: (),
+			reward: -(*action as f64 - 7.0).abs(),
+			done: *action == 7
+		}
+	}
+	fn reset(&mut self) -> Observation<()> {
+		Observation {
+			state: (),
+			reward: 0.0,
+			done: false
+		}
+	}
+	fn render(&self) {
+	}
+}
+
+#[test]
+fn sample_same_seed() {
+	let space = (Finite::new(100), Finite::new(100));
+	let (mut rng1, mut rng2) = (StdRng::from_seed(SEED), StdRng::from_seed(SEED));
+
+	let samples1: Vec<_> = (0..50).map(|_| space.sample(&mut rng1)).collect();
+	let samples2: Vec<_> = (0..50).map(|_| space.sample(&mut rng2)).collect();
+	assert_eq!(samples1, samples2);
+}
+
+#[test]
+fn qlearner_same_seed() {
+	let train = || {
+		let mut env = Target;
+		let mut rng = StdRng::from_seed(SEED);
+		let mut agent = EGreedyQAgent::new(QTable::new(), env.action_space(), 0.3, Softmax::default());
+		let mut trainer = QLearner::default(env.action_space()).train_period(TimePeriod::TIMESTEPS(500));
+
+		trainer.train(&mut agent, &mut env, &mut rng);
+		(0..10).map(|a| agent.eval(&(), &a)).collect::<Vec<_>>()
+	};
+
+	assert_eq!(train(), train());
+}
+
+#[test]
+fn cem_same_seed() {
+	let train = || {
+		let mut env = Target;
+		let mut rng = StdRng::from_seed(SEED);
+		let q_func = QLinear::default(&env.action_space());
+		let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.1, Softmax::default());
+		let mut trainer = CrossEntropy::default().eval_period(TimePeriod::TIMESTEPS(10))
+												 .num_samples(20).iters(5);
+
+		trainer.train(&mut agent, &mut env, &mut rng);
+		agent.get_params()
+	};
+
+	assert_eq!(train(), train());
+}
+
+#[test]
+fn dyna_same_seed() {
+	let train = || {
+		let mut env = Target;
+		let mut rng = StdRng::from_seed(SEED);
+		let mut agent = EGreedyQAgent::new(QTable::new(), env.action_space(), 0.3, Softmax::default());
+		let mut trainer = DynaQ::default(env.action_space(), PlainModel::new()).train_period(TimePeriod::TIMESTEPS(200));
+
+		trainer.train(&mut agent, &mut env, &mut rng);
+		(0..10).map(|a| agent.eval(&(), &a)).collect::<Vec<_>>()
+	};
+
+	assert_eq!(train(), train());
+}
+
+#[test]
+fn pg_same_seed() {
+	let train = || {
+		let mut env = Target;
+		let mut rng = StdRng::from_seed(SEED);
+		let mut agent = PolicyAgent::new(env.action_space(), QLinear::default(&env.action_space()), 0.1);
+		let mut trainer = PolicyGradient::default(GradientDesc).eval_period(TimePeriod::TIMESTEPS(50)).iters(5);
+
+		trainer.train(&mut agent, &mut env, &mut rng);
+		agent.get_params()
+	};
+
+	assert_eq!(train(), train());
+}
+
+#[test]
+fn nes_same_seed() {
+	let train = || {
+		let mut env = Target;
+		let mut rng = StdRng::from_seed(SEED);
+		let q_func = QLinear::default(&env.action_space());
+		let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.1, Softmax::default());
+		let mut trainer = NaturalEvo::default().eval_period(TimePeriod::TIMESTEPS(10))
+											   .num_samples(20).iters(5);
+
+		trainer.train(&mut agent, &mut env, &mut rng);
+		agent.get_params()
+	};
+
+	assert_eq!(train(), train());
+}
+
+#[test]
+fn actor_critic_linear_same_seed() {
+	let train = || {
+		let mut env = Aim;
+		let mut rng = StdRng::from_seed(SEED);
+		let mean_func: VLinear<f64, Vec<Range>> = VLinear::with_features(position());
+		let mut agent: GaussianAgent<f64, f64, Vec<Range>, Vec<Range>, _> = GaussianAgent::new(mean_func, 0.3);
+		let critic: VLinear<f64, Vec<Range>> = VLinear::with_features(position());
+		let mut trainer = ActorCritic::default(critic, GradientDesc).lr(0.01).iters(5)
+																	.eval_period(TimePeriod::EPISODES(10));
+
+		trainer.train(&mut agent, &mut env, &mut rng);
+		(agent.get_params(), trainer.get_critic().eval(&vec![1.0]))
+	};
+
+	assert_eq!(train(), train());
+}
\ No newline at end of file
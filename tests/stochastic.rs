@@ -0,0 +1,150 @@
+// Checks that the probabilities reported by stochastic agents match how they actually act
+
+extern crate renforce as re;
+extern crate rand;
+
+mod common;
+
+use rand::{SeedableRng, StdRng};
+
+use re::prelude::*;
+
+use re::environment::{Finite, Range};
+
+use re::util::Chooser;
+use re::util::table::QTable;
+use re::util::network::{VNetwork, QNetwork, Activation};
+use re::util::chooser::{Uniform, Softmax};
+
+use common::position;
+
+// The fraction of 10000 samples in which the agent chose each of the actions in action_space
+fn frequencies<S: Space, T: Agent<S, Finite>>(agent: &T, state: &S::Element, action_space: &Finite) -> Vec<f64> {
+	let mut rng = StdRng::from_seed(&[1]);
+	let mut counts = vec![0.0; action_space.size()];
+	for _ in 0..10000 {
+		counts[agent.get_action(state, &mut rng) as usize] += 1.0;
+	}
+	counts.into_iter().map(|c| c/10000.0).collect()
+}
+
+fn assert_close(probs: Vec<f64>, expected: Vec<f64>, tol: f64) {
+	assert_eq!(probs.len(), expected.len());
+	for (p, e) in probs.iter().zip(&expected) {
+		assert!((p - e).abs() < tol, "expected {:?}, found {:?}", expected, probs);
+	}
+}
+
+fn q_table() -> QTable<Finite, Finite> {
+	let mut q_func = QTable::new();
+	q_func.update(&0, &1, 1.0, 1.0);
+	q_func.update(&0, &2, 0.5, 1.0);
+	q_func
+}
+
+#[test]
+fn egreedy_probabilities() {
+	let action_space = Finite::new(3);
+	let agent = EGreedyQAgent::new(q_table(), action_space, 0.3, Uniform);
+
+	let probs = agent.get_action_probs(&0, &action_space);
+	assert_close(probs.clone(), vec![0.1, 0.8, 0.1], 1e-10);
+	assert_close(frequencies(&agent, &0, &action_space), probs, 0.02);
+	assert!((agent.get_action_prob(&0, &1) - 0.8).abs() < 1e-10);
+
+	let agent = EGreedyQAgent::new(q_table(), action_space, 0.5, Softmax::default());
+	let probs = agent.get_action_probs(&0, &action_space);
+	assert!((probs.iter().sum::<f64>() - 1.0).abs() < 1e-10);
+	assert_close(frequencies(&agent, &0, &action_space), probs, 0.02);
+}
+
+#[test]
+fn greedy_probabilities() {
+	let action_space = Finite::new(3);
+	let agent = GreedyQAgent::new(q_table(), action_space);
+
+	assert_eq!(agent.get_action_probs(&0, &action_space), vec![0.0, 1.0, 0.0]);
+	assert_eq!(agent.get_action_prob(&0, &2), 0.0);
+	// Ties go to the first action
+	assert_eq!(agent.get_action_probs(&1, &action_space), vec![1.0, 0.0, 0.0]);
+}
+
+#[test]
+fn random_probabilities() {
+	let action_space = Finite::new(4);
+	let agent = RandomAgent::new(action_space);
+
+	let probs = StochasticAgent::<Finite, Finite>::get_action_probs(&agent, &0, &action_space);
+	assert_eq!(probs, vec![0.25; 4]);
+	assert_eq!(StochasticAgent::<Finite, Finite>::get_action_prob(&agent, &0, &7), 0.0);
+}
+
+#[test]
+fn policy_agent_probabilities() {
+	let mut rng = StdRng::from_seed(&[2]);
+	let action_space = Finite::new(3);
+
+	let log_func = QNetwork::new(position(), &[(4, Activation::Tanh)], &action_space, &mut rng);
+	let agent = PolicyAgent::new(action_space, log_func, 0.5);
+
+	let state = vec![0.7];
+	let probs = agent.get_action_probs(&state, &action_space);
+	assert!((probs.iter().sum::<f64>() - 1.0).abs() < 1e-10);
+	assert!((agent.get_action_prob(&state, &2) - agent.log_prob(&state, &2).exp()).abs() < 1e-10);
+	assert_close(frequencies(&agent, &state, &action_space), probs, 0.02);
+}
+
+#[test]
+fn policy_agent_log_grad() {
+	let mut rng = StdRng::from_seed(&[4]);
+	let action_space = Finite::new(3);
+
+	let log_func = QNetwork::new(position(), &[(4, Activation::Tanh)], &action_space, &mut rng);
+	let mut agent = PolicyAgent::new(action_space, log_func, 0.5);
+
+	// Compare with central differences of log_prob
+	let (state, action) = (vec![0.7], 1);
+	let grad = agent.log_grad(&state, &action);
+	let params = agent.get_params();
+	let eps = 1e-6;
+	for i in 0..params.len() {
+		let mut shifted = params.clone();
+		shifted[i] += eps;
+		agent.set_params(shifted.clone());
+		let up = agent.log_prob(&state, &action);
+		shifted[i] -= 2.0*eps;
+		agent.set_params(shifted);
+		let down = agent.log_prob(&state, &action);
+		agent.set_params(params.clone());
+
+		let numeric = (up - down)/(2.0*eps);
+		assert!((grad[i] - numeric).abs() < 1e-5, "parameter {}: log_grad {} but finite difference {}", i, grad[i], numeric);
+	}
+}
+
+#[test]
+fn gaussian_agent_density() {
+	let mut rng = StdRng::from_seed(&[3]);
+
+	let mean_func: VNetwork<f64, Vec<Range>> = VNetwork::new(position(), &[], 1, &mut rng);
+	let agent: GaussianAgent<f64, f64, Vec<Range>, Vec<Range>, _> = GaussianAgent::new(mean_func, 0.5);
+
+	// The density should integrate to 1
+	let state = vec![1.0];
+	let mean = agent.mean_func.apply(&state)[0];
+	let total: f64 = (-400..400).map(|i| {
+		0.01 * agent.get_action_prob(&state, &vec![mean + 0.01*i as f64])
+	}).sum();
+	assert!((total - 1.0).abs() < 1e-3, "density integrated to {}", total);
+}
+
+#[test]
+fn softmax_large_weights() {
+	let mut rng = StdRng::from_seed(&[2]);
+	let (choices, weights) = (vec![0, 1], vec![1000.0, 999.0]);
+
+	let probs = Chooser::<u32>::probabilities(&Softmax::default(), weights.clone());
+	let picks = (0..10000).filter(|_| Softmax::default().choose(&choices, weights.clone(), &mut rng) == 0).count();
+	assert_close(vec![picks as f64/10000.0], vec![probs[0]], 0.02);
+	assert_close(probs, vec![0.731, 0.269], 0.001);
+}
\ No newline at end of file
@@ -0,0 +1,104 @@
+// Checks how prioritized sweeping plans with its learned model
+
+extern crate renforce as re;
+extern crate rand;
+
+use rand::{SeedableRng, StdRng};
+
+use re::prelude::*;
+
+use re::environment::Finite;
+
+use re::trainer::{QLearner, PrioritizedSweeping};
+
+use re::model::PlainModel;
+
+use re::util::table::QTable;
+
+// Walks from state 0 to state 3 regardless of the action taken, only rewarded on the last step
+struct Chain {
+	pos: u32,
+}
+
+impl Environment for Chain {
+	type State = Finite;
+	type Action = Finite;
+
+	fn state_space(&self) -> Finite {
+		Finite::new(4)
+	}
+	fn action_space(&self) -> Finite {
+		Finite::new(2)
+	}
+	fn step(&mut self, _: &u32) -> Observation<Finite> {
+		self.pos += 1;
+		Observation {
+			state: self.pos,
+			reward: if self.pos == 3 {1.0} else {0.0},
+			done: self.pos == 3
+		}
+	}
+	fn reset(&mut self) -> Observation<Finite> {
+		self.pos = 0;
+		Observation {
+			state: 0,
+			reward: 0.0,
+			done: false
+		}
+	}
+	fn render(&self) {
+	}
+}
+
+type ChainAgent = GreedyQAgent<Finite, Finite, QTable<Finite, Finite>>;
+
+fn greedy_agent() -> ChainAgent {
+	GreedyQAgent::new(QTable::new(), Finite::new(2))
+}
+
+#[test]
+fn sweeps_reward_backwards() {
+	let mut env = Chain {pos: 0};
+	let mut rng = StdRng::from_seed(&[1]);
+
+	let mut one_step = greedy_agent();
+	QLearner::new(env.action_space(), 0.5, 1.0, TimePeriod::EPISODES(1)).train(&mut one_step, &mut env, &mut rng);
+	assert_eq!((one_step.eval(&0, &0), one_step.eval(&1, &0)), (0.0, 0.0));
+
+	let mut agent = greedy_agent();
+	let mut trainer = PrioritizedSweeping::new(env.action_space(), 0.5, 1.0, TimePeriod::EPISODES(1), 5, PlainModel::new());
+	trainer.train(&mut agent, &mut env, &mut rng);
+	assert_eq!((agent.eval(&0, &0), agent.eval(&1, &0), agent.eval(&2, &0)), (0.25, 0.5, 1.0));
+	// Untried actions are never planned without an exploration bonus
+	assert_eq!(agent.eval(&0, &1), 0.0);
+}
+
+#[test]
+fn planning_stops_when_queue_empties() {
+	let mut env = Chain {pos: 0};
+	let mut rng = StdRng::from_seed(&[2]);
+
+	let mut agent = greedy_agent();
+	let mut trainer = PrioritizedSweeping::new(env.action_space(), 0.5, 1.0, TimePeriod::EPISODES(1), 1, PlainModel::new());
+	trainer.train(&mut agent, &mut env, &mut rng);
+	// Only one planning update was allowed after the reward, so the sweep is still queued
+	assert_eq!((agent.eval(&0, &0), agent.eval(&1, &0), agent.eval(&2, &0)), (0.0, 0.0, 1.0));
+
+	trainer.train(&mut agent, &mut env, &mut rng);
+	assert_eq!((agent.eval(&0, &0), agent.eval(&1, &0)), (0.25, 0.5));
+}
+
+#[test]
+fn bonus_rewards_untried_actions() {
+	let mut env = Chain {pos: 0};
+	let mut rng = StdRng::from_seed(&[3]);
+
+	let mut agent = greedy_agent();
+	let mut trainer = PrioritizedSweeping::new(env.action_space(), 0.5, 1.0, TimePeriod::EPISODES(3), 20, PlainModel::new())
+										  .kappa(0.1);
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	let untried = if agent.eval(&0, &0) > agent.eval(&0, &1) {1} else {0};
+	assert!(agent.eval(&0, &untried) > 0.0);
+	assert!(agent.eval(&1, &untried) > 0.0);
+}
\ No newline at end of file
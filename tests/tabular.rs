@@ -0,0 +1,108 @@
+// Checks that a TabularModel can be shared between planners and learners
+
+extern crate renforce as re;
+extern crate rand;
+
+use rand::{SeedableRng, StdRng};
+
+use re::prelude::*;
+
+use re::environment::Finite;
+
+use re::model::{Model, TabularModel, DPSolver, DPMethod};
+
+use re::trainer::QLearner;
+
+use re::util::table::{QTable, VTable};
+use re::util::chooser::Uniform;
+
+// In state 0, action 0 earns 0.5 and stays while action 1 moves to state 1
+// In state 1, action 0 earns 10 and ends the episode (state 2) while action 1 moves to a random nonterminal state
+fn small_mdp() -> TabularModel<Finite, Finite> {
+	TabularModel::new(Finite::new(3), Finite::new(2))
+		.with_transition(0, 0, 0, 1.0, 0.5)
+		.with_transition(0, 1, 1, 1.0, 0.0)
+		.with_transition(1, 0, 2, 1.0, 10.0)
+		.with_transition(1, 1, 0, 0.5, 0.0)
+		.with_transition(1, 1, 1, 0.5, 0.0)
+		.with_terminal(2)
+}
+
+#[test]
+fn model_reads_tables() {
+	let mdp = small_mdp();
+
+	assert_eq!((mdp.transition(&1, &1, &0), mdp.transition(&1, &1, &2), mdp.transition(&2, &0, &2)), (0.5, 0.0, 0.0));
+	assert_eq!((mdp.reward(&1, &0, &2), mdp.reward(&0, &1, &1)), (10.0, 0.0));
+	assert_eq!(mdp.successors(&1, &1), vec![(0, 0.5), (1, 0.5)]);
+	assert!(mdp.successors(&2, &0).is_empty());
+
+	let mdp = mdp.with_transition(1, 1, 0, 0.25, 1.0).with_transition(1, 1, 1, 0.75, 0.0);
+	assert_eq!(mdp.successors(&1, &1), vec![(0, 0.25), (1, 0.75)]);
+	assert_eq!(mdp.reward(&1, &1, &0), 1.0);
+}
+
+#[test]
+fn environment_samples_tables() {
+	let mut mdp = small_mdp().start_dist(vec![(0, 0.5), (1, 0.5)]).seed(&[1]);
+
+	let mut counts = [0; 2];
+	let mut starts = [0; 2];
+	for _ in 0..1000 {
+		starts[mdp.reset().state as usize] += 1;
+		let obs = mdp.step(&1);
+		if obs.state == 0 {
+			assert_eq!(obs.reward, 0.0);
+		}
+		assert!(!obs.done);
+		counts[obs.state as usize] += 1;
+	}
+	assert!(400 < starts[0] && starts[0] < 600);
+	assert!(200 < counts[0] && counts[0] < 800);
+
+	mdp = mdp.start(1);
+	assert_eq!(mdp.reset().state, 1);
+	let obs = mdp.step(&0);
+	assert_eq!((obs.state, obs.reward, obs.done), (2, 10.0, true));
+}
+
+#[test]
+fn seeded_environments_match() {
+	let run = |seed| {
+		let mut mdp = small_mdp().start(1).seed(seed);
+		mdp.reset();
+		(0..20).map(|_| mdp.step(&1).state).collect::<Vec<_>>()
+	};
+	assert_eq!(run(&[3]), run(&[3]));
+}
+
+#[test]
+fn learner_matches_planner() {
+	let mut mdp = small_mdp().seed(&[2]);
+	let mut rng = StdRng::from_seed(&[2]);
+
+	let mut v_func = VTable::new();
+	DPSolver::new(Finite::new(3), Finite::new(2), 0.9, DPMethod::ValueIteration).solve_v(&mdp, &mut v_func);
+
+	let mut agent = EGreedyQAgent::new(QTable::new(), Finite::new(2), 0.5, Uniform);
+	QLearner::new(Finite::new(2), 0.9, 0.1, TimePeriod::TIMESTEPS(20000)).train(&mut agent, &mut mdp, &mut rng);
+
+	for s in 0..2 {
+		let best = agent.eval(&s, &0).max(agent.eval(&s, &1));
+		assert!((best - v_func.eval(&s)).abs() < 0.5, "Q-learning found V({}) = {}, not {}", s, best, v_func.eval(&s));
+	}
+}
+
+#[test]
+#[should_panic]
+fn transitions_over_one_rejected() {
+	small_mdp().with_transition(0, 1, 0, 0.5, 0.0);
+}
+
+#[test]
+#[should_panic]
+fn transitions_under_one_rejected_when_used() {
+	let mut mdp = small_mdp().with_transition(1, 1, 1, 0.25, 0.0).start(1);
+	mdp.reset();
+	mdp.step(&1);
+}
\ No newline at end of file
@@ -0,0 +1,194 @@
+// Checks the exact updates made by Expected SARSA and the n-step TD learners
+
+extern crate renforce as re;
+extern crate rand;
+
+use std::cell::Cell;
+
+use rand::{Rng, SeedableRng, StdRng};
+
+use re::prelude::*;
+
+use re::environment::Finite;
+
+use re::trainer::{QLearner, ExpectedSARSA, NStepQLearner, NStepSARSA};
+
+use re::util::table::QTable;
+use re::util::chooser::Uniform;
+
+// Walks from state 0 to state 3 regardless of the action taken, only rewarded on the last step
+struct Chain {
+	pos: u32,
+}
+
+impl Environment for Chain {
+	type State = Finite;
+	type Action = Finite;
+
+	fn state_space(&self) -> Finite {
+		Finite::new(4)
+	}
+	fn action_space(&self) -> Finite {
+		Finite::new(2)
+	}
+	fn step(&mut self, _: &u32) -> Observation<Finite> {
+		self.pos += 1;
+		Observation {
+			state: self.pos,
+			reward: if self.pos == 3 {1.0} else {0.0},
+			done: self.pos == 3
+		}
+	}
+	fn reset(&mut self) -> Observation<Finite> {
+		self.pos = 0;
+		Observation {
+			state: 0,
+			reward: 0.0,
+			done: false
+		}
+	}
+	fn render(&self) {
+	}
+}
+
+// Has a single state, and every step ends the episode with a reward of 1
+struct Instant;
+
+impl Environment for Instant {
+	type State = Finite;
+	type Action = Finite;
+
+	fn state_space(&self) -> Finite {
+		Finite::new(1)
+	}
+	fn action_space(&self) -> Finite {
+		Finite::new(2)
+	}
+	fn step(&mut self, _: &u32) -> Observation<Finite> {
+		Observation {
+			state: 0,
+			reward: 1.0,
+			done: true
+		}
+	}
+	fn reset(&mut self) -> Observation<Finite> {
+		Observation {
+			state: 0,
+			reward: 0.0,
+			done: false
+		}
+	}
+	fn render(&self) {
+	}
+}
+
+fn greedy_agent(q_func: QTable<Finite, Finite>) -> GreedyQAgent<Finite, Finite, QTable<Finite, Finite>> {
+	GreedyQAgent::new(q_func, Finite::new(2))
+}
+
+#[test]
+fn nstep_qlearner_propagates_reward() {
+	let mut env = Chain {pos: 0};
+	let mut rng = StdRng::from_seed(&[1]);
+
+	let mut one_step = greedy_agent(QTable::new());
+	QLearner::new(env.action_space(), 0.5, 1.0, TimePeriod::EPISODES(1)).train(&mut one_step, &mut env, &mut rng);
+	assert_eq!((one_step.eval(&1, &0), one_step.eval(&2, &0)), (0.0, 1.0));
+
+	let mut agent = greedy_agent(QTable::new());
+	let mut trainer = NStepQLearner::new(env.action_space(), 0.5, 1.0, 2, TimePeriod::EPISODES(1));
+	trainer.train(&mut agent, &mut env, &mut rng);
+	assert_eq!((agent.eval(&0, &0), agent.eval(&1, &0), agent.eval(&2, &0)), (0.0, 0.5, 1.0));
+
+	trainer.train(&mut agent, &mut env, &mut rng);
+	assert_eq!(agent.eval(&0, &0), 0.25);
+}
+
+#[test]
+fn nstep_sarsa_propagates_reward() {
+	let mut env = Chain {pos: 0};
+	let mut rng = StdRng::from_seed(&[2]);
+
+	let mut agent = greedy_agent(QTable::new());
+	let mut trainer = NStepSARSA::new(0.5, 1.0, 3, TimePeriod::EPISODES(1));
+	trainer.train(&mut agent, &mut env, &mut rng);
+	assert_eq!((agent.eval(&0, &0), agent.eval(&1, &0), agent.eval(&2, &0)), (0.25, 0.5, 1.0));
+}
+
+#[test]
+fn nstep_bootstraps_when_training_stops_early() {
+	let mut env = Chain {pos: 0};
+	let mut rng = StdRng::from_seed(&[3]);
+
+	let mut q_func = QTable::new();
+	q_func.update(&2, &1, 1.0, 1.0);
+	let mut agent = greedy_agent(q_func);
+	let mut trainer = NStepQLearner::new(env.action_space(), 0.5, 1.0, 3, TimePeriod::TIMESTEPS(2));
+	trainer.train(&mut agent, &mut env, &mut rng);
+	assert_eq!((agent.eval(&0, &0), agent.eval(&1, &0)), (0.25, 0.5));
+}
+
+#[test]
+fn nstep_does_not_bootstrap_past_terminal_state() {
+	let mut env = Instant;
+	let mut rng = StdRng::from_seed(&[4]);
+
+	let mut agent = greedy_agent(QTable::new());
+	NStepQLearner::new(env.action_space(), 0.5, 1.0, 1, TimePeriod::EPISODES(20)).train(&mut agent, &mut env, &mut rng);
+	assert_eq!(agent.eval(&0, &0), 1.0);
+
+	let mut agent = greedy_agent(QTable::new());
+	NStepSARSA::new(0.5, 1.0, 1, TimePeriod::EPISODES(20)).train(&mut agent, &mut env, &mut rng);
+	assert_eq!(agent.eval(&0, &0), 1.0);
+}
+
+#[test]
+fn expected_sarsa_averages_next_actions() {
+	let mut env = Chain {pos: 0};
+	let mut rng = StdRng::from_seed(&[4]);
+
+	let mut q_func = QTable::new();
+	q_func.update(&1, &1, 1.0, 1.0);
+	// Chooses action 1 with probability 0.75 in state 1
+	let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.5, Uniform);
+	let mut trainer = ExpectedSARSA::new(env.action_space(), 0.5, 1.0, TimePeriod::TIMESTEPS(1));
+	trainer.train(&mut agent, &mut env, &mut rng);
+
+	assert_eq!(agent.eval(&0, &0).max(agent.eval(&0, &1)), 0.375);
+}
+// Alternates between actions 0 and 1 every time it is asked for one
+#[derive(Debug)]
+struct Alternating {
+	q_func: QTable<Finite, Finite>,
+	calls: Cell<u32>,
+}
+
+impl Agent<Finite, Finite> for Alternating {
+	fn get_action<R: Rng>(&self, _: &u32, _: &mut R) -> u32 {
+		let calls = self.calls.get();
+		self.calls.set(calls + 1);
+		calls % 2
+	}
+}
+
+impl QFunction<Finite, Finite> for Alternating {
+	fn eval(&self, state: &u32, action: &u32) -> f64 {
+		self.q_func.eval(state, action)
+	}
+	fn update(&mut self, state: &u32, action: &u32, new_val: f64, alpha: f64) {
+		self.q_func.update(state, action, new_val, alpha)
+	}
+}
+
+#[test]
+fn nstep_sarsa_bootstraps_from_action_taken() {
+	let mut env = Chain {pos: 0};
+	let mut rng = StdRng::from_seed(&[5]);
+
+	let mut q_func = QTable::new();
+	q_func.update(&1, &1, 1.0, 1.0);
+	// Takes action 0 in state 0 and action 1 in state 1, so asking again in state 1 would give 0
+	let mut agent = Alternating {q_func: q_func, calls: Cell::new(0)};
+	NStepSARSA::new(0.5, 1.0, 1, TimePeriod::TIMESTEPS(2)).train(&mut agent, &mut env, &mut rng);
+	assert_eq!(agent.eval(&0, &0), 0.5);
+}
\ No newline at end of file
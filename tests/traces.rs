@@ -0,0 +1,106 @@
+// Checks the eligibility trace trainers on hand-picked transitions
+
+extern crate renforce as re;
+extern crate rand;
+
+use rand::{SeedableRng, StdRng};
+
+use re::prelude::*;
+
+use re::environment::Finite;
+
+use re::trainer::{SARSALambda, WatkinsQLambda};
+
+use re::util::{TracedQFunction, TraceType};
+use re::util::table::QTable;
+use re::util::approx::QLinear;
+
+type TableAgent = GreedyQAgent<Finite, Finite, QTable<Finite, Finite>>;
+
+// Walks from state 0 to state 3, only rewarded on the last step
+fn chain(actions: &[u32]) -> Vec<Transition<Finite, Finite>> {
+	(0..3).map(|s| (s, actions[s as usize], if s == 2 {1.0} else {0.0}, s + 1)).collect()
+}
+
+fn train<L: OnlineTrainer<Finite, Finite, TableAgent>>(trainer: &mut L, agent: &mut TableAgent, transitions: Vec<Transition<Finite, Finite>>) {
+	let mut rng = StdRng::from_seed(&[1]);
+	for t in transitions {
+		trainer.train_step(agent, t, &mut rng);
+	}
+	trainer.finish_episode(agent, true, &mut rng);
+}
+
+#[test]
+fn sarsa_lambda_credits_whole_episode() {
+	let mut agent = GreedyQAgent::new(QTable::new(), Finite::new(2));
+	let mut trainer = SARSALambda::new(0.5, 1.0, 1.0, TimePeriod::EPISODES(1));
+	train(&mut trainer, &mut agent, chain(&[0, 0, 0]));
+
+	assert_eq!((agent.eval(&0, &0), agent.eval(&1, &0), agent.eval(&2, &0)), (0.25, 0.5, 1.0));
+}
+
+#[test]
+fn trace_types_differ_on_revisits() {
+	// The same pair is visited twice before the reward arrives
+	let transitions = vec![(0, 0, 0.0, 0), (0, 0, 1.0, 1)];
+	let value = |trace_type| {
+		let mut agent = GreedyQAgent::new(QTable::new(), Finite::new(2));
+		let mut trainer = SARSALambda::new(1.0, 0.5, 1.0, TimePeriod::EPISODES(1)).trace_type(trace_type);
+		train(&mut trainer, &mut agent, transitions.clone());
+		agent.eval(&0, &0)
+	};
+
+	assert_eq!(value(TraceType::Accumulating), 1.0);
+	assert_eq!(value(TraceType::Replacing), 0.5);
+	assert_eq!(value(TraceType::Dutch), 0.75);
+}
+
+#[test]
+fn watkins_cuts_traces_after_exploring() {
+	let mut q_func = QTable::new();
+	q_func.update(&1, &0, 0.1, 1.0);
+	let mut agent = GreedyQAgent::new(q_func, Finite::new(2));
+	let mut trainer = WatkinsQLambda::new(Finite::new(2), 0.5, 1.0, 1.0, TimePeriod::EPISODES(1));
+
+	// Action 1 is exploratory in state 1, so state 0 gets no credit for the final reward
+	train(&mut trainer, &mut agent, chain(&[0, 1, 0]));
+	assert_eq!((agent.eval(&0, &0), agent.eval(&1, &1), agent.eval(&2, &0)), (0.05, 0.5, 1.0));
+
+	// Acting greedily lets the reward flow all the way back
+	train(&mut trainer, &mut agent, chain(&[0, 0, 0]));
+	assert!(agent.eval(&0, &0) > 0.05);
+}
+
+#[test]
+fn linear_traces_match_tabular() {
+	// With a single state and no features, QLinear is just a table
+	let transitions: Vec<Transition<(), Finite>> = vec![((), 0, 1.0, ()), ((), 1, -1.0, ()), ((), 0, 0.5, ()), ((), 0, 2.0, ())];
+	let mut rng = StdRng::from_seed(&[2]);
+
+	for &trace_type in &[TraceType::Accumulating, TraceType::Replacing, TraceType::Dutch] {
+		let mut table: GreedyQAgent<(), Finite, QTable<(), Finite>> = GreedyQAgent::new(QTable::new(), Finite::new(2));
+		let mut linear: GreedyQAgent<(), Finite, QLinear<f64, (), Finite>> = GreedyQAgent::new(QLinear::default(&Finite::new(2)), Finite::new(2));
+		let mut table_trainer = SARSALambda::new(0.9, 0.3, 0.8, TimePeriod::EPISODES(1)).trace_type(trace_type);
+		let mut linear_trainer = SARSALambda::new(0.9, 0.3, 0.8, TimePeriod::EPISODES(1)).trace_type(trace_type);
+
+		for t in &transitions {
+			table_trainer.train_step(&mut table, t.clone(), &mut rng);
+			linear_trainer.train_step(&mut linear, t.clone(), &mut rng);
+		}
+		for a in 0..2 {
+			assert!((table.eval(&(), &a) - linear.eval(&(), &a)).abs() < 1e-10, "{:?} traces differ", trace_type);
+		}
+	}
+}
+
+#[test]
+fn decayed_traces_are_forgotten() {
+	let q_func: QTable<Finite, Finite> = QTable::new();
+	let mut trace = q_func.new_trace();
+	q_func.visit(&mut trace, &0, &0, TraceType::Replacing, 0.1);
+	q_func.visit(&mut trace, &1, &0, TraceType::Replacing, 0.1);
+	assert_eq!(trace.len(), 2);
+
+	q_func.decay_trace(&mut trace, 1e-20);
+	assert!(trace.is_empty());
+}
\ No newline at end of file